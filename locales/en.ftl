@@ -0,0 +1,507 @@
+## English messages
+
+## Days and months
+
+day-monday = Monday
+day-tuesday = Tuesday
+day-wednesday = Wednesday
+day-thursday = Thursday
+day-friday = Friday
+day-saturday = Saturday
+day-sunday = Sunday
+
+month-january = January
+month-february = February
+month-march = March
+month-april = April
+month-may = May
+month-june = June
+month-july = July
+month-august = August
+month-september = September
+month-october = October
+month-november = November
+month-december = December
+
+month-short-january = Jan
+month-short-february = Feb
+month-short-march = Mar
+month-short-april = Apr
+month-short-may = May
+month-short-june = Jun
+month-short-july = Jul
+month-short-august = Aug
+month-short-september = Sep
+month-short-october = Oct
+month-short-november = Nov
+month-short-december = Dec
+
+## Errors
+
+error-not-found = The address does not exist
+error-unprocessable = The value contained in the address is not correct
+error-undefined = Unidentified error
+error-internal = An error occurred, try again later
+error-login-again = Log in again
+error-not-authenticated = You need to log in to see this page
+error-not-authenticated-administrator = You need to log in as an administrator to see this page
+error-wrong-card-id = The card number you entered is not yours
+error-invalid-csrf-token = The request is not valid, reload the page and try again
+error-invalid-invitation = The invitation link is not valid or has expired
+error-unknown-volunteer = No volunteer has this card
+
+## Menu
+
+menu-insert-shifts = Book shifts
+menu-personal-shifts = Your shifts
+menu-visualize-shifts = View shifts
+menu-account = Profile
+menu-logout = Log out
+menu-volunteers = Volunteers
+menu-invitations = Invitations
+menu-change-requests = Change requests
+menu-assignments = Assign shifts
+menu-planning = Planning
+menu-statistics = Statistics
+menu-export = Export
+menu-archive = Archive
+menu-backup = Backup
+menu-api = API
+menu-webhooks = Webhooks
+menu-locked-logins = Locked logins
+menu-sessions = Devices
+
+## Forms
+
+card-id-missing = Enter a card number without leading and trailing spaces.
+card-id-invalid = Enter a card number as in the example.
+card-id-underflow = The card number must be positive.
+card-id-overflow = The card number must be less than or equal to 100.
+button-login = Log in
+button-save-shifts = Save shifts
+button-add-shift = Add shift
+label-choose-week = Choose week
+label-choose-day = Choose day
+label-choose-task = Choose task
+label-choose-location = Choose location
+
+## Login
+
+login-description = Enter your details
+login-card-id = Card number (without initial letters)
+login-card-id-placeholder = e.g. 001
+login-surname = Surname
+login-surname-placeholder = e.g. Smith
+login-surname-empty = Enter a surname.
+login-card-id-missing = The card number "{ $card_id }" does not exist
+login-card-id-disabled = The card number "{ $card_id }" is disabled
+login-surname-missing = The surname "{ $surname }" does not exist
+login-wrong-surname = The surname "{ $surname }" is not associated with the card number "{ $card_id }"
+login-wrong-credentials = Wrong credentials
+login-code-expired = The login code has expired, log in again
+login-locked = Too many failed attempts, try again in { $minutes } { $minutes ->
+        [one] minute
+       *[other] minutes
+    }
+card-disabled = Card Disabled
+administration-description = Log in as coordinator
+administration-password = Password
+administration-password-empty = Enter the password (at least 8 characters)
+administration-wrong-password = The password is not correct
+code-description = Enter the code sent to { $recipient }
+code-text = Login code
+code-placeholder = e.g. 123456
+code-empty = Enter the { $digits }-digit code you received
+code-wrong = The code is not correct
+
+## Cookies
+
+cookie-title = This website uses cookies!
+cookie-link = What are cookies?
+cookie-accept = I accept
+cookie-decline = I decline
+cookie-policy-title = Cookie Policy
+cookie-policy-intro = This page provides information about the technologies used by <a href="{ $website }" target="_blank">{ $website }</a>.
+cookie-policy-usage-title = 1. How we use cookies
+cookie-policy-usage = Our Website uses cookies to distinguish a user from other users of the Website. This allows us to provide a user with a correct use of the Website while improving the Website and the experience offered to users.
+cookie-policy-accept = By clicking the <strong>Accept</strong> button, inside the popup containing the link to this page, the cookie conditions described below in this page are automatically accepted.
+cookie-policy-decline =
+    By clicking the <strong>Decline</strong> button, the use of cookies is not allowed.
+    Cookies can be disabled or blocked through the browser as explained below in this page.
+    However, blocking or disabling cookies has a negative impact on the use of the Website.
+cookie-policy-definition-title = 2. What is a cookie
+cookie-policy-definition = A cookie is a small file made of letters and numbers which is saved in the browser or on a storage device of a computer to check whether a user has previously visited a website or to locally save a small amount of information.
+cookie-policy-kinds = There are two kinds of cookies: persistent and session cookies. Session cookies expire at the end of a browser session, that is as soon as the browser is closed, while persistent cookies stay saved on the computer across browser sessions until they expire or are manually deleted by a user.
+cookie-policy-used-title = 3. Cookies in use
+cookie-policy-used = We use the following kinds of cookies:
+cookie-policy-necessary-title = Strictly<br>necessary cookies
+cookie-policy-necessary = These cookies are needed by the features of the Website. They are essential for users to correctly browse the Website and use its features, including the login to our services and the choice of the language.
+cookie-policy-technical-title = Technical cookies
+cookie-policy-technical = These cookies are used to keep the session of a user after logging in
+cookie-policy-management-title = 4. How can cookies be managed?
+cookie-policy-management = Cookies can be automatically refused, or the existing ones deleted, through the browser settings. More information about managing cookies in some browsers is available at the following links:
+cookie-policy-other-browsers = For browsers which are not in the list above, visit the websites of those browsers.
+cookie-policy-blocked = However, if a browser is set to block all cookies, some parts of the Website might not work correctly.
+cookie-policy-contacts-title = 5. Contacts
+cookie-policy-contacts = You can contact us at this email:
+
+## Volunteer shifts
+
+shifts-heading = Here are your shifts
+shifts-empty = No shifts booked!
+shifts-insert = Book new shifts
+shifts-delete = Cancel
+shifts-certificate = Hours certificate
+shifts-rejected = Some shifts have not been booked:
+
+## Shifts booking
+
+shifts-manager-heading = Book one or more shifts
+shifts-manager-greeting = Hello { $name }!
+shifts-manager-shift = Shift { $number }
+shifts-manager-explain-dates = Choose a date...
+shifts-manager-explain-tasks = Choose a task...
+shifts-manager-invalid-task = The task does not exist
+shifts-manager-invalid-location = The location does not exist or does not offer the task
+shifts-manager-invalid-date = Shifts can be booked only from today until Saturday of next week
+shifts-manager-already-booked = The shift has already been booked
+shifts-manager-full = The location has no more places for the task
+shifts-manager-unknown-volunteer = The volunteer does not exist
+
+## Shifts visualizer and roster
+
+visualizer-print = Print week
+visualizer-capacity = Places: { $capacity }
+print-empty-slot = Uncovered
+print-others = + { $count } more
+print-generated-at = Generated on { $date }
+
+## Periods and dates
+
+period-from = From
+period-to = To
+error-dates = Error computing the dates
+error-invalid-date = The date entered is not valid
+error-date-order = The start date must precede the end date
+
+## Certificate
+
+certificate-heading = Service hours certificate
+certificate-title = Volunteer service certificate
+certificate-print = Generate certificate
+certificate-shifts = Back to shifts
+certificate-empty = No shifts served in the chosen period
+certificate-date = Date
+certificate-task = Task
+certificate-hours = Hours
+certificate-duration = Duration
+certificate-total = Total
+certificate-place = Place and date
+certificate-signature = Coordinator signature
+certificate-duration-words = { $hours ->
+        [one] 1 hour
+       *[other] { $hours } hours
+    }{ $minutes ->
+        [0] {""}
+       *[other] {" "}and { $minutes } minutes
+    }
+certificate-statement = This is to certify that { $name } { $surname }, card no. { $card_id }, served as a volunteer at { $association } from { $from } to { $to }, for a total of { $duration }, in the shifts listed below.
+
+## Export
+
+export-heading = Export shifts
+export-format = Format
+export-download = Download
+export-sheet = Shifts
+export-date = Date
+export-task = Task
+export-hours = Hours
+export-card-id = Card
+export-surname = Surname
+export-name = Name
+export-location = Location
+export-address = Address
+
+## Statistics
+
+statistics-heading = Statistics
+statistics-weeks = Weeks of inactivity
+statistics-filter = Filter
+statistics-volunteers = Volunteers participation
+statistics-tasks = Tasks coverage
+statistics-inactive = Inactive volunteers
+statistics-download = Download CSV
+statistics-empty-shifts = No shifts in the chosen period!
+statistics-empty-inactive = No inactive volunteers!
+statistics-never = Never
+statistics-card-id = Card
+statistics-surname = Surname
+statistics-name = Name
+statistics-shifts = Shifts
+statistics-hours = Hours
+statistics-last-shift = Last shift
+statistics-task = Task
+statistics-task-hours = Hours
+statistics-covered-days = Covered days
+statistics-coverage = Coverage
+statistics-fill-rate = Coverage %
+statistics-average = Average volunteers
+
+## Webhooks
+
+webhooks-heading = Webhooks
+webhooks-empty = No webhooks configured!
+webhooks-url = Address receiving the events
+webhooks-create = Add webhook
+webhooks-secret = Secret to verify the signature
+webhooks-delete = Delete
+webhooks-log = Recent deliveries
+webhooks-log-empty = No deliveries!
+webhooks-retry = Retry
+webhooks-pending = Pending
+webhooks-delivered = Delivered
+webhooks-failed = Failed
+webhooks-invalid-url = Invalid webhook address
+webhooks-event = Event
+webhooks-address = Address
+webhooks-status = Status
+webhooks-attempts = Attempts
+webhooks-response = Response
+webhooks-created = Created
+webhooks-last-attempt = Last attempt
+
+## Archive
+
+archive-heading = Shifts archive
+archive-archived = Past shifts in the archive
+archive-oldest = Oldest shift
+archive-last-purge = Last cleanup
+archive-never = Never
+archive-retention = Keep past shifts for
+archive-forever = Forever
+archive-save = Save
+archive-purge = Older shifts are automatically deleted once a day
+archive-weeks = { $weeks ->
+        [one] 1 week
+       *[other] { $weeks } weeks
+    }
+archive-years = { $years ->
+        [one] 1 year
+       *[other] { $years } years
+    }
+archive-months = { $months ->
+        [one] 1 month
+       *[other] { $months } months
+    }
+archive-invalid-retention = Invalid retention period
+
+## Backup
+
+backup-heading = Backup and restore
+backup-download = Download backup
+backup-archive = Backup archive
+backup-mode = Restore mode
+backup-replace = Replace all data
+backup-merge = Merge with existing data
+backup-dry-run = Simulate without saving
+backup-restore = Restore
+backup-dry-run-done = Simulation completed, no data has been changed
+backup-restore-done = Restore completed
+backup-not-archive = The uploaded file is not a backup archive
+backup-unsupported-version = Archive version { $version } is not supported, the current version is { $current }
+backup-invalid-archive = The backup archive is not valid: { $error }
+backup-invalid-task = The shift of { $date } has an invalid task
+backup-invalid-location = The shift of { $date } has an invalid location
+backup-missing-card = The shift of { $date } belongs to card { $card_id }, which does not exist
+backup-note-missing-card = The note of { $author } is about card { $card_id }, which does not exist
+backup-override-missing-card = The changed data of card { $card_id } belong to a volunteer who does not exist
+backup-account-missing-card = The preferences or the change requests of card { $card_id } belong to a volunteer who does not exist
+backup-no-locations = The backup archive does not contain any location
+backup-invalid-location-task = Location { $location } offers task { $task }, which does not exist
+backup-volunteers-added = Volunteers added
+backup-volunteers-updated = Volunteers updated
+backup-volunteers-removed = Volunteers removed
+backup-shifts-added = Shifts added
+backup-shifts-skipped = Shifts already present
+backup-shifts-removed = Shifts removed
+backup-settings-restored = Settings restored
+backup-notes-added = Notes added
+backup-overrides-restored = Changed data restored
+backup-preferences-restored = Preferences restored
+backup-change-requests-restored = Change requests restored
+backup-tasks-restored = Tasks restored
+backup-locations-restored = Locations restored
+
+## Volunteers
+
+volunteers-shifts-manager = Add shifts
+volunteers-shifts = Delete shifts
+volunteers-update = Update volunteers
+volunteers-profile = Volunteer profile
+volunteers-invitation = Invite
+volunteers-certificate = Hours certificate
+volunteers-single-use = Single-use link
+volunteers-time-limited = Link valid for { $days } days
+volunteers-search = Search by card, surname, name or phone
+volunteers-status-all = All volunteers
+volunteers-status-enabled = Enabled only
+volunteers-status-disabled = Disabled only
+volunteers-shifts-all = With or without shifts
+volunteers-shifts-with = With shifts this week
+volunteers-shifts-without = Without shifts this week
+volunteers-sort-card = Sort by card
+volunteers-sort-surname = Sort by surname
+volunteers-sort-name = Sort by name
+volunteers-filter = Filter
+volunteers-found = { $count ->
+        [one] One volunteer found
+       *[other] { $count } volunteers found
+    }
+volunteers-empty = No volunteer matches the search
+volunteers-previous = Previous
+volunteers-next = Next
+
+## API tokens
+
+api-tokens-heading = API access
+api-tokens-empty = No tokens created!
+api-tokens-name = Token name
+api-tokens-scope = Permissions
+api-tokens-public-scope = Read only, without phone numbers
+api-tokens-administration-scope = Coordinator
+api-tokens-create = Create token
+api-tokens-new = Copy the token now, it will not be shown again:
+api-tokens-never-used = Never used
+api-tokens-revoke = Revoke
+api-invalid-page = page must be positive and per_page must be between 1 and { $max }
+api-invalid-date = The date "{ $date }" is not valid
+api-administration-only = Only coordinator tokens can change shifts
+api-unknown-volunteer = The volunteer does not exist
+api-unknown-shift = The shift does not exist or has already passed
+api-bad-request = The request is not valid
+api-unauthorized = The token is not valid
+api-not-found = The resource does not exist
+api-unprocessable = The content of the request is not correct
+api-error = An error occurred
+
+## Locked logins
+
+locked-logins-heading = Locked logins
+locked-logins-empty = No locked logins!
+locked-logins-unlock = Unlock
+locked-logins-ip = IP address
+locked-logins-volunteer = Volunteer card
+locked-logins-administration = Coordinator card
+locked-logins-unknown = Unknown
+
+## Invitations
+
+invitations-heading = Invitations
+invitations-empty = No pending invitations!
+invitations-revoke = Revoke
+invitations-single-use = Single use
+invitations-time-limited = Reusable until expiry
+invitations-disabled-volunteer = The volunteer is disabled
+invitations-accept-heading = You have been invited to book your shifts
+invitations-accept = Log in
+
+## Connected devices
+
+sessions-heading = Connected devices
+sessions-empty = No connected devices!
+sessions-revoke = Log out
+sessions-current = This device
+sessions-administration-role = Coordinator
+sessions-volunteer-role = Volunteer
+
+## Shifts assignment
+
+assignments-heading = Assign shifts
+assignments-search = Search volunteers
+assignments-assign = Assign to the chosen volunteers
+assignments-done = Assignment completed
+assignments-no-volunteers = Choose at least one volunteer
+assignments-booked = Booked shifts
+assignments-already-booked = Shift already booked
+assignments-disabled = Card disabled
+assignments-full = No more places
+assignments-rejected = Shift not booked
+
+## Planning
+
+planning-heading = Weekly planning
+planning-volunteers = Available volunteers
+planning-explain = Drag a volunteer into a cell to book the shift, or move it to another cell or to the removal area.
+planning-remove = Drag here to remove the shift
+
+## Volunteer profile
+
+profile-contacts = Contacts
+profile-call = Call
+profile-send-message = Send SMS
+profile-actions = Actions
+profile-local-data = Data changed by coordinators
+profile-edit = Volunteer data
+profile-surname = Surname
+profile-name = Name
+profile-phone = Phone
+profile-disabled = Card disabled
+profile-save = Save
+profile-source = Value in the file: { $value }
+profile-enabled = Card enabled
+profile-restore = Restore the file data
+profile-email = Email
+profile-channels = Preferred contacts
+profile-no-channels = No preferred contacts
+profile-language = Language
+profile-change-request = The volunteer asked to change their data
+profile-upcoming = Upcoming shifts
+profile-past = Served shifts
+profile-no-shifts = No shifts
+profile-skills = Served tasks
+profile-no-skills = No served tasks
+profile-notes = Coordinators notes
+profile-notes-private = Notes are visible only to coordinators
+profile-add-note = Add note
+profile-delete-note = Delete note
+profile-no-notes = No notes
+
+## Volunteer profile
+
+account-heading = Your profile
+account-data = Saved data
+account-card-id = Card
+account-surname = Surname
+account-name = Name
+account-phone = Phone
+account-change = Change your data
+account-change-explain = Changes are applied after a coordinator approves them
+account-send-request = Send request
+account-pending = Request sent on { $date }, waiting for approval:
+account-withdraw = Withdraw request
+account-preferences = Contact preferences
+account-email = Email
+account-channels = How you want to be contacted
+account-notify-email = Email
+account-notify-sms = SMS
+account-language = Language
+account-browser-language = Browser language
+account-save = Save
+account-preferences-saved = Preferences saved
+account-request-sent = Request sent to coordinators
+account-request-withdrawn = Request withdrawn
+account-invalid-email = Invalid email address
+
+## Change requests
+
+change-requests-heading = Change requests
+change-requests-explain = Data volunteers asked to change. Approved data are kept when volunteers are updated from the file.
+change-requests-empty = No requests
+change-requests-surname = Surname
+change-requests-name = Name
+change-requests-phone = Phone
+change-requests-current = Current
+change-requests-requested = Requested
+change-requests-approve = Approve
+change-requests-reject = Reject
+change-requests-profile = Profile
@@ -0,0 +1,507 @@
+## Messaggi in italiano
+
+## Giorni e mesi
+
+day-monday = Lunedì
+day-tuesday = Martedì
+day-wednesday = Mercoledì
+day-thursday = Giovedì
+day-friday = Venerdì
+day-saturday = Sabato
+day-sunday = Domenica
+
+month-january = Gennaio
+month-february = Febbraio
+month-march = Marzo
+month-april = Aprile
+month-may = Maggio
+month-june = Giugno
+month-july = Luglio
+month-august = Agosto
+month-september = Settembre
+month-october = Ottobre
+month-november = Novembre
+month-december = Dicembre
+
+month-short-january = Gen
+month-short-february = Feb
+month-short-march = Mar
+month-short-april = Apr
+month-short-may = Mag
+month-short-june = Giu
+month-short-july = Lug
+month-short-august = Ago
+month-short-september = Set
+month-short-october = Ott
+month-short-november = Nov
+month-short-december = Dic
+
+## Errori
+
+error-not-found = L'indirizzo non esiste
+error-unprocessable = Il valore presente nell'indirizzo non è corretto
+error-undefined = Errore non identificato
+error-internal = Si è verificato un errore, riprova più tardi
+error-login-again = Accedi di nuovo
+error-not-authenticated = Bisogna autenticarsi per vedere questa pagina
+error-not-authenticated-administrator = Bisogna autenticarsi come amministratori per vedere questa pagina
+error-wrong-card-id = Il numero di tessera inserito non è il tuo
+error-invalid-csrf-token = La richiesta non è valida, ricarica la pagina e riprova
+error-invalid-invitation = Il link di invito non è valido oppure è scaduto
+error-unknown-volunteer = Nessun volontario ha questa tessera
+
+## Menu
+
+menu-insert-shifts = Inserisci turni
+menu-personal-shifts = I tuoi turni
+menu-visualize-shifts = Vedi turni
+menu-account = Profilo
+menu-logout = Esci
+menu-volunteers = Volontari
+menu-invitations = Inviti
+menu-change-requests = Richieste di modifica
+menu-assignments = Assegna turni
+menu-planning = Pianificazione
+menu-statistics = Statistiche
+menu-export = Esporta
+menu-archive = Archivio
+menu-backup = Backup
+menu-api = API
+menu-webhooks = Webhook
+menu-locked-logins = Accessi bloccati
+menu-sessions = Dispositivi
+
+## Moduli
+
+card-id-missing = Inserire un numero di tessera senza spazi iniziali e finali.
+card-id-invalid = Inserire un numero di tessera come nell'esempio.
+card-id-underflow = Il numero di tessera deve essere positivo.
+card-id-overflow = Il numero di tessera deve essere inferiore o uguale a 100.
+button-login = Accedi
+button-save-shifts = Salva turni
+button-add-shift = Aggiungi turno
+label-choose-week = Scegli settimana
+label-choose-day = Scegli giorno
+label-choose-task = Scegli mansione
+label-choose-location = Scegli sede
+
+## Accesso
+
+login-description = Inserisci i tuoi dati
+login-card-id = Numero tessera (senza sigle iniziali)
+login-card-id-placeholder = es. 001
+login-surname = Cognome
+login-surname-placeholder = es. Rossi
+login-surname-empty = Inserire un cognome.
+login-card-id-missing = Il numero di tessera "{ $card_id }" non esiste
+login-card-id-disabled = Il numero di tessera "{ $card_id }" è disabilitato
+login-surname-missing = Il cognome "{ $surname }" non esiste
+login-wrong-surname = Il cognome "{ $surname }" non è associato al numero di tessera "{ $card_id }"
+login-wrong-credentials = Credenziali errate
+login-code-expired = Il codice di accesso è scaduto, accedi di nuovo
+login-locked = Troppi tentativi errati, riprova tra { $minutes } { $minutes ->
+        [one] minuto
+       *[other] minuti
+    }
+card-disabled = Tessera Disabilitata
+administration-description = Autenticati come referente
+administration-password = Password
+administration-password-empty = Inserire la password (almeno 8 caratteri)
+administration-wrong-password = La password inserita non è corretta
+code-description = Inserisci il codice inviato a { $recipient }
+code-text = Codice di accesso
+code-placeholder = es. 123456
+code-empty = Inserire il codice di { $digits } cifre ricevuto
+code-wrong = Il codice inserito non è corretto
+
+## Cookie
+
+cookie-title = Questo sito usa i cookie!
+cookie-link = Ma cosa sono i cookie?
+cookie-accept = Accetto
+cookie-decline = Declino
+cookie-policy-title = Politica Cookie
+cookie-policy-intro = Questa pagina fornisce una serie di informazioni sulle tecnologie usate da <a href="{ $website }" target="_blank">{ $website }</a>.
+cookie-policy-usage-title = 1. Come usiamo i cookie
+cookie-policy-usage = Il nostro Sito usa i cookie per distinguere un utente da un altro utente del Sito. Questo consente di fornire ad un utente un corretto utilizzo del Sito migliorando al tempo stesso il Sito e l'esperienza fornita ad un utente.
+cookie-policy-accept = Cliccando il bottone <strong>Accetta</strong>, presente all'interno del popup contenente il collegamento che porta a questa pagina, vengono automaticamente accettate le condizioni sui cookie descritte successivamente in questa pagina.
+cookie-policy-decline =
+    Cliccando il bottone <strong>Declina</strong>, non viene consentito l'uso dei cookie.
+    I cookie possono essere disabilitati o bloccati tramite browser come spiegato successivamente in questa pagina.
+    Tuttavia, bloccare o disabilitare i cookie comporta ad un impatto negativo sull'utilizzo del Sito.
+cookie-policy-definition-title = 2. Cos'è un cookie
+cookie-policy-definition = Un cookie è un piccolo file composto da lettere e numeri che viene salvato nel browser o su un dispositivo di memoria fisica di un computer per verificare se un utente ha precedentemente visitato un sito oppure per salvare localmente una piccola quantità di informazioni.
+cookie-policy-kinds = Esistono due tipi di cookie: persistenti e di sessione. I cookie di sessione espirano al termine di una sessione del browser, cioè non appena si esce dal browser, mentre i cookie persistenti rimangono invece salvati sul computer durante le varie sessioni del browser fino a quando non espirano o vengono cancellati manualmente da un utente.
+cookie-policy-used-title = 3. Cookie in uso
+cookie-policy-used = Usiamo i seguenti tipi di cookie:
+cookie-policy-necessary-title = Cookie<br>strettamente necessari
+cookie-policy-necessary = Questi cookie sono necessari per le varie funzionalità del Sito. Questi cookie sono essenziali agli utenti per navigare correttamente all'interno del Sito ed usare le sue funzionalità, inclusa l'autenticazione ai nostri servizi e la scelta della lingua.
+cookie-policy-technical-title = Cookie tecnici
+cookie-policy-technical = Questi cookie sono usati per mantenere la sessione di un utente quando si autentica
+cookie-policy-management-title = 4. Come si possono gestire i cookie?
+cookie-policy-management = È possibile rifiutare automaticamente l'accetazione dei cookie o cancellare quelli già presenti attraverso le impostazioni del browser. Maggiori informazioni sulla gestione dei cookie in alcuni browser ai seguenti collegamenti:
+cookie-policy-other-browsers = Per informazioni relative ai broswer non presenti nella lista soprastante, visitare i siti dei browser ricercati.
+cookie-policy-blocked = Tuttavia, se si imposta un broswer in modo tale da bloccare tutti i cookie, alcune parti del Sito potrebbero non funzionare correttamente.
+cookie-policy-contacts-title = 5. Contatti
+cookie-policy-contacts = Ci puoi contattare a questa email:
+
+## Turni del volontario
+
+shifts-heading = Ecco i tuoi turni
+shifts-empty = Nessun turno inserito!
+shifts-insert = Inserisci nuovi turni
+shifts-delete = Cancella
+shifts-certificate = Attestato ore
+shifts-rejected = Alcuni turni non sono stati inseriti:
+
+## Inserimento turni
+
+shifts-manager-heading = Inserisci uno o più turni
+shifts-manager-greeting = Ciao { $name }!
+shifts-manager-shift = Turno { $number }
+shifts-manager-explain-dates = Inserisci data...
+shifts-manager-explain-tasks = Inserisci mansione...
+shifts-manager-invalid-task = La mansione non esiste
+shifts-manager-invalid-location = La sede non esiste o non offre la mansione
+shifts-manager-invalid-date = Si possono inserire solo turni da oggi fino al sabato della prossima settimana
+shifts-manager-already-booked = Il turno è già stato inserito
+shifts-manager-full = La sede non ha più posti per la mansione
+shifts-manager-unknown-volunteer = Il volontario non esiste
+
+## Visualizzazione e stampa dei turni
+
+visualizer-print = Stampa settimana
+visualizer-capacity = Posti: { $capacity }
+print-empty-slot = Da coprire
+print-others = + altri { $count }
+print-generated-at = Generato il { $date }
+
+## Periodi e date
+
+period-from = Dal giorno
+period-to = Al giorno
+error-dates = Errore nel calcolo delle date
+error-invalid-date = La data inserita non è valida
+error-date-order = La data di inizio deve precedere la data di fine
+
+## Attestato
+
+certificate-heading = Attestato ore di servizio
+certificate-title = Attestato di servizio volontario
+certificate-print = Genera attestato
+certificate-shifts = Torna ai turni
+certificate-empty = Nessun turno svolto nel periodo scelto
+certificate-date = Data
+certificate-task = Mansione
+certificate-hours = Orario
+certificate-duration = Durata
+certificate-total = Totale
+certificate-place = Luogo e data
+certificate-signature = Firma del referente
+certificate-duration-words = { $hours ->
+        [one] 1 ora
+       *[other] { $hours } ore
+    }{ $minutes ->
+        [0] {""}
+       *[other] {" "}e { $minutes } minuti
+    }
+certificate-statement = Si attesta che { $name } { $surname }, tessera n. { $card_id }, ha prestato servizio volontario presso { $association } dal { $from } al { $to }, per un totale di { $duration }, nei turni elencati di seguito.
+
+## Esportazione
+
+export-heading = Esporta turni
+export-format = Formato
+export-download = Scarica
+export-sheet = Turni
+export-date = Data
+export-task = Mansione
+export-hours = Orario
+export-card-id = Tessera
+export-surname = Cognome
+export-name = Nome
+export-location = Sede
+export-address = Indirizzo
+
+## Statistiche
+
+statistics-heading = Statistiche
+statistics-weeks = Settimane di inattività
+statistics-filter = Filtra
+statistics-volunteers = Partecipazione dei volontari
+statistics-tasks = Copertura delle mansioni
+statistics-inactive = Volontari inattivi
+statistics-download = Scarica CSV
+statistics-empty-shifts = Nessun turno nel periodo scelto!
+statistics-empty-inactive = Nessun volontario inattivo!
+statistics-never = Mai
+statistics-card-id = Tessera
+statistics-surname = Cognome
+statistics-name = Nome
+statistics-shifts = Turni
+statistics-hours = Ore
+statistics-last-shift = Ultimo turno
+statistics-task = Mansione
+statistics-task-hours = Orario
+statistics-covered-days = Giorni coperti
+statistics-coverage = Copertura
+statistics-fill-rate = Copertura %
+statistics-average = Media volontari
+
+## Webhook
+
+webhooks-heading = Webhook
+webhooks-empty = Nessun webhook configurato!
+webhooks-url = Indirizzo che riceve gli eventi
+webhooks-create = Aggiungi webhook
+webhooks-secret = Segreto per verificare la firma
+webhooks-delete = Elimina
+webhooks-log = Consegne recenti
+webhooks-log-empty = Nessuna consegna!
+webhooks-retry = Riprova
+webhooks-pending = In attesa
+webhooks-delivered = Consegnato
+webhooks-failed = Fallito
+webhooks-invalid-url = Indirizzo del webhook non valido
+webhooks-event = Evento
+webhooks-address = Indirizzo
+webhooks-status = Stato
+webhooks-attempts = Tentativi
+webhooks-response = Risposta
+webhooks-created = Creato
+webhooks-last-attempt = Ultimo tentativo
+
+## Archivio
+
+archive-heading = Archivio turni
+archive-archived = Turni passati in archivio
+archive-oldest = Turno più vecchio
+archive-last-purge = Ultima pulizia
+archive-never = Mai
+archive-retention = Conserva i turni passati per
+archive-forever = Sempre
+archive-save = Salva
+archive-purge = I turni più vecchi vengono eliminati automaticamente una volta al giorno
+archive-weeks = { $weeks ->
+        [one] 1 settimana
+       *[other] { $weeks } settimane
+    }
+archive-years = { $years ->
+        [one] 1 anno
+       *[other] { $years } anni
+    }
+archive-months = { $months ->
+        [one] 1 mese
+       *[other] { $months } mesi
+    }
+archive-invalid-retention = Periodo di conservazione non valido
+
+## Backup
+
+backup-heading = Backup e ripristino
+backup-download = Scarica backup
+backup-archive = Archivio di backup
+backup-mode = Modalità di ripristino
+backup-replace = Sostituisci tutti i dati
+backup-merge = Unisci ai dati presenti
+backup-dry-run = Simula senza salvare
+backup-restore = Ripristina
+backup-dry-run-done = Simulazione completata, nessun dato è stato modificato
+backup-restore-done = Ripristino completato
+backup-not-archive = Il file caricato non è un archivio di backup
+backup-unsupported-version = La versione { $version } dell'archivio non è supportata, la versione attuale è { $current }
+backup-invalid-archive = L'archivio di backup non è valido: { $error }
+backup-invalid-task = Il turno del { $date } ha una mansione non valida
+backup-invalid-location = Il turno del { $date } ha una sede non valida
+backup-missing-card = Il turno del { $date } appartiene alla tessera { $card_id } che non esiste
+backup-note-missing-card = La nota di { $author } riguarda la tessera { $card_id } che non esiste
+backup-override-missing-card = I dati modificati della tessera { $card_id } riguardano un volontario che non esiste
+backup-account-missing-card = Le preferenze o le richieste di modifica della tessera { $card_id } riguardano un volontario che non esiste
+backup-no-locations = L'archivio di backup non contiene nessuna sede
+backup-invalid-location-task = La sede { $location } offre la mansione { $task } che non esiste
+backup-volunteers-added = Volontari aggiunti
+backup-volunteers-updated = Volontari aggiornati
+backup-volunteers-removed = Volontari rimossi
+backup-shifts-added = Turni aggiunti
+backup-shifts-skipped = Turni già presenti
+backup-shifts-removed = Turni rimossi
+backup-settings-restored = Impostazioni ripristinate
+backup-notes-added = Note aggiunte
+backup-overrides-restored = Dati modificati ripristinati
+backup-preferences-restored = Preferenze ripristinate
+backup-change-requests-restored = Richieste di modifica ripristinate
+backup-tasks-restored = Mansioni ripristinate
+backup-locations-restored = Sedi ripristinate
+
+## Volontari
+
+volunteers-shifts-manager = Inserisci turni
+volunteers-shifts = Cancella turni
+volunteers-update = Aggiorna volontari
+volunteers-profile = Scheda volontario
+volunteers-invitation = Invita
+volunteers-certificate = Attestato ore
+volunteers-single-use = Link monouso
+volunteers-time-limited = Link valido { $days } giorni
+volunteers-search = Cerca per tessera, cognome, nome o telefono
+volunteers-status-all = Tutti i volontari
+volunteers-status-enabled = Solo abilitati
+volunteers-status-disabled = Solo disabilitati
+volunteers-shifts-all = Con o senza turni
+volunteers-shifts-with = Con turni questa settimana
+volunteers-shifts-without = Senza turni questa settimana
+volunteers-sort-card = Ordina per tessera
+volunteers-sort-surname = Ordina per cognome
+volunteers-sort-name = Ordina per nome
+volunteers-filter = Filtra
+volunteers-found = { $count ->
+        [one] Un volontario trovato
+       *[other] { $count } volontari trovati
+    }
+volunteers-empty = Nessun volontario corrisponde alla ricerca
+volunteers-previous = Precedente
+volunteers-next = Successiva
+
+## Token API
+
+api-tokens-heading = Accesso API
+api-tokens-empty = Nessun token creato!
+api-tokens-name = Nome del token
+api-tokens-scope = Permessi
+api-tokens-public-scope = Sola lettura, senza numeri di telefono
+api-tokens-administration-scope = Referente
+api-tokens-create = Crea token
+api-tokens-new = Copia il token ora, non sarà più mostrato:
+api-tokens-never-used = Mai usato
+api-tokens-revoke = Revoca
+api-invalid-page = page deve essere positivo e per_page deve essere compreso tra 1 e { $max }
+api-invalid-date = La data "{ $date }" non è valida
+api-administration-only = Solo i token dei referenti possono modificare i turni
+api-unknown-volunteer = Il volontario non esiste
+api-unknown-shift = Il turno non esiste o è già passato
+api-bad-request = La richiesta non è valida
+api-unauthorized = Il token non è valido
+api-not-found = La risorsa non esiste
+api-unprocessable = Il contenuto della richiesta non è corretto
+api-error = Si è verificato un errore
+
+## Accessi bloccati
+
+locked-logins-heading = Accessi bloccati
+locked-logins-empty = Nessun accesso bloccato!
+locked-logins-unlock = Sblocca
+locked-logins-ip = Indirizzo IP
+locked-logins-volunteer = Tessera volontario
+locked-logins-administration = Tessera referente
+locked-logins-unknown = Sconosciuto
+
+## Inviti
+
+invitations-heading = Inviti
+invitations-empty = Nessun invito in sospeso!
+invitations-revoke = Revoca
+invitations-single-use = Monouso
+invitations-time-limited = Riutilizzabile fino alla scadenza
+invitations-disabled-volunteer = Il volontario è disabilitato
+invitations-accept-heading = Sei stato invitato a inserire i tuoi turni
+invitations-accept = Accedi
+
+## Dispositivi collegati
+
+sessions-heading = Dispositivi collegati
+sessions-empty = Nessun dispositivo collegato!
+sessions-revoke = Disconnetti
+sessions-current = Questo dispositivo
+sessions-administration-role = Referente
+sessions-volunteer-role = Volontario
+
+## Assegnazione turni
+
+assignments-heading = Assegna turni
+assignments-search = Cerca volontari
+assignments-assign = Assegna ai volontari scelti
+assignments-done = Assegnazione completata
+assignments-no-volunteers = Scegli almeno un volontario
+assignments-booked = Turni inseriti
+assignments-already-booked = Turno già inserito
+assignments-disabled = Tessera disabilitata
+assignments-full = Posti esauriti
+assignments-rejected = Turno non inserito
+
+## Pianificazione
+
+planning-heading = Pianificazione settimanale
+planning-volunteers = Volontari disponibili
+planning-explain = Trascina un volontario in una casella per inserire il turno, oppure spostalo in un'altra casella o nella zona di rimozione.
+planning-remove = Trascina qui per rimuovere il turno
+
+## Scheda volontario
+
+profile-contacts = Contatti
+profile-call = Chiama
+profile-send-message = Invia SMS
+profile-actions = Azioni
+profile-local-data = Dati modificati dai referenti
+profile-edit = Dati del volontario
+profile-surname = Cognome
+profile-name = Nome
+profile-phone = Telefono
+profile-disabled = Tessera disabilitata
+profile-save = Salva
+profile-source = Valore del file: { $value }
+profile-enabled = Tessera abilitata
+profile-restore = Ripristina i dati del file
+profile-email = Email
+profile-channels = Contatti preferiti
+profile-no-channels = Nessun contatto preferito
+profile-language = Lingua
+profile-change-request = Il volontario ha chiesto di modificare i suoi dati
+profile-upcoming = Prossimi turni
+profile-past = Turni svolti
+profile-no-shifts = Nessun turno
+profile-skills = Mansioni svolte
+profile-no-skills = Nessuna mansione svolta
+profile-notes = Note dei referenti
+profile-notes-private = Le note sono visibili solo ai referenti
+profile-add-note = Aggiungi nota
+profile-delete-note = Elimina nota
+profile-no-notes = Nessuna nota
+
+## Profilo del volontario
+
+account-heading = Il tuo profilo
+account-data = Dati registrati
+account-card-id = Tessera
+account-surname = Cognome
+account-name = Nome
+account-phone = Telefono
+account-change = Modifica i tuoi dati
+account-change-explain = Le modifiche saranno applicate dopo l'approvazione di un referente
+account-send-request = Invia richiesta
+account-pending = Richiesta inviata il { $date }, in attesa di approvazione:
+account-withdraw = Ritira richiesta
+account-preferences = Preferenze di contatto
+account-email = Email
+account-channels = Come vuoi essere contattato
+account-notify-email = Email
+account-notify-sms = SMS
+account-language = Lingua
+account-browser-language = Lingua del browser
+account-save = Salva
+account-preferences-saved = Preferenze salvate
+account-request-sent = Richiesta inviata ai referenti
+account-request-withdrawn = Richiesta ritirata
+account-invalid-email = Indirizzo email non valido
+
+## Richieste di modifica
+
+change-requests-heading = Richieste di modifica
+change-requests-explain = Dati che i volontari chiedono di modificare. I dati approvati sono mantenuti quando i volontari sono aggiornati dal file.
+change-requests-empty = Nessuna richiesta
+change-requests-surname = Cognome
+change-requests-name = Nome
+change-requests-phone = Telefono
+change-requests-current = Attuale
+change-requests-requested = Richiesto
+change-requests-approve = Approva
+change-requests-reject = Rifiuta
+change-requests-profile = Scheda
@@ -1,19 +1,393 @@
-DROP TABLE IF EXISTS shifts;
-DROP TABLE IF EXISTS volunteers;
+CREATE TABLE IF NOT EXISTS organisations (
+  id serial PRIMARY KEY,
+  slug VARCHAR(30) NOT NULL UNIQUE,
+  name VARCHAR(100) NOT NULL,
+  volunteers_url VARCHAR(300) NOT NULL,
+  password_hash VARCHAR(200) NOT NULL,
+  logo_path VARCHAR(200) NOT NULL
+);
+
+-- Passwords hashes are saved in the PHC format, longer than the first ones
+ALTER TABLE organisations ALTER COLUMN password_hash TYPE VARCHAR(200);
 
-CREATE TABLE volunteers (
-  card_id smallint NOT NULL PRIMARY KEY,
+-- The first organisation is configured through the secrets
+INSERT INTO organisations(slug, name, volunteers_url, password_hash, logo_path)
+SELECT 'default', '', '', '', 'img/logo.png'
+WHERE NOT EXISTS (SELECT 1 FROM organisations);
+
+CREATE TABLE IF NOT EXISTS locations (
+  organisation_id integer NOT NULL,
+  id smallint NOT NULL,
+  name VARCHAR(60) NOT NULL,
+  address VARCHAR(200) NOT NULL,
+  PRIMARY KEY(organisation_id, id),
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
+);
+
+-- Organisations without locations receive the default one
+INSERT INTO locations(organisation_id, id, name, address)
+SELECT organisations.id, 1, 'Sede principale', ''
+FROM organisations
+WHERE NOT EXISTS (SELECT 1 FROM locations WHERE organisation_id = organisations.id);
+
+CREATE TABLE IF NOT EXISTS volunteers (
+  organisation_id integer NOT NULL,
+  card_id smallint NOT NULL,
   phone_number VARCHAR(20) NOT NULL,
   name VARCHAR(60) NOT NULL,
   surname VARCHAR(50) NOT NULL,
-  disabled BOOLEAN NOT NULL
+  disabled BOOLEAN NOT NULL,
+  PRIMARY KEY(organisation_id, card_id),
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
 );
 
-CREATE TABLE shifts (
+CREATE TABLE IF NOT EXISTS shifts (
   id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
   date DATE NOT NULL,
   task smallint NOT NULL,
   card_id smallint NOT NULL,
-  FOREIGN KEY(card_id)
-      REFERENCES volunteers(card_id)
+  location_id smallint NOT NULL,
+  UNIQUE(organisation_id, date, task, card_id),
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id),
+  FOREIGN KEY(organisation_id, location_id)
+      REFERENCES locations(organisation_id, id)
+);
+
+-- Volunteers and shifts saved before organisations existed belong to the
+-- first organisation, and shifts saved before locations existed belong to
+-- its default location. This runs before any table referencing volunteers
+-- through their organisation is created.
+DO $$
+DECLARE
+  first_organisation integer := (SELECT MIN(id) FROM organisations);
+BEGIN
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+        AND table_name = 'volunteers' AND column_name = 'organisation_id'
+  ) THEN
+    -- Foreign keys referencing the old primary key are dropped as well, and
+    -- added again below with the organisation
+    ALTER TABLE volunteers DROP CONSTRAINT volunteers_pkey CASCADE;
+    ALTER TABLE volunteers ADD COLUMN organisation_id integer
+        REFERENCES organisations(id);
+    UPDATE volunteers SET organisation_id = first_organisation;
+    ALTER TABLE volunteers ALTER COLUMN organisation_id SET NOT NULL;
+    ALTER TABLE volunteers ADD PRIMARY KEY(organisation_id, card_id);
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+        AND table_name = 'shifts' AND column_name = 'organisation_id'
+  ) THEN
+    ALTER TABLE shifts ADD COLUMN organisation_id integer;
+    UPDATE shifts SET organisation_id = first_organisation;
+    ALTER TABLE shifts ALTER COLUMN organisation_id SET NOT NULL;
+    ALTER TABLE shifts ADD FOREIGN KEY(organisation_id, card_id)
+        REFERENCES volunteers(organisation_id, card_id);
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+        AND table_name = 'shifts' AND column_name = 'location_id'
+  ) THEN
+    ALTER TABLE shifts ADD COLUMN location_id smallint NOT NULL DEFAULT 1;
+    ALTER TABLE shifts ADD FOREIGN KEY(organisation_id, location_id)
+        REFERENCES locations(organisation_id, id);
+    ALTER TABLE shifts ALTER COLUMN location_id DROP DEFAULT;
+  END IF;
+END $$;
+
+-- A volunteer books a task at most once a day. Shifts booked twice before
+-- the constraint existed are removed, keeping the first one.
+DO $$
+BEGIN
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.table_constraints
+    WHERE table_schema = current_schema()
+        AND table_name = 'shifts' AND constraint_type = 'UNIQUE'
+  ) THEN
+    DELETE FROM shifts WHERE EXISTS (
+      SELECT 1 FROM shifts AS first
+      WHERE first.organisation_id = shifts.organisation_id AND first.date = shifts.date
+          AND first.task = shifts.task AND first.card_id = shifts.card_id
+          AND first.id < shifts.id
+    );
+    ALTER TABLE shifts ADD UNIQUE(organisation_id, date, task, card_id);
+  END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS login_attempts (
+  organisation_id integer NOT NULL,
+  kind VARCHAR(20) NOT NULL,
+  identifier VARCHAR(64) NOT NULL,
+  attempts integer NOT NULL,
+  locked_until TIMESTAMPTZ,
+  PRIMARY KEY(organisation_id, kind, identifier),
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
+);
+
+CREATE TABLE IF NOT EXISTS sessions (
+  id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
+  token VARCHAR(64) NOT NULL UNIQUE,
+  card_id smallint NOT NULL,
+  is_administration BOOLEAN NOT NULL,
+  user_agent VARCHAR(300) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  last_seen TIMESTAMPTZ NOT NULL,
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
+);
+
+-- Login codes saved in clear text expire within minutes, so they are
+-- dropped instead of being migrated
+DO $$
+BEGIN
+  IF EXISTS (
+    SELECT 1 FROM information_schema.columns
+    WHERE table_schema = current_schema()
+        AND table_name = 'login_codes' AND column_name = 'code'
+  ) THEN
+    DROP TABLE login_codes;
+  END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS login_codes (
+  organisation_id integer NOT NULL,
+  card_id smallint NOT NULL,
+  code_hash VARCHAR(64) NOT NULL,
+  expires_at TIMESTAMPTZ NOT NULL,
+  attempts integer NOT NULL,
+  PRIMARY KEY(organisation_id, card_id),
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
+);
+
+CREATE TABLE IF NOT EXISTS invitations (
+  id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
+  token VARCHAR(64) NOT NULL UNIQUE,
+  card_id smallint NOT NULL,
+  single_use BOOLEAN NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  expires_at TIMESTAMPTZ NOT NULL,
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
+);
+
+CREATE TABLE IF NOT EXISTS settings (
+  organisation_id integer NOT NULL,
+  key VARCHAR(50) NOT NULL,
+  value VARCHAR(200) NOT NULL,
+  PRIMARY KEY(organisation_id, key),
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
+);
+
+-- The retention setting contains periods in weeks as well, not only months
+UPDATE settings SET key = 'retention_period' WHERE key = 'retention_months';
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+  id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
+  name VARCHAR(60) NOT NULL,
+  token_hash VARCHAR(64) NOT NULL UNIQUE,
+  is_administration BOOLEAN NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  last_used TIMESTAMPTZ,
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
+);
+
+CREATE TABLE IF NOT EXISTS webhooks (
+  id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
+  url VARCHAR(300) NOT NULL,
+  secret VARCHAR(64) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+  id serial PRIMARY KEY,
+  webhook_id integer NOT NULL,
+  event VARCHAR(30) NOT NULL,
+  payload TEXT NOT NULL,
+  status VARCHAR(10) NOT NULL,
+  attempts integer NOT NULL,
+  response VARCHAR(300),
+  created_at TIMESTAMPTZ NOT NULL,
+  next_attempt TIMESTAMPTZ NOT NULL,
+  last_attempt TIMESTAMPTZ,
+  FOREIGN KEY(webhook_id)
+      REFERENCES webhooks(id) ON DELETE CASCADE
+);
+
+-- Tables created before organisations existed belong to the first
+-- organisation. Tables just created above already have their final shape
+-- and are skipped.
+DO $$
+DECLARE
+  first_organisation integer := (SELECT MIN(id) FROM organisations);
+  migrated text;
+BEGIN
+  FOREACH migrated IN ARRAY ARRAY[
+    'login_attempts', 'sessions', 'login_codes', 'invitations', 'settings',
+    'api_tokens', 'webhooks'
+  ] LOOP
+    IF NOT EXISTS (
+      SELECT 1 FROM information_schema.columns
+      WHERE table_schema = current_schema()
+          AND table_name = migrated AND column_name = 'organisation_id'
+    ) THEN
+      EXECUTE format('ALTER TABLE %I ADD COLUMN organisation_id integer', migrated);
+      EXECUTE format('UPDATE %I SET organisation_id = $1', migrated)
+          USING first_organisation;
+      EXECUTE format('ALTER TABLE %I ALTER COLUMN organisation_id SET NOT NULL', migrated);
+
+      -- Tables whose rows belong to a volunteer reference it through the
+      -- organisation, the other ones reference the organisation itself
+      IF migrated IN ('sessions', 'login_codes', 'invitations') THEN
+        EXECUTE format('ALTER TABLE %I ADD FOREIGN KEY(organisation_id, card_id)
+            REFERENCES volunteers(organisation_id, card_id)', migrated);
+      ELSE
+        EXECUTE format('ALTER TABLE %I ADD FOREIGN KEY(organisation_id)
+            REFERENCES organisations(id)', migrated);
+      END IF;
+    END IF;
+  END LOOP;
+END $$;
+
+-- Primary keys of the migrated tables contain the organisation as well
+DO $$
+BEGIN
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.key_column_usage
+    WHERE table_schema = current_schema()
+        AND table_name = 'login_attempts' AND constraint_name = 'login_attempts_pkey'
+        AND column_name = 'organisation_id'
+  ) THEN
+    ALTER TABLE login_attempts DROP CONSTRAINT login_attempts_pkey;
+    ALTER TABLE login_attempts ADD PRIMARY KEY(organisation_id, kind, identifier);
+  END IF;
+
+  IF NOT EXISTS (
+    SELECT 1 FROM information_schema.key_column_usage
+    WHERE table_schema = current_schema()
+        AND table_name = 'settings' AND constraint_name = 'settings_pkey'
+        AND column_name = 'organisation_id'
+  ) THEN
+    ALTER TABLE settings DROP CONSTRAINT settings_pkey;
+    ALTER TABLE settings ADD PRIMARY KEY(organisation_id, key);
+  END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS tasks (
+  organisation_id integer NOT NULL,
+  id smallint NOT NULL,
+  name VARCHAR(60) NOT NULL,
+  hours VARCHAR(11) NOT NULL,
+  PRIMARY KEY(organisation_id, id),
+  FOREIGN KEY(organisation_id)
+      REFERENCES organisations(id)
+);
+
+-- Organisations without tasks receive the default ones
+INSERT INTO tasks(organisation_id, id, name, hours)
+SELECT organisations.id, defaults.id, defaults.name, defaults.hours
+FROM organisations CROSS JOIN (VALUES
+    (0, 'Aiuto Cucina', '10:00-14:00'),
+    (1, 'Accoglienza', '11:30-13:30'),
+    (2, 'Servizio tavoli', '11:00-14:00'),
+    (3, 'Pomeriggio', '14:00-16:00'),
+    (4, 'Accoglienza notturna', '19:00-21:00')
+) AS defaults(id, name, hours)
+WHERE NOT EXISTS (SELECT 1 FROM tasks WHERE organisation_id = organisations.id);
+
+CREATE TABLE IF NOT EXISTS location_tasks (
+  organisation_id integer NOT NULL,
+  location_id smallint NOT NULL,
+  task_id smallint NOT NULL,
+  capacity smallint,
+  PRIMARY KEY(organisation_id, location_id, task_id),
+  FOREIGN KEY(organisation_id, location_id)
+      REFERENCES locations(organisation_id, id),
+  FOREIGN KEY(organisation_id, task_id)
+      REFERENCES tasks(organisation_id, id)
+);
+
+-- Locations without tasks offer every task, without a capacity limit
+INSERT INTO location_tasks(organisation_id, location_id, task_id, capacity)
+SELECT locations.organisation_id, locations.id, tasks.id, NULL
+FROM locations JOIN tasks ON locations.organisation_id = tasks.organisation_id
+WHERE NOT EXISTS (
+  SELECT 1 FROM location_tasks
+  WHERE organisation_id = locations.organisation_id AND location_id = locations.id
+);
+
+CREATE TABLE IF NOT EXISTS volunteer_notes (
+  id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
+  card_id smallint NOT NULL,
+  author VARCHAR(120) NOT NULL,
+  text VARCHAR(2000) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
+);
+
+-- Values changed by referents, which are kept when the volunteers file is
+-- imported again. A NULL value keeps the one of the file.
+CREATE TABLE IF NOT EXISTS volunteer_overrides (
+  organisation_id integer NOT NULL,
+  card_id smallint NOT NULL,
+  surname VARCHAR(50),
+  name VARCHAR(60),
+  phone_number VARCHAR(20),
+  disabled BOOLEAN,
+  source_surname VARCHAR(50) NOT NULL,
+  source_name VARCHAR(60) NOT NULL,
+  source_phone_number VARCHAR(20) NOT NULL,
+  source_disabled BOOLEAN NOT NULL,
+  PRIMARY KEY(organisation_id, card_id),
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
+);
+
+-- Contact preferences chosen by volunteers, which are not part of the
+-- volunteers file
+CREATE TABLE IF NOT EXISTS volunteer_preferences (
+  organisation_id integer NOT NULL,
+  card_id smallint NOT NULL,
+  email VARCHAR(254),
+  notify_email BOOLEAN NOT NULL,
+  notify_sms BOOLEAN NOT NULL,
+  language VARCHAR(5),
+  PRIMARY KEY(organisation_id, card_id),
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
+);
+
+-- Data changes asked by volunteers, applied as local values once a referent
+-- approves them. Each volunteer has at most one pending request.
+CREATE TABLE IF NOT EXISTS volunteer_change_requests (
+  id serial PRIMARY KEY,
+  organisation_id integer NOT NULL,
+  card_id smallint NOT NULL,
+  surname VARCHAR(50) NOT NULL,
+  name VARCHAR(60) NOT NULL,
+  phone_number VARCHAR(20) NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL,
+  UNIQUE(organisation_id, card_id),
+  FOREIGN KEY(organisation_id, card_id)
+      REFERENCES volunteers(organisation_id, card_id)
 );
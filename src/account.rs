@@ -0,0 +1,333 @@
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::CookieJar;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::database::{
+    delete_change_request, query_change_request, query_volunteer_preferences,
+    query_volunteer_profile, set_change_request, set_volunteer_preferences, Organisation,
+    Volunteer, VolunteerManager, VolunteerPreferences,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::{save_language, Locale};
+use crate::login::session::{check_volunteer_access, Session};
+use crate::menu::{Menu, MenuAdministration};
+use crate::time::format_date_time;
+use crate::{AppState, ACCOUNT_ROUTE};
+
+// Messages
+const HEADING_MESSAGE: &str = "account-heading";
+const DATA_MESSAGE: &str = "account-data";
+const CARD_ID_MESSAGE: &str = "account-card-id";
+const SURNAME_MESSAGE: &str = "account-surname";
+const NAME_MESSAGE: &str = "account-name";
+const PHONE_MESSAGE: &str = "account-phone";
+const CHANGE_MESSAGE: &str = "account-change";
+const CHANGE_EXPLAIN_MESSAGE: &str = "account-change-explain";
+const SEND_REQUEST_MESSAGE: &str = "account-send-request";
+const PENDING_MESSAGE: &str = "account-pending";
+const WITHDRAW_MESSAGE: &str = "account-withdraw";
+const PREFERENCES_MESSAGE: &str = "account-preferences";
+const EMAIL_MESSAGE: &str = "account-email";
+const CHANNELS_MESSAGE: &str = "account-channels";
+const NOTIFY_EMAIL_MESSAGE: &str = "account-notify-email";
+const NOTIFY_SMS_MESSAGE: &str = "account-notify-sms";
+const LANGUAGE_MESSAGE: &str = "account-language";
+const BROWSER_LANGUAGE_MESSAGE: &str = "account-browser-language";
+const SAVE_MESSAGE: &str = "account-save";
+
+// Flash messages, each one associated to the message shown after a change
+const FLASH_MESSAGES: [(&str, &str); 3] = [
+    ("preferences-saved", "account-preferences-saved"),
+    ("request-sent", "account-request-sent"),
+    ("request-withdrawn", "account-request-withdrawn"),
+];
+
+// Route to the profile page
+macro_rules! account_uri {
+    ($($t:tt)*) => (rocket::uri!(ACCOUNT_ROUTE, $($t)*))
+}
+
+// Language which can be chosen for the messages
+#[derive(Serialize)]
+struct LanguageOption {
+    // Language code
+    code: &'static str,
+    // Language name
+    name: &'static str,
+    // Whether it is the saved language
+    is_selected: bool,
+}
+
+impl LanguageOption {
+    fn options(language: Option<&str>) -> Vec<Self> {
+        Locale::ALL
+            .into_iter()
+            .map(|locale| Self {
+                code: locale.code(),
+                name: locale.name(),
+                is_selected: language == Some(locale.code()),
+            })
+            .collect()
+    }
+}
+
+// Data change waiting for a referent approval
+#[derive(Serialize)]
+struct PendingChange {
+    // Requested values
+    surname: String,
+    name: String,
+    phone_number: String,
+    // Creation date and time
+    created_at: String,
+}
+
+#[get("/?<id>")]
+pub(crate) async fn show_account(
+    id: i16,
+    flash: Option<FlashMessage<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the user or the administrator is authenticated
+    let is_administration = check_volunteer_access(&session, id, uri)?;
+
+    let Some(volunteer) = query_error(
+        query_volunteer_profile(&state.pool, organisation.id, id),
+        uri,
+    )
+    .await?
+    else {
+        return Err(InternalError::unknown_volunteer(uri));
+    };
+
+    let preferences = query_error(
+        query_volunteer_preferences(&state.pool, organisation.id, id),
+        uri,
+    )
+    .await?;
+
+    let pending = query_error(query_change_request(&state.pool, organisation.id, id), uri)
+        .await?
+        .map(|request| PendingChange {
+            surname: request.surname,
+            name: request.name,
+            phone_number: request.phone_number,
+            created_at: format_date_time(state.timezone, request.created_at),
+        });
+
+    // Confirmation of the last change
+    let flash_message = flash.and_then(|flash| {
+        FLASH_MESSAGES
+            .into_iter()
+            .find(|(kind, _)| *kind == flash.message())
+            .map(|(_, message)| locale.text(message))
+    });
+
+    Ok(Template::render(
+        "account",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            is_administration,
+            menu: Menu::render(locale, organisation, id),
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            flash_message,
+            data_message: locale.text(DATA_MESSAGE),
+            card_id_message: locale.text(CARD_ID_MESSAGE),
+            surname_message: locale.text(SURNAME_MESSAGE),
+            name_message: locale.text(NAME_MESSAGE),
+            phone_message: locale.text(PHONE_MESSAGE),
+            volunteer: &volunteer.volunteer,
+            phone_number: &volunteer.phone_number,
+            change_message: locale.text(CHANGE_MESSAGE),
+            change_explain_message: locale.text(CHANGE_EXPLAIN_MESSAGE),
+            request_route: account_uri!(request_change(id)),
+            send_request_message: locale.text(SEND_REQUEST_MESSAGE),
+            pending_message: pending.as_ref().map(|pending| {
+                locale.format(
+                    PENDING_MESSAGE,
+                    &[("date", pending.created_at.clone().into())],
+                )
+            }),
+            pending,
+            withdraw_message: locale.text(WITHDRAW_MESSAGE),
+            preferences_message: locale.text(PREFERENCES_MESSAGE),
+            preferences_route: account_uri!(save_preferences(id)),
+            email_message: locale.text(EMAIL_MESSAGE),
+            channels_message: locale.text(CHANNELS_MESSAGE),
+            notify_email_message: locale.text(NOTIFY_EMAIL_MESSAGE),
+            notify_sms_message: locale.text(NOTIFY_SMS_MESSAGE),
+            language_message: locale.text(LANGUAGE_MESSAGE),
+            browser_language_message: locale.text(BROWSER_LANGUAGE_MESSAGE),
+            languages: LanguageOption::options(preferences.language.as_deref()),
+            preferences,
+            save_message: locale.text(SAVE_MESSAGE),
+        },
+    ))
+}
+
+// Contact preferences chosen by a volunteer
+#[derive(FromForm)]
+struct PreferencesForm<'r> {
+    #[field(validate = len(..=254))]
+    email: &'r str,
+    notify_email: bool,
+    notify_sms: bool,
+    language: &'r str,
+}
+
+#[put("/preferenze/<card_id>", data = "<preferences_form>")]
+async fn save_preferences(
+    card_id: i16,
+    preferences_form: Form<PreferencesForm<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+) -> Result<Flash<Redirect>, InternalError> {
+    // Check if the user or the administrator is authenticated
+    let is_administration = check_volunteer_access(&session, card_id, uri)?;
+
+    // An email address without an at sign cannot receive messages
+    let email = preferences_form.email.trim();
+    if !email.is_empty() && !email.contains('@') {
+        return Err(InternalError::text(uri, "account-invalid-email"));
+    }
+
+    // Unknown languages leave the choice to the browser
+    let locale = Locale::from_code(preferences_form.language);
+    let preferences = VolunteerPreferences {
+        email: (!email.is_empty()).then(|| email.to_string()),
+        notify_email: preferences_form.notify_email,
+        notify_sms: preferences_form.notify_sms,
+        language: locale.map(|locale| locale.code().to_string()),
+    };
+    query_error(
+        set_volunteer_preferences(&state.pool, organisation.id, card_id, &preferences),
+        uri,
+    )
+    .await?;
+
+    // Pages are shown at once in the chosen language, but not to referents
+    // changing the preferences of a volunteer
+    if let Some(locale) = locale.filter(|_| !is_administration) {
+        save_language(jar, locale);
+    }
+
+    Ok(Flash::success(
+        Redirect::to(account_uri!(show_account(card_id))),
+        "preferences-saved",
+    ))
+}
+
+// Data change asked by a volunteer
+#[derive(FromForm)]
+struct ChangeForm<'r> {
+    #[field(validate = len(1..=50))]
+    surname: &'r str,
+    #[field(validate = len(1..=60))]
+    name: &'r str,
+    #[field(validate = len(..=20))]
+    phone_number: &'r str,
+}
+
+#[post("/richiesta/<card_id>", data = "<change_form>")]
+async fn request_change(
+    card_id: i16,
+    change_form: Form<ChangeForm<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Flash<Redirect>, InternalError> {
+    // Check if the user or the administrator is authenticated
+    check_volunteer_access(&session, card_id, uri)?;
+
+    let Some(current) = query_error(
+        query_volunteer_profile(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    else {
+        return Err(InternalError::unknown_volunteer(uri));
+    };
+
+    // The disabled state cannot be changed by volunteers
+    let requested = VolunteerManager {
+        volunteer: Volunteer {
+            card_id,
+            surname: change_form.surname.trim().to_string(),
+            name: change_form.name.trim().to_string(),
+            disabled: current.volunteer.disabled,
+        },
+        phone_number: change_form.phone_number.trim().to_string(),
+    };
+
+    // Asking again for the saved data withdraws the pending request
+    if requested == current {
+        query_error(
+            delete_change_request(&state.pool, organisation.id, card_id),
+            uri,
+        )
+        .await?;
+        return Ok(Flash::success(
+            Redirect::to(account_uri!(show_account(card_id))),
+            "request-withdrawn",
+        ));
+    }
+
+    query_error(
+        set_change_request(&state.pool, organisation.id, &requested),
+        uri,
+    )
+    .await?;
+
+    Ok(Flash::success(
+        Redirect::to(account_uri!(show_account(card_id))),
+        "request-sent",
+    ))
+}
+
+#[delete("/richiesta/<card_id>")]
+async fn withdraw_change(
+    card_id: i16,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Flash<Redirect>, InternalError> {
+    // Check if the user or the administrator is authenticated
+    check_volunteer_access(&session, card_id, uri)?;
+
+    query_error(
+        delete_change_request(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?;
+
+    Ok(Flash::success(
+        Redirect::to(account_uri!(show_account(card_id))),
+        "request-withdrawn",
+    ))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![
+        show_account,
+        save_preferences,
+        request_change,
+        withdraw_change
+    ]
+}
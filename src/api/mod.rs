@@ -0,0 +1,647 @@
+pub(crate) mod token;
+
+use chrono::NaiveDate;
+
+use rocket::http::{ContentType, Status};
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::{Request, State};
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::{book_shifts, Locations, ShiftRejection, Tasks};
+use crate::database::{
+    delete_current_shift, query_check_card_id, query_exported_shifts_count,
+    query_exported_shifts_page, query_tasks, query_volunteers_count, query_volunteers_page,
+    Organisation, Shift, DEFAULT_LOCATION,
+};
+use crate::download_database::parse_date;
+use crate::locale::Locale;
+use crate::time::Date;
+use crate::webhooks::{send_event, WebhookEvent};
+use crate::AppState;
+
+use token::ApiToken;
+
+// Default and maximum number of items contained in a page
+const DEFAULT_PER_PAGE: u32 = 50;
+const MAX_PER_PAGE: u32 = 200;
+
+// OpenAPI document describing the API
+const OPENAPI_DOCUMENT: &str = include_str!("openapi.json");
+
+// Error returned by the API as JSON
+#[derive(Responder)]
+pub(crate) struct ApiError(Custom<Json<ApiMessage>>);
+
+// Body of an API error
+#[derive(Serialize)]
+pub(crate) struct ApiMessage {
+    // Error description
+    error: String,
+}
+
+impl ApiError {
+    fn new(status: Status, error: &str) -> Self {
+        Self(Custom(
+            status,
+            Json(ApiMessage {
+                error: error.to_string(),
+            }),
+        ))
+    }
+
+    // Invalid request parameters
+    pub(crate) fn bad_request(error: &str) -> Self {
+        Self::new(Status::BadRequest, error)
+    }
+
+    // Token without the permissions required by the request
+    pub(crate) fn forbidden(error: &str) -> Self {
+        Self::new(Status::Forbidden, error)
+    }
+
+    // Missing resource
+    pub(crate) fn not_found(error: &str) -> Self {
+        Self::new(Status::NotFound, error)
+    }
+
+    // Only referents tokens can change data
+    fn check_administration(token: &ApiToken, locale: Locale) -> Result<(), Self> {
+        if token.is_administration {
+            Ok(())
+        } else {
+            Err(Self::forbidden(&locale.text("api-administration-only")))
+        }
+    }
+
+    // Shift which cannot be booked
+    fn rejected(rejection: ShiftRejection, locale: Locale) -> Self {
+        let status = match rejection {
+            ShiftRejection::AlreadyBooked | ShiftRejection::Full => Status::Conflict,
+            ShiftRejection::UnknownVolunteer => Status::NotFound,
+            ShiftRejection::Disabled => Status::Forbidden,
+            ShiftRejection::InvalidTask
+            | ShiftRejection::InvalidLocation
+            | ShiftRejection::InvalidDate => Status::UnprocessableEntity,
+        };
+        Self::new(status, &rejection.message(locale))
+    }
+}
+
+// Convert query errors into API errors
+pub(crate) async fn api_query_error<T, K: ToString>(
+    function: impl std::future::Future<Output = Result<T, K>>,
+) -> Result<T, ApiError> {
+    function
+        .await
+        .map_err(|e| ApiError::new(Status::InternalServerError, &e.to_string()))
+}
+
+// Page requested through the query parameters
+#[derive(FromForm)]
+struct PageQuery {
+    // Page number, starting from 1
+    page: Option<u32>,
+    // Maximum number of items contained in a page
+    per_page: Option<u32>,
+}
+
+// Shifts and page requested through the query parameters
+#[derive(FromForm)]
+struct ShiftsQuery<'r> {
+    // First and last days of the shifts, unbounded when missing
+    from: Option<&'r str>,
+    to: Option<&'r str>,
+    // Page number, starting from 1
+    page: Option<u32>,
+    // Maximum number of items contained in a page
+    per_page: Option<u32>,
+}
+
+// Checked page number and size
+struct PageRange {
+    page: u32,
+    per_page: u32,
+}
+
+impl PageRange {
+    fn new(page: Option<u32>, per_page: Option<u32>, locale: Locale) -> Result<Self, ApiError> {
+        let page = page.unwrap_or(1);
+        let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE);
+        if page == 0 || per_page == 0 || per_page > MAX_PER_PAGE {
+            return Err(ApiError::bad_request(
+                &locale.format("api-invalid-page", &[("max", MAX_PER_PAGE.into())]),
+            ));
+        }
+        Ok(Self { page, per_page })
+    }
+
+    // Maximum number of items to retrieve
+    fn limit(&self) -> i64 {
+        self.per_page.into()
+    }
+
+    // Number of items preceding the page
+    fn offset(&self) -> i64 {
+        i64::from(self.page - 1) * i64::from(self.per_page)
+    }
+}
+
+// Page of items
+#[derive(Serialize)]
+pub(crate) struct Page<T> {
+    // Items contained in the page
+    data: Vec<T>,
+    // Page number, starting from 1
+    page: u32,
+    // Maximum number of items contained in a page
+    per_page: u32,
+    // Total number of items
+    total: i64,
+}
+
+impl<T> Page<T> {
+    fn new(data: Vec<T>, range: &PageRange, total: i64) -> Self {
+        Self {
+            data,
+            page: range.page,
+            per_page: range.per_page,
+            total,
+        }
+    }
+}
+
+// Task description
+#[derive(Serialize)]
+struct ApiTask {
+    // Task identifier
+    id: i16,
+    // Task name
+    name: String,
+    // Task hours
+    hours: String,
+    // Task duration in minutes
+    minutes: u32,
+}
+
+// Task offered by a location
+#[derive(Serialize)]
+struct ApiLocationTask {
+    // Task identifier
+    task: i16,
+    // Maximum number of volunteers for each day, missing when unlimited
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capacity: Option<i16>,
+}
+
+// Location description
+#[derive(Serialize)]
+struct ApiLocation {
+    // Location identifier
+    id: i16,
+    // Location name
+    name: String,
+    // Location address
+    address: String,
+    // Tasks offered by the location
+    tasks: Vec<ApiLocationTask>,
+}
+
+// Volunteer data, the phone number is visible only to referents tokens
+#[derive(Serialize)]
+struct ApiVolunteer {
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname
+    surname: String,
+    // Volunteer name
+    name: String,
+    // Whether the volunteer is disabled
+    disabled: bool,
+    // Volunteer phone number
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phone_number: Option<String>,
+}
+
+// Shift together with its task and volunteer
+#[derive(Serialize)]
+struct ApiShift {
+    // Shift date
+    date: NaiveDate,
+    // Task identifier
+    task: i16,
+    // Task name
+    task_name: String,
+    // Task hours
+    hours: String,
+    // Location identifier
+    location: i16,
+    // Location name
+    location_name: String,
+    // Location address
+    address: String,
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname
+    surname: String,
+    // Volunteer name
+    name: String,
+}
+
+// Shift to be booked
+#[derive(Deserialize)]
+struct NewShift {
+    // Card identifier
+    card_id: i16,
+    // Shift date
+    date: NaiveDate,
+    // Task identifier
+    task: i16,
+    // Location identifier, the first location when missing
+    #[serde(default)]
+    location: Option<i16>,
+}
+
+// Shift together with its identifier, needed to cancel it
+#[derive(Serialize)]
+pub(crate) struct ShiftRecord {
+    // Shift identifier
+    id: i32,
+    // Shift date
+    date: NaiveDate,
+    // Task identifier
+    task: i16,
+    // Task name
+    task_name: String,
+    // Task hours
+    hours: String,
+    // Location identifier
+    location: i16,
+    // Location name
+    location_name: String,
+    // Location address
+    address: String,
+    // Card identifier
+    card_id: i16,
+}
+
+impl ShiftRecord {
+    pub(crate) fn new(tasks: &Tasks, locations: &Locations, id: i32, shift: &Shift) -> Self {
+        Self {
+            id,
+            date: shift.date,
+            task: shift.task,
+            task_name: tasks.task_from_id(shift.task),
+            hours: tasks.hours_from_id(shift.task),
+            location: shift.location_id,
+            location_name: locations.name_from_id(shift.location_id),
+            address: locations.address_from_id(shift.location_id),
+            card_id: shift.card_id,
+        }
+    }
+}
+
+// Retrieve a page of the shifts contained in a date range
+async fn shifts(
+    state: &State<AppState>,
+    organisation: &Organisation,
+    query: ShiftsQuery<'_>,
+    card_id: Option<i16>,
+    locale: Locale,
+) -> Result<Page<ApiShift>, ApiError> {
+    let range = PageRange::new(query.page, query.per_page, locale)?;
+    let from = parse_api_date(query.from, locale)?;
+    let to = parse_api_date(query.to, locale)?;
+    let tasks = Tasks::new(api_query_error(query_tasks(&state.pool, organisation.id)).await?);
+    let locations = api_query_error(Locations::load(&state.pool, organisation.id)).await?;
+
+    let total = api_query_error(query_exported_shifts_count(
+        &state.pool,
+        organisation.id,
+        from,
+        to,
+        card_id,
+    ))
+    .await?;
+    let shifts = api_query_error(query_exported_shifts_page(
+        &state.pool,
+        organisation.id,
+        from,
+        to,
+        card_id,
+        range.limit(),
+        range.offset(),
+    ))
+    .await?
+    .into_iter()
+    .map(|exported| ApiShift {
+        date: exported.shift.date,
+        task: exported.shift.task,
+        task_name: tasks.task_from_id(exported.shift.task),
+        hours: tasks.hours_from_id(exported.shift.task),
+        location: exported.shift.location_id,
+        location_name: locations.name_from_id(exported.shift.location_id),
+        address: locations.address_from_id(exported.shift.location_id),
+        card_id: exported.shift.card_id,
+        surname: exported.surname,
+        name: exported.name,
+    })
+    .collect();
+
+    Ok(Page::new(shifts, &range, total))
+}
+
+// Parse a date of the query parameters
+fn parse_api_date(date: Option<&str>, locale: Locale) -> Result<Option<NaiveDate>, ApiError> {
+    parse_date(date).map_err(|_| {
+        ApiError::bad_request(&locale.format(
+            "api-invalid-date",
+            &[("date", date.unwrap_or_default().to_string().into())],
+        ))
+    })
+}
+
+#[get("/tasks")]
+async fn tasks(
+    _token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+) -> Result<Json<Vec<ApiTask>>, ApiError> {
+    let tasks = Tasks::new(api_query_error(query_tasks(&state.pool, organisation.id)).await?);
+    Ok(Json(
+        tasks
+            .render()
+            .iter()
+            .map(|task| ApiTask {
+                id: task.task_value,
+                name: task.task_name.clone(),
+                hours: task.task_hours.clone(),
+                minutes: tasks.minutes_from_id(task.task_value),
+            })
+            .collect(),
+    ))
+}
+
+#[get("/locations")]
+async fn locations(
+    _token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+) -> Result<Json<Vec<ApiLocation>>, ApiError> {
+    let tasks = Tasks::new(api_query_error(query_tasks(&state.pool, organisation.id)).await?);
+    let locations = api_query_error(Locations::load(&state.pool, organisation.id)).await?;
+    Ok(Json(
+        locations
+            .render()
+            .iter()
+            .map(|location| ApiLocation {
+                id: location.id,
+                name: location.name.clone(),
+                address: location.address.clone(),
+                tasks: locations
+                    .tasks(location.id, &tasks)
+                    .into_iter()
+                    .map(|task| ApiLocationTask {
+                        task: task.task_value,
+                        capacity: locations.capacity(location.id, task.task_value),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    ))
+}
+
+#[get("/volunteers?<query..>")]
+async fn volunteers(
+    query: PageQuery,
+    token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Json<Page<ApiVolunteer>>, ApiError> {
+    let range = PageRange::new(query.page, query.per_page, locale)?;
+    let total = api_query_error(query_volunteers_count(&state.pool, organisation.id)).await?;
+    let volunteers = api_query_error(query_volunteers_page(
+        &state.pool,
+        organisation.id,
+        range.limit(),
+        range.offset(),
+    ))
+    .await?
+    .into_iter()
+    .map(|volunteer| ApiVolunteer {
+        card_id: volunteer.volunteer.card_id,
+        surname: volunteer.volunteer.surname,
+        name: volunteer.volunteer.name,
+        disabled: volunteer.volunteer.disabled,
+        phone_number: token.is_administration.then_some(volunteer.phone_number),
+    })
+    .collect();
+
+    Ok(Json(Page::new(volunteers, &range, total)))
+}
+
+#[get("/shifts?<query..>")]
+async fn all_shifts(
+    query: ShiftsQuery<'_>,
+    _token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Json<Page<ApiShift>>, ApiError> {
+    Ok(Json(
+        shifts(state, organisation, query, None, locale).await?,
+    ))
+}
+
+#[get("/volunteers/<card_id>/shifts?<query..>")]
+async fn volunteer_shifts(
+    card_id: i16,
+    query: ShiftsQuery<'_>,
+    _token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Json<Page<ApiShift>>, ApiError> {
+    if !api_query_error(query_check_card_id(&state.pool, organisation.id, card_id)).await? {
+        return Err(ApiError::not_found(&locale.text("api-unknown-volunteer")));
+    }
+
+    Ok(Json(
+        shifts(state, organisation, query, Some(card_id), locale).await?,
+    ))
+}
+
+#[post("/shifts", format = "json", data = "<new_shift>")]
+async fn book_shift(
+    new_shift: Json<NewShift>,
+    token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Custom<Json<ShiftRecord>>, ApiError> {
+    ApiError::check_administration(&token, locale)?;
+
+    let locations = api_query_error(Locations::load(&state.pool, organisation.id)).await?;
+    let shift = Shift {
+        date: new_shift.date,
+        task: new_shift.task,
+        card_id: new_shift.card_id,
+        location_id: new_shift
+            .location
+            .or_else(|| locations.first())
+            .unwrap_or(DEFAULT_LOCATION),
+    };
+
+    let record = create_shift(state, organisation, &locations, &shift, locale).await?;
+
+    Ok(Custom(Status::Created, Json(record)))
+}
+
+// Book a shift applying the same checks of the shifts manager page, then
+// notify webhooks and visualizer pages. It is shared with the planning board.
+pub(crate) async fn create_shift(
+    state: &AppState,
+    organisation: &Organisation,
+    locations: &Locations,
+    shift: &Shift,
+    locale: Locale,
+) -> Result<ShiftRecord, ApiError> {
+    let tasks = Tasks::new(api_query_error(query_tasks(&state.pool, organisation.id)).await?);
+    let current_date = Date::current(state.timezone);
+
+    // Checks and insertion are performed inside a transaction locking the
+    // volunteer and the task of the location, so that concurrent requests
+    // cannot book the same shift twice or exceed the capacity
+    let mut tx = api_query_error(state.pool.begin()).await?;
+    let mut results = api_query_error(book_shifts(
+        &mut tx,
+        organisation.id,
+        std::slice::from_ref(shift),
+        &tasks,
+        locations,
+        &current_date,
+    ))
+    .await?;
+    let id = results
+        .remove(0)
+        .map_err(|rejection| ApiError::rejected(rejection, locale))?;
+    api_query_error(tx.commit()).await?;
+    let record = ShiftRecord::new(&tasks, locations, id, shift);
+
+    // Notify webhooks about the new shift
+    send_event(
+        &state.pool,
+        organisation.id,
+        &WebhookEvent::ShiftCreated(ShiftRecord::new(&tasks, locations, id, shift)),
+    )
+    .await;
+
+    // Send an event to refresh visualizer pages, the shift is already saved
+    if let Err(e) = state
+        .sender
+        .shift_added(&state.pool, organisation.id, shift)
+        .await
+    {
+        error!("Error notifying visualizer pages: {e}");
+    }
+
+    Ok(record)
+}
+
+#[delete("/shifts/<id>")]
+async fn cancel_shift(
+    id: i32,
+    token: ApiToken,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Status, ApiError> {
+    ApiError::check_administration(&token, locale)?;
+
+    cancel_current_shift(state, organisation, id, locale).await?;
+
+    Ok(Status::NoContent)
+}
+
+// Cancel a shift which is not already passed, then notify webhooks and
+// visualizer pages. It is shared with the planning board.
+pub(crate) async fn cancel_current_shift(
+    state: &AppState,
+    organisation: &Organisation,
+    id: i32,
+    locale: Locale,
+) -> Result<(), ApiError> {
+    // Tasks and locations are retrieved before the shift is deleted, so that
+    // the request does not fail afterwards
+    let tasks = Tasks::new(api_query_error(query_tasks(&state.pool, organisation.id)).await?);
+    let locations = api_query_error(Locations::load(&state.pool, organisation.id)).await?;
+
+    // Past shifts belong to the archive and cannot be cancelled
+    let today = Date::current(state.timezone).naive_date();
+    let Some(shift) = api_query_error(delete_current_shift(
+        &state.pool,
+        organisation.id,
+        id,
+        today,
+    ))
+    .await?
+    else {
+        return Err(ApiError::not_found(&locale.text("api-unknown-shift")));
+    };
+
+    // Notify webhooks about the deleted shift
+    send_event(
+        &state.pool,
+        organisation.id,
+        &WebhookEvent::ShiftDeleted(ShiftRecord::new(&tasks, &locations, id, &shift)),
+    )
+    .await;
+
+    // Send an event to refresh visualizer pages, the shift is already saved
+    if let Err(e) = state
+        .sender
+        .shift_removed(&state.pool, organisation.id, &shift)
+        .await
+    {
+        error!("Error notifying visualizer pages: {e}");
+    }
+
+    Ok(())
+}
+
+#[get("/openapi.json")]
+pub(crate) fn openapi() -> (ContentType, &'static str) {
+    (ContentType::JSON, OPENAPI_DOCUMENT)
+}
+
+// Return errors as JSON inside the API, in the language of the request
+#[catch(default)]
+fn default(status: Status, req: &Request<'_>) -> ApiError {
+    let message = match status.code {
+        400 => "api-bad-request",
+        401 => "api-unauthorized",
+        404 => "api-not-found",
+        422 => "api-unprocessable",
+        _ => "api-error",
+    };
+    ApiError::new(status, &Locale::negotiate(req).text(message))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![
+        tasks,
+        locations,
+        volunteers,
+        all_shifts,
+        volunteer_shifts,
+        book_shift,
+        cancel_shift,
+        openapi
+    ]
+}
+
+pub(crate) fn catchers() -> Vec<rocket::Catcher> {
+    catchers![default]
+}
@@ -0,0 +1,221 @@
+use chrono_tz::Tz;
+
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Redirect;
+use rocket::{Request, State};
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::database::{
+    delete_api_token, insert_api_token, query_api_tokens, use_api_token, ApiTokenInfo, Organisation,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::format_date_time;
+use crate::token::{hash_token, random_token};
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+// Prefix of the authorization header value
+const BEARER_PREFIX: &str = "Bearer ";
+
+// Messages
+const HEADING_MESSAGE: &str = "api-tokens-heading";
+const EMPTY_TOKENS_MESSAGE: &str = "api-tokens-empty";
+const NAME_MESSAGE: &str = "api-tokens-name";
+const SCOPE_MESSAGE: &str = "api-tokens-scope";
+const PUBLIC_SCOPE_MESSAGE: &str = "api-tokens-public-scope";
+const ADMINISTRATION_SCOPE_MESSAGE: &str = "api-tokens-administration-scope";
+const CREATE_MESSAGE: &str = "api-tokens-create";
+const NEW_TOKEN_MESSAGE: &str = "api-tokens-new";
+const NEVER_USED_MESSAGE: &str = "api-tokens-never-used";
+const REVOKE_MESSAGE: &str = "api-tokens-revoke";
+
+// Token which authenticated an API request
+pub(crate) struct ApiToken {
+    // Check whether the token can read administration data
+    pub(crate) is_administration: bool,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiToken {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(token) = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix(BEARER_PREFIX))
+        else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        let Some(state) = req.rocket().state::<AppState>() else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+
+        // Tokens are valid only inside their organisation
+        let Outcome::Success(organisation) = req.guard::<&Organisation>().await else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+
+        match use_api_token(&state.pool, organisation.id, &hash_token(token.trim())).await {
+            Ok(Some(token)) => Outcome::Success(Self {
+                is_administration: token.is_administration,
+            }),
+            Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+            Err(_) => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+// API token and the relative route to revoke it
+#[derive(Serialize)]
+struct ApiTokenView {
+    // Token name
+    name: String,
+    // Token permissions
+    scope: String,
+    // Creation date and time
+    created_at: String,
+    // Last use date and time
+    last_used: String,
+    // Route to revoke the token
+    revoke_route: Origin<'static>,
+}
+
+impl ApiTokenView {
+    fn tokens(locale: Locale, timezone: Tz, tokens: Vec<ApiTokenInfo>) -> Vec<Self> {
+        tokens
+            .into_iter()
+            .map(|token| Self {
+                name: token.name,
+                scope: locale.text(if token.is_administration {
+                    ADMINISTRATION_SCOPE_MESSAGE
+                } else {
+                    PUBLIC_SCOPE_MESSAGE
+                }),
+                created_at: format_date_time(timezone, token.created_at),
+                last_used: token.last_used.map_or_else(
+                    || locale.text(NEVER_USED_MESSAGE),
+                    |date_time| format_date_time(timezone, date_time),
+                ),
+                revoke_route: administration_uri!(revoke_api_token(token.id)),
+            })
+            .collect()
+    }
+}
+
+// Render API tokens page, optionally showing a new token
+async fn render_api_tokens(
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+    new_token: Option<String>,
+) -> Result<Template, InternalError> {
+    let tokens = ApiTokenView::tokens(
+        locale,
+        state.timezone,
+        query_error(query_api_tokens(&state.pool, organisation.id), uri).await?,
+    );
+
+    Ok(Template::render(
+        "api_tokens",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            no_tokens_message: tokens.is_empty().then(|| locale.text(EMPTY_TOKENS_MESSAGE)),
+            tokens,
+            create_route: administration_uri!(create_api_token),
+            name_message: locale.text(NAME_MESSAGE),
+            scope_message: locale.text(SCOPE_MESSAGE),
+            public_scope_message: locale.text(PUBLIC_SCOPE_MESSAGE),
+            administration_scope_message: locale.text(ADMINISTRATION_SCOPE_MESSAGE),
+            create_message: locale.text(CREATE_MESSAGE),
+            new_token_message: locale.text(NEW_TOKEN_MESSAGE),
+            new_token,
+            revoke_message: locale.text(REVOKE_MESSAGE),
+            openapi_route: uri!(crate::API_ROUTE, super::openapi),
+        },
+    ))
+}
+
+#[get("/api")]
+pub(crate) async fn show_api_tokens(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    render_api_tokens(state, organisation, uri, locale, None).await
+}
+
+#[derive(FromForm)]
+struct NewApiToken<'r> {
+    #[field(validate = len(1..=60))]
+    name: &'r str,
+    is_administration: bool,
+}
+
+#[post("/api", data = "<token_form>")]
+async fn create_api_token(
+    token_form: Form<NewApiToken<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Only the token hash is saved, the token is shown once
+    let token = random_token();
+    query_error(
+        insert_api_token(
+            &state.pool,
+            organisation.id,
+            token_form.name.trim(),
+            &hash_token(&token),
+            token_form.is_administration,
+        ),
+        uri,
+    )
+    .await?;
+
+    render_api_tokens(state, organisation, uri, locale, Some(token)).await
+}
+
+#[delete("/api/<id>")]
+async fn revoke_api_token(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(delete_api_token(&state.pool, organisation.id, id), uri).await?;
+
+    // Redirect to API tokens page
+    Ok(Redirect::to(administration_uri!(show_api_tokens)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_api_tokens, create_api_token, revoke_api_token]
+}
@@ -0,0 +1,295 @@
+use anyhow::Result;
+
+use chrono::{Days, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::response::Redirect;
+use rocket::tokio::time::{interval, Duration};
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use sqlx::PgPool;
+
+use crate::database::{
+    delete_old_shifts, query_archived_shifts, query_organisations, query_setting, update_setting,
+    Organisation, Setting,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::{format_date_time, Date};
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+// Setting containing the period past shifts are kept for
+const RETENTION_SETTING: &str = "retention_period";
+// Setting containing the retention period in months, saved by older releases
+const MONTHS_RETENTION_SETTING: &str = "retention_months";
+// Setting containing the date and time of the last purge
+const LAST_PURGE_SETTING: &str = "last_purge";
+
+// Period past shifts are kept for
+#[derive(Clone, Copy, PartialEq)]
+enum Retention {
+    Forever,
+    Weeks(u32),
+    Months(u32),
+}
+
+// Retention periods which can be chosen
+const RETENTION_PERIODS: [Retention; 7] = [
+    Retention::Forever,
+    Retention::Weeks(3),
+    Retention::Months(3),
+    Retention::Months(6),
+    Retention::Months(12),
+    Retention::Months(24),
+    Retention::Months(60),
+];
+
+// Retention period used until a referent chooses another one: past shifts are
+// kept, so that statistics and certificates cover them
+const DEFAULT_RETENTION: Retention = Retention::Forever;
+
+impl Retention {
+    // Parse a saved retention period: weeks end with a "w", plain numbers are
+    // months and zero means forever
+    fn parse(value: &str) -> Option<Self> {
+        match value.strip_suffix('w') {
+            Some(weeks) => weeks.parse().ok().map(Self::Weeks),
+            None => match value.parse().ok()? {
+                0 => Some(Self::Forever),
+                months => Some(Self::Months(months)),
+            },
+        }
+    }
+
+    // Value saved inside the settings
+    fn value(self) -> String {
+        match self {
+            Self::Forever => "0".to_string(),
+            Self::Weeks(weeks) => format!("{weeks}w"),
+            Self::Months(months) => months.to_string(),
+        }
+    }
+
+    // Period description
+    fn text(self, locale: Locale) -> String {
+        match self {
+            Self::Forever => locale.text(FOREVER_MESSAGE),
+            Self::Weeks(weeks) => locale.format("archive-weeks", &[("weeks", weeks.into())]),
+            Self::Months(months) if months % 12 == 0 => {
+                locale.format("archive-years", &[("years", (months / 12).into())])
+            }
+            Self::Months(months) => locale.format("archive-months", &[("months", months.into())]),
+        }
+    }
+}
+
+// Hours between two purges of old shifts
+const PURGE_INTERVAL_HOURS: u64 = 24;
+
+// Messages
+const HEADING_MESSAGE: &str = "archive-heading";
+const ARCHIVED_MESSAGE: &str = "archive-archived";
+const OLDEST_MESSAGE: &str = "archive-oldest";
+const LAST_PURGE_MESSAGE: &str = "archive-last-purge";
+const NEVER_MESSAGE: &str = "archive-never";
+const RETENTION_MESSAGE: &str = "archive-retention";
+const FOREVER_MESSAGE: &str = "archive-forever";
+const PURGE_MESSAGE: &str = "archive-purge";
+const SAVE_MESSAGE: &str = "archive-save";
+
+// Key under which a setting of an archive is restored, the retention period
+// of older archives moves to its current key
+pub(crate) fn restored_setting_key(key: &str) -> &str {
+    if key == MONTHS_RETENTION_SETTING {
+        RETENTION_SETTING
+    } else {
+        key
+    }
+}
+
+// Get the period past shifts are kept for
+async fn retention(pool: &PgPool, organisation_id: i32) -> Result<Retention> {
+    Ok(query_setting(pool, organisation_id, RETENTION_SETTING)
+        .await?
+        .and_then(|value| Retention::parse(&value))
+        .unwrap_or(DEFAULT_RETENTION))
+}
+
+// Save a setting outside of a transaction
+async fn save_setting(pool: &PgPool, organisation_id: i32, key: &str, value: String) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    update_setting(
+        &mut conn,
+        organisation_id,
+        &Setting {
+            key: key.to_string(),
+            value,
+        },
+    )
+    .await
+}
+
+// Date before which shifts are deleted, none when they are kept forever
+fn purge_date(timezone: Tz, retention: Retention) -> Option<NaiveDate> {
+    let today = Date::current(timezone).naive_date();
+    match retention {
+        Retention::Forever => None,
+        Retention::Weeks(weeks) => today.checked_sub_days(Days::new(u64::from(weeks) * 7)),
+        Retention::Months(months) => today.checked_sub_months(Months::new(months)),
+    }
+}
+
+// Delete the shifts of an organisation older than its retention period,
+// returning their number
+async fn purge_old_shifts(pool: &PgPool, organisation_id: i32, timezone: Tz) -> Result<u64> {
+    let Some(before) = purge_date(timezone, retention(pool, organisation_id).await?) else {
+        return Ok(0);
+    };
+    let deleted = delete_old_shifts(pool, organisation_id, before).await?;
+    save_setting(
+        pool,
+        organisation_id,
+        LAST_PURGE_SETTING,
+        Utc::now().to_rfc3339(),
+    )
+    .await?;
+    Ok(deleted)
+}
+
+// Purge the old shifts of every organisation, returning their number
+async fn purge_organisations_shifts(pool: &PgPool, timezone: Tz) -> Result<u64> {
+    let mut deleted = 0;
+    for organisation in query_organisations(pool).await? {
+        deleted += purge_old_shifts(pool, organisation.id, timezone).await?;
+    }
+    Ok(deleted)
+}
+
+// Fairing which periodically purges old shifts once the server is started
+pub(crate) fn purge_job() -> AdHoc {
+    AdHoc::on_liftoff("Old shifts purge", |rocket| {
+        Box::pin(async move {
+            let Some(state) = rocket.state::<AppState>() else {
+                return;
+            };
+            let pool = state.pool.clone();
+            let timezone = state.timezone;
+            rocket::tokio::spawn(async move {
+                let mut timer = interval(Duration::from_secs(PURGE_INTERVAL_HOURS * 3600));
+                loop {
+                    timer.tick().await;
+                    match purge_organisations_shifts(&pool, timezone).await {
+                        Ok(0) => {}
+                        Ok(deleted) => info!("Deleted {deleted} old shifts"),
+                        Err(e) => error!("Error deleting old shifts: {e}"),
+                    }
+                }
+            });
+        })
+    })
+}
+
+#[get("/archivio")]
+pub(crate) async fn show_archive(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let today = Date::current(state.timezone).naive_date();
+    let (archived, oldest) = query_error(
+        query_archived_shifts(&state.pool, organisation.id, today),
+        uri,
+    )
+    .await?;
+    let retention = query_error(retention(&state.pool, organisation.id), uri).await?;
+    let last_purge = query_error(
+        query_setting(&state.pool, organisation.id, LAST_PURGE_SETTING),
+        uri,
+    )
+    .await?
+    .and_then(|date_time| date_time.parse().ok())
+    .map_or(locale.text(NEVER_MESSAGE), |date_time| {
+        format_date_time(state.timezone, date_time)
+    });
+
+    // Retention periods, marking the chosen one
+    let periods: Vec<(String, String, bool)> = RETENTION_PERIODS
+        .into_iter()
+        .map(|period| (period.value(), period.text(locale), period == retention))
+        .collect();
+
+    Ok(Template::render(
+        "archive",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            archived_message: locale.text(ARCHIVED_MESSAGE),
+            archived,
+            oldest_message: locale.text(OLDEST_MESSAGE),
+            oldest: oldest.map(|date| date.format("%d/%m/%Y").to_string()),
+            last_purge_message: locale.text(LAST_PURGE_MESSAGE),
+            last_purge,
+            retention_route: administration_uri!(update_retention),
+            retention_message: locale.text(RETENTION_MESSAGE),
+            periods,
+            purge_message: locale.text(PURGE_MESSAGE),
+            save_message: locale.text(SAVE_MESSAGE),
+        },
+    ))
+}
+
+#[derive(FromForm)]
+struct RetentionForm<'r> {
+    period: &'r str,
+}
+
+#[put("/archivio", data = "<retention_form>")]
+async fn update_retention(
+    retention_form: Form<RetentionForm<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Only the proposed periods can be chosen
+    let Some(retention) =
+        Retention::parse(retention_form.period).filter(|period| RETENTION_PERIODS.contains(period))
+    else {
+        return Err(InternalError::text(uri, "archive-invalid-retention"));
+    };
+
+    query_error(
+        save_setting(
+            &state.pool,
+            organisation.id,
+            RETENTION_SETTING,
+            retention.value(),
+        ),
+        uri,
+    )
+    .await?;
+
+    // Redirect to archive page
+    Ok(Redirect::to(administration_uri!(show_archive)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_archive, update_retention]
+}
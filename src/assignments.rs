@@ -0,0 +1,351 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use sqlx::PgPool;
+
+use crate::api::ShiftRecord;
+use crate::data::{book_shifts, check_shift, Locations, ShiftRejection, Tasks};
+use crate::database::{
+    insert_db_date, query_tasks, query_volunteers, Organisation, Shift, ShiftQuery,
+    VolunteerManager, DEFAULT_LOCATION,
+};
+use crate::download_database::{parse_date, DATE_FORMAT};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::{format_date, Date};
+use crate::webhooks::{send_event, WebhookEvent};
+use crate::{AppState, ADMINISTRATION_ROUTE, DISABLED_MESSAGE};
+
+// Messages
+const HEADING_MESSAGE: &str = "assignments-heading";
+const SEARCH_MESSAGE: &str = "assignments-search";
+const ASSIGN_MESSAGE: &str = "assignments-assign";
+const DONE_MESSAGE: &str = "assignments-done";
+const NO_VOLUNTEERS_MESSAGE: &str = "assignments-no-volunteers";
+
+// Day which can be chosen
+#[derive(Serialize)]
+struct AssignmentDay {
+    // Date, in the format used by forms
+    value: String,
+    // Date text
+    text: String,
+}
+
+impl AssignmentDay {
+    // Days going from the current date until Saturday of next week,
+    // Sundays excluded, as for the shifts booked by volunteers
+    fn days(locale: Locale, current_date: &Date) -> Vec<Self> {
+        let Some(last_day) = insert_db_date(current_date.next_week().day_date(5)) else {
+            return Vec::new();
+        };
+        current_date
+            .naive_date()
+            .iter_days()
+            .take_while(|date| *date <= last_day)
+            .filter(|date| date.weekday() != Weekday::Sun)
+            .map(|date| Self {
+                value: date.format(DATE_FORMAT).to_string(),
+                text: format_date(locale, &date),
+            })
+            .collect()
+    }
+}
+
+// Volunteers assigned to a shift, divided by result
+#[derive(Default)]
+struct AssignmentSummary {
+    // Volunteers whose shift has been booked
+    booked: Vec<String>,
+    // Volunteers who had already booked the same task in the same day
+    already_booked: Vec<String>,
+    // Disabled volunteers, who cannot book shifts
+    disabled: Vec<String>,
+    // Volunteers exceeding the capacity of the location
+    full: Vec<String>,
+    // Volunteers whose shift has been rejected for other reasons, as when
+    // they have been removed in the meantime
+    rejected: Vec<String>,
+}
+
+impl AssignmentSummary {
+    // Summary lines shown in the page, only for the results which happened
+    fn lines(&self, locale: Locale) -> Vec<(String, String)> {
+        [
+            ("assignments-booked", &self.booked),
+            ("assignments-already-booked", &self.already_booked),
+            ("assignments-disabled", &self.disabled),
+            ("assignments-full", &self.full),
+            ("assignments-rejected", &self.rejected),
+        ]
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(id, names)| (locale.text(id), names.join(", ")))
+        .collect()
+    }
+}
+
+// Volunteer name as shown in the summary
+fn volunteer_name(volunteer: &VolunteerManager) -> String {
+    format!(
+        "{} {} ({})",
+        volunteer.volunteer.surname, volunteer.volunteer.name, volunteer.volunteer.card_id
+    )
+}
+
+// Book the same shift for more volunteers inside a single transaction,
+// applying the same checks of the shifts booked by volunteers. Volunteers who
+// are disabled or who had already booked the task in the same day are
+// skipped, as the ones exceeding the capacity of the location.
+async fn assign_shift(
+    pool: &PgPool,
+    organisation_id: i32,
+    template: &Shift,
+    volunteers: &[&VolunteerManager],
+    tasks: &Tasks,
+    locations: &Locations,
+    current_date: &Date,
+) -> Result<(AssignmentSummary, Vec<ShiftQuery>)> {
+    let shifts: Vec<Shift> = volunteers
+        .iter()
+        .map(|volunteer| Shift {
+            card_id: volunteer.volunteer.card_id,
+            ..template.clone()
+        })
+        .collect();
+
+    let mut tx = pool.begin().await?;
+    let results = book_shifts(
+        &mut tx,
+        organisation_id,
+        &shifts,
+        tasks,
+        locations,
+        current_date,
+    )
+    .await?;
+    tx.commit().await?;
+
+    let mut summary = AssignmentSummary::default();
+    let mut inserted = Vec::new();
+    for ((volunteer, shift), result) in volunteers.iter().zip(shifts).zip(results) {
+        let name = volunteer_name(volunteer);
+        match result {
+            Ok(id) => {
+                summary.booked.push(name);
+                inserted.push(ShiftQuery { id, shift });
+            }
+            Err(ShiftRejection::AlreadyBooked) => summary.already_booked.push(name),
+            Err(ShiftRejection::Disabled) => summary.disabled.push(name),
+            Err(ShiftRejection::Full) => summary.full.push(name),
+            Err(_) => summary.rejected.push(name),
+        }
+    }
+
+    Ok((summary, inserted))
+}
+
+// Result of an assignment shown in the page
+enum AssignmentResult {
+    Done(AssignmentSummary),
+    Rejected(String),
+}
+
+// Render the assignments page, optionally with the result of an assignment
+async fn render_assignments(
+    state: &AppState,
+    organisation: &Organisation,
+    locale: Locale,
+    result: Option<AssignmentResult>,
+) -> Result<Template> {
+    let current_date = Date::current(state.timezone);
+    let tasks = query_tasks(&state.pool, organisation.id).await?;
+    let locations = Locations::load(&state.pool, organisation.id).await?;
+    let volunteers = query_volunteers(&state.pool, organisation.id).await?;
+
+    let (summary, result_message, error_message) = match result {
+        Some(AssignmentResult::Done(summary)) => {
+            (summary.lines(locale), Some(locale.text(DONE_MESSAGE)), None)
+        }
+        Some(AssignmentResult::Rejected(message)) => (Vec::new(), None, Some(message)),
+        None => (Vec::new(), None, None),
+    };
+
+    Ok(Template::render(
+        "assignments",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            assign_route: administration_uri!(show_assignments),
+            day_text: locale.text("label-choose-day"),
+            days: AssignmentDay::days(locale, &current_date),
+            task_text: locale.text("label-choose-task"),
+            tasks,
+            location_text: locale.text("label-choose-location"),
+            // The location is chosen only when there are more of them
+            choose_location: locations.render().len() > 1,
+            locations: locations.render(),
+            search_message: locale.text(SEARCH_MESSAGE),
+            volunteers,
+            disabled_message: locale.text(DISABLED_MESSAGE),
+            assign_message: locale.text(ASSIGN_MESSAGE),
+            result_message,
+            error_message,
+            summary,
+        },
+    ))
+}
+
+#[get("/assegna")]
+pub(crate) async fn show_assignments(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(render_assignments(state, organisation, locale, None), uri).await
+}
+
+// Shift assigned to more volunteers
+#[derive(FromForm)]
+struct Assignment<'r> {
+    date: &'r str,
+    task: i16,
+    // Missing when the organisation has a single location
+    location: Option<i16>,
+    card_ids: Vec<i16>,
+}
+
+#[post("/assegna", data = "<assignment_form>")]
+async fn assign_shifts(
+    assignment_form: Form<Assignment<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+
+    // Build the shift, which is the same for every volunteer
+    let date = parse_date(Some(assignment_form.date))
+        .ok()
+        .flatten()
+        .unwrap_or(NaiveDate::MIN);
+    let shift = Shift {
+        date,
+        task: assignment_form.task,
+        card_id: 0,
+        location_id: assignment_form
+            .location
+            .or_else(|| locations.first())
+            .unwrap_or(DEFAULT_LOCATION),
+    };
+
+    // Follow the same rules of the shifts booked by volunteers
+    let current_date = Date::current(state.timezone);
+    if let Err(rejection) = check_shift(&shift, &tasks, &locations, &current_date, &HashSet::new())
+    {
+        let result = AssignmentResult::Rejected(rejection.message(locale));
+        return query_error(
+            render_assignments(state, organisation, locale, Some(result)),
+            uri,
+        )
+        .await;
+    }
+
+    // Only the volunteers of the organisation can be chosen
+    let all_volunteers = query_error(query_volunteers(&state.pool, organisation.id), uri).await?;
+    let volunteers: Vec<&VolunteerManager> = all_volunteers
+        .iter()
+        .filter(|volunteer| {
+            assignment_form
+                .card_ids
+                .contains(&volunteer.volunteer.card_id)
+        })
+        .collect();
+    if volunteers.is_empty() {
+        let result = AssignmentResult::Rejected(locale.text(NO_VOLUNTEERS_MESSAGE));
+        return query_error(
+            render_assignments(state, organisation, locale, Some(result)),
+            uri,
+        )
+        .await;
+    }
+
+    let (summary, inserted) = query_error(
+        assign_shift(
+            &state.pool,
+            organisation.id,
+            &shift,
+            &volunteers,
+            &tasks,
+            &locations,
+            &current_date,
+        ),
+        uri,
+    )
+    .await?;
+
+    // Notify webhooks and visualizer pages about the new shifts
+    for inserted in inserted {
+        send_event(
+            &state.pool,
+            organisation.id,
+            &WebhookEvent::ShiftCreated(ShiftRecord::new(
+                &tasks,
+                &locations,
+                inserted.id,
+                &inserted.shift,
+            )),
+        )
+        .await;
+
+        // The shift is already saved, so failures are only logged
+        if let Err(e) = state
+            .sender
+            .shift_added(&state.pool, organisation.id, &inserted.shift)
+            .await
+        {
+            error!("Error notifying visualizer pages: {e}");
+        }
+    }
+
+    query_error(
+        render_assignments(
+            state,
+            organisation,
+            locale,
+            Some(AssignmentResult::Done(summary)),
+        ),
+        uri,
+    )
+    .await
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_assignments, assign_shifts]
+}
@@ -0,0 +1,614 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+
+use chrono::{DateTime, Utc};
+
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::http::uri::Origin;
+use rocket::http::ContentType;
+use rocket::serde::json;
+use rocket::tokio::io::AsyncReadExt;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::{Deserialize, Serialize};
+
+use sqlx::PgPool;
+
+use crate::archive::restored_setting_key;
+use crate::database::{
+    delete_all_preferences_changes, delete_all_shifts, delete_all_volunteer_notes,
+    delete_all_volunteer_overrides, delete_location_tasks, delete_other_tasks_locations,
+    delete_other_volunteers, insert_missing_shift, insert_missing_volunteer_note,
+    insert_requested_change, query_every_shift, query_every_volunteer_note,
+    query_every_volunteer_preferences, query_location_tasks, query_locations,
+    query_requested_changes, query_settings, query_tasks, query_volunteer_overrides,
+    query_volunteers, set_volunteer_preferences, update_setting, upsert_location,
+    upsert_location_task, upsert_task, upsert_volunteer, upsert_volunteer_override, CardNote,
+    CardPreferences, Location, LocationTask, Organisation, RequestedChange, Setting, Shift, Task,
+    VolunteerManager, VolunteerOverride,
+};
+use crate::download_database::ExportFile;
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+// Version of the archive format, increase it at each change
+const ARCHIVE_VERSION: u32 = 7;
+// Oldest archive version which can still be restored
+const MIN_ARCHIVE_VERSION: u32 = 1;
+// First archive version containing tasks and locations
+const LOCATIONS_ARCHIVE_VERSION: u32 = 4;
+// First archive version containing the notes about volunteers
+const NOTES_ARCHIVE_VERSION: u32 = 5;
+// First archive version containing the volunteer values changed by referents
+const OVERRIDES_ARCHIVE_VERSION: u32 = 6;
+// First archive version containing the preferences and the data changes
+// asked by volunteers
+const PREFERENCES_ARCHIVE_VERSION: u32 = 7;
+
+// Messages
+const HEADING_MESSAGE: &str = "backup-heading";
+const DOWNLOAD_MESSAGE: &str = "backup-download";
+const ARCHIVE_MESSAGE: &str = "backup-archive";
+const MODE_MESSAGE: &str = "backup-mode";
+const REPLACE_MESSAGE: &str = "backup-replace";
+const MERGE_MESSAGE: &str = "backup-merge";
+const DRY_RUN_MESSAGE: &str = "backup-dry-run";
+const RESTORE_MESSAGE: &str = "backup-restore";
+const DRY_RUN_DONE_MESSAGE: &str = "backup-dry-run-done";
+const RESTORE_DONE_MESSAGE: &str = "backup-restore-done";
+const NOT_ARCHIVE_MESSAGE: &str = "backup-not-archive";
+
+// Archive containing all web app data
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    // Version of the archive format
+    version: u32,
+    // Archive creation date and time
+    created_at: DateTime<Utc>,
+    // All volunteers
+    volunteers: Vec<VolunteerManager>,
+    // All shifts, past ones included. Shifts of archives older than version 3
+    // belong to the default location.
+    shifts: Vec<Shift>,
+    // Administration settings, missing in version 1 archives
+    #[serde(default)]
+    settings: Vec<Setting>,
+    // Tasks, locations and the tasks offered by each location, missing in
+    // archives older than version 4
+    #[serde(default)]
+    tasks: Vec<Task>,
+    #[serde(default)]
+    locations: Vec<Location>,
+    #[serde(default)]
+    location_tasks: Vec<LocationTask>,
+    // Notes of referents about volunteers, missing in archives older than
+    // version 5
+    #[serde(default)]
+    notes: Vec<CardNote>,
+    // Volunteer values changed by referents, which are kept when the
+    // volunteers file is imported again, missing in archives older than
+    // version 6
+    #[serde(default)]
+    overrides: Vec<VolunteerOverride>,
+    // Contact preferences and data changes asked by volunteers, missing in
+    // archives older than version 7
+    #[serde(default)]
+    preferences: Vec<CardPreferences>,
+    #[serde(default)]
+    change_requests: Vec<RequestedChange>,
+}
+
+// Archive version, read before the remaining data
+#[derive(Deserialize)]
+struct ArchiveVersion {
+    version: u32,
+}
+
+impl Archive {
+    // Whether the archive tasks and locations replace the present ones
+    fn replaces_locations(&self, mode: RestoreMode) -> bool {
+        mode == RestoreMode::Replace && self.version >= LOCATIONS_ARCHIVE_VERSION
+    }
+
+    // Parse an archive, checking its version and its content
+    fn parse(
+        locale: Locale,
+        content: &str,
+        mode: RestoreMode,
+        existing_locations: (&[Task], &[Location]),
+        existing_card_ids: HashSet<i16>,
+    ) -> Result<Self> {
+        let version: ArchiveVersion =
+            json::from_str(content).map_err(|_| anyhow!(locale.text(NOT_ARCHIVE_MESSAGE)))?;
+        if !(MIN_ARCHIVE_VERSION..=ARCHIVE_VERSION).contains(&version.version) {
+            return Err(anyhow!(locale.format(
+                "backup-unsupported-version",
+                &[
+                    ("version", version.version.into()),
+                    ("current", ARCHIVE_VERSION.into()),
+                ],
+            )));
+        }
+
+        let archive: Self = json::from_str(content).map_err(|e| {
+            anyhow!(locale.format("backup-invalid-archive", &[("error", e.to_string().into())],))
+        })?;
+
+        // Archive tasks and locations are added to the present ones, unless
+        // they replace them
+        let mut task_ids: HashSet<i16> = archive.tasks.iter().map(|task| task.task_value).collect();
+        let mut location_ids: HashSet<i16> = archive
+            .locations
+            .iter()
+            .map(|location| location.id)
+            .collect();
+        if !archive.replaces_locations(mode) {
+            let (tasks, locations) = existing_locations;
+            task_ids.extend(tasks.iter().map(|task| task.task_value));
+            location_ids.extend(locations.iter().map(|location| location.id));
+        }
+
+        // At least a location is needed to book shifts
+        if location_ids.is_empty() {
+            return Err(anyhow!(locale.text("backup-no-locations")));
+        }
+
+        // Every offered task must refer to an existing task and location
+        for offered in &archive.location_tasks {
+            if !task_ids.contains(&offered.task_id) || !location_ids.contains(&offered.location_id)
+            {
+                return Err(anyhow!(locale.format(
+                    "backup-invalid-location-task",
+                    &[
+                        ("location", offered.location_id.into()),
+                        ("task", offered.task_id.into()),
+                    ],
+                )));
+            }
+        }
+
+        // Every shift must refer to an existing task, location and volunteer
+        let mut card_ids = existing_card_ids;
+        card_ids.extend(
+            archive
+                .volunteers
+                .iter()
+                .map(|volunteer| volunteer.volunteer.card_id),
+        );
+        for shift in &archive.shifts {
+            if !task_ids.contains(&shift.task) {
+                return Err(anyhow!(locale.format(
+                    "backup-invalid-task",
+                    &[("date", shift.date.to_string().into())],
+                )));
+            }
+            if !location_ids.contains(&shift.location_id) {
+                return Err(anyhow!(locale.format(
+                    "backup-invalid-location",
+                    &[("date", shift.date.to_string().into())],
+                )));
+            }
+            if !card_ids.contains(&shift.card_id) {
+                return Err(anyhow!(locale.format(
+                    "backup-missing-card",
+                    &[
+                        ("date", shift.date.to_string().into()),
+                        ("card_id", shift.card_id.into()),
+                    ],
+                )));
+            }
+        }
+
+        // Every note must refer to an existing volunteer
+        for note in &archive.notes {
+            if !card_ids.contains(&note.card_id) {
+                return Err(anyhow!(locale.format(
+                    "backup-note-missing-card",
+                    &[
+                        ("author", note.note.author.clone().into()),
+                        ("card_id", note.card_id.into()),
+                    ],
+                )));
+            }
+        }
+
+        // Every changed value must refer to an existing volunteer
+        for volunteer_override in &archive.overrides {
+            if !card_ids.contains(&volunteer_override.card_id) {
+                return Err(anyhow!(locale.format(
+                    "backup-override-missing-card",
+                    &[("card_id", volunteer_override.card_id.into())],
+                )));
+            }
+        }
+
+        // Every preference and requested change must refer to an existing
+        // volunteer
+        let missing_card_id = archive
+            .preferences
+            .iter()
+            .map(|preferences| preferences.card_id)
+            .chain(archive.change_requests.iter().map(|change| change.card_id))
+            .find(|card_id| !card_ids.contains(card_id));
+        if let Some(card_id) = missing_card_id {
+            return Err(anyhow!(locale.format(
+                "backup-account-missing-card",
+                &[("card_id", card_id.into())],
+            )));
+        }
+
+        Ok(archive)
+    }
+}
+
+// How archive data are restored
+#[derive(FromFormField, Clone, Copy, PartialEq)]
+enum RestoreMode {
+    // Data not contained in the archive are deleted
+    Replace,
+    // Archive data are added to the present ones
+    Merge,
+}
+
+// Changes performed by a restore
+#[derive(Default)]
+struct RestoreSummary {
+    volunteers_added: u64,
+    volunteers_updated: u64,
+    volunteers_removed: u64,
+    shifts_added: u64,
+    shifts_skipped: u64,
+    shifts_removed: u64,
+    settings_restored: u64,
+    notes_added: u64,
+    overrides_restored: u64,
+    preferences_restored: u64,
+    change_requests_restored: u64,
+    tasks_restored: u64,
+    locations_restored: u64,
+}
+
+impl RestoreSummary {
+    // Summary lines shown in the page
+    fn lines(&self, locale: Locale) -> Vec<(String, u64)> {
+        [
+            ("backup-volunteers-added", self.volunteers_added),
+            ("backup-volunteers-updated", self.volunteers_updated),
+            ("backup-volunteers-removed", self.volunteers_removed),
+            ("backup-shifts-added", self.shifts_added),
+            ("backup-shifts-skipped", self.shifts_skipped),
+            ("backup-shifts-removed", self.shifts_removed),
+            ("backup-settings-restored", self.settings_restored),
+            ("backup-notes-added", self.notes_added),
+            ("backup-overrides-restored", self.overrides_restored),
+            ("backup-preferences-restored", self.preferences_restored),
+            (
+                "backup-change-requests-restored",
+                self.change_requests_restored,
+            ),
+            ("backup-tasks-restored", self.tasks_restored),
+            ("backup-locations-restored", self.locations_restored),
+        ]
+        .into_iter()
+        .map(|(id, count)| (locale.text(id), count))
+        .collect()
+    }
+}
+
+// Restore an archive inside a transaction, which is reverted in case of
+// errors or when only a simulation is requested
+async fn restore_archive(
+    pool: &PgPool,
+    organisation_id: i32,
+    archive: &Archive,
+    mode: RestoreMode,
+    dry_run: bool,
+) -> Result<RestoreSummary> {
+    let mut tx = pool.begin().await?;
+    let mut summary = RestoreSummary::default();
+
+    if mode == RestoreMode::Replace {
+        summary.shifts_removed = delete_all_shifts(&mut tx, organisation_id).await?;
+    }
+
+    // Locations and tasks are restored before the shifts referring to them.
+    // When they are replaced, the ones missing in the archive are deleted
+    // once nothing refers to them.
+    let replaces_locations = archive.replaces_locations(mode);
+    if replaces_locations {
+        delete_location_tasks(&mut tx, organisation_id).await?;
+    }
+    for location in &archive.locations {
+        upsert_location(&mut tx, organisation_id, location).await?;
+        summary.locations_restored += 1;
+    }
+    for task in &archive.tasks {
+        upsert_task(&mut tx, organisation_id, task).await?;
+        summary.tasks_restored += 1;
+    }
+    for offered in &archive.location_tasks {
+        upsert_location_task(&mut tx, organisation_id, offered).await?;
+    }
+    if replaces_locations {
+        let task_ids: Vec<i16> = archive.tasks.iter().map(|task| task.task_value).collect();
+        let location_ids: Vec<i16> = archive
+            .locations
+            .iter()
+            .map(|location| location.id)
+            .collect();
+        delete_other_tasks_locations(&mut tx, organisation_id, &task_ids, &location_ids).await?;
+    }
+
+    for volunteer in &archive.volunteers {
+        if upsert_volunteer(&mut tx, organisation_id, volunteer).await? {
+            summary.volunteers_added += 1;
+        } else {
+            summary.volunteers_updated += 1;
+        }
+    }
+
+    if mode == RestoreMode::Replace {
+        let card_ids: Vec<i16> = archive
+            .volunteers
+            .iter()
+            .map(|volunteer| volunteer.volunteer.card_id)
+            .collect();
+        summary.volunteers_removed =
+            delete_other_volunteers(&mut tx, organisation_id, &card_ids).await?;
+    }
+
+    for shift in &archive.shifts {
+        if insert_missing_shift(&mut tx, organisation_id, shift).await? {
+            summary.shifts_added += 1;
+        } else {
+            summary.shifts_skipped += 1;
+        }
+    }
+
+    // Notes are replaced only by archives which contain them
+    if mode == RestoreMode::Replace && archive.version >= NOTES_ARCHIVE_VERSION {
+        delete_all_volunteer_notes(&mut tx, organisation_id).await?;
+    }
+    for note in &archive.notes {
+        if insert_missing_volunteer_note(&mut tx, organisation_id, note).await? {
+            summary.notes_added += 1;
+        }
+    }
+
+    // Changed values are replaced only by archives which contain them
+    if mode == RestoreMode::Replace && archive.version >= OVERRIDES_ARCHIVE_VERSION {
+        delete_all_volunteer_overrides(&mut tx, organisation_id).await?;
+    }
+    for volunteer_override in &archive.overrides {
+        upsert_volunteer_override(&mut tx, organisation_id, volunteer_override).await?;
+        summary.overrides_restored += 1;
+    }
+
+    // Preferences and requested changes are replaced only by archives which
+    // contain them
+    if mode == RestoreMode::Replace && archive.version >= PREFERENCES_ARCHIVE_VERSION {
+        delete_all_preferences_changes(&mut tx, organisation_id).await?;
+    }
+    for preferences in &archive.preferences {
+        set_volunteer_preferences(
+            &mut *tx,
+            organisation_id,
+            preferences.card_id,
+            &preferences.preferences,
+        )
+        .await?;
+        summary.preferences_restored += 1;
+    }
+    for change in &archive.change_requests {
+        insert_requested_change(&mut tx, organisation_id, change).await?;
+        summary.change_requests_restored += 1;
+    }
+
+    for setting in &archive.settings {
+        let setting = Setting {
+            key: restored_setting_key(&setting.key).to_string(),
+            value: setting.value.clone(),
+        };
+        update_setting(&mut tx, organisation_id, &setting).await?;
+        summary.settings_restored += 1;
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+
+    Ok(summary)
+}
+
+// Render the backup page, optionally with the result of a restore
+fn render_backup(
+    locale: Locale,
+    organisation: &Organisation,
+    result: Option<Result<(RestoreSummary, bool)>>,
+) -> Template {
+    let (summary, result_message, error_message) = match result {
+        Some(Ok((summary, dry_run))) => (
+            summary.lines(locale),
+            Some(locale.text(if dry_run {
+                DRY_RUN_DONE_MESSAGE
+            } else {
+                RESTORE_DONE_MESSAGE
+            })),
+            None,
+        ),
+        Some(Err(e)) => (Vec::new(), None, Some(e.to_string())),
+        None => (Vec::new(), None, None),
+    };
+
+    Template::render(
+        "backup",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            download_route: administration_uri!(download_backup),
+            download_message: locale.text(DOWNLOAD_MESSAGE),
+            restore_route: administration_uri!(show_backup),
+            archive_message: locale.text(ARCHIVE_MESSAGE),
+            mode_message: locale.text(MODE_MESSAGE),
+            replace_message: locale.text(REPLACE_MESSAGE),
+            merge_message: locale.text(MERGE_MESSAGE),
+            dry_run_message: locale.text(DRY_RUN_MESSAGE),
+            restore_message: locale.text(RESTORE_MESSAGE),
+            result_message,
+            error_message,
+            summary,
+        },
+    )
+}
+
+#[get("/backup")]
+pub(crate) async fn show_backup(
+    session: Option<Session>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    Ok(render_backup(locale, organisation, None))
+}
+
+#[get("/backup/archivio")]
+async fn download_backup(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<ExportFile, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let archive = Archive {
+        version: ARCHIVE_VERSION,
+        created_at: Utc::now(),
+        volunteers: query_error(query_volunteers(&state.pool, organisation.id), uri).await?,
+        shifts: query_error(query_every_shift(&state.pool, organisation.id), uri).await?,
+        settings: query_error(query_settings(&state.pool, organisation.id), uri).await?,
+        tasks: query_error(query_tasks(&state.pool, organisation.id), uri).await?,
+        locations: query_error(query_locations(&state.pool, organisation.id), uri).await?,
+        location_tasks: query_error(query_location_tasks(&state.pool, organisation.id), uri)
+            .await?,
+        notes: query_error(
+            query_every_volunteer_note(&state.pool, organisation.id),
+            uri,
+        )
+        .await?,
+        overrides: query_error(query_volunteer_overrides(&state.pool, organisation.id), uri)
+            .await?,
+        preferences: query_error(
+            query_every_volunteer_preferences(&state.pool, organisation.id),
+            uri,
+        )
+        .await?,
+        change_requests: query_error(query_requested_changes(&state.pool, organisation.id), uri)
+            .await?,
+    };
+    let content =
+        json::to_pretty_string(&archive).map_err(|e| InternalError::unexpected(uri, e))?;
+
+    Ok(ExportFile::new(
+        content.into_bytes(),
+        ContentType::JSON,
+        format!("backup_{}.json", archive.created_at.format("%Y-%m-%d")),
+    ))
+}
+
+#[derive(FromForm)]
+struct Restore<'r> {
+    archive: TempFile<'r>,
+    mode: RestoreMode,
+    dry_run: bool,
+}
+
+#[post("/backup", data = "<restore_form>")]
+async fn restore_backup(
+    restore_form: Form<Restore<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Read the uploaded archive
+    let mut content = String::new();
+    restore_form
+        .archive
+        .open()
+        .await
+        .map_err(|e| InternalError::unexpected(uri, e))?
+        .read_to_string(&mut content)
+        .await
+        .map_err(|_| InternalError::text(uri, NOT_ARCHIVE_MESSAGE))?;
+
+    // Volunteers which can be referred by merged shifts
+    let existing_card_ids = if restore_form.mode == RestoreMode::Merge {
+        query_error(query_volunteers(&state.pool, organisation.id), uri)
+            .await?
+            .into_iter()
+            .map(|volunteer| volunteer.volunteer.card_id)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    // Shifts can refer to the present tasks and locations, unless the archive
+    // replaces them
+    let tasks = query_error(query_tasks(&state.pool, organisation.id), uri).await?;
+    let locations = query_error(query_locations(&state.pool, organisation.id), uri).await?;
+
+    let result = match Archive::parse(
+        locale,
+        &content,
+        restore_form.mode,
+        (&tasks, &locations),
+        existing_card_ids,
+    ) {
+        Ok(archive) => {
+            let summary = query_error(
+                restore_archive(
+                    &state.pool,
+                    organisation.id,
+                    &archive,
+                    restore_form.mode,
+                    restore_form.dry_run,
+                ),
+                uri,
+            )
+            .await?;
+
+            // Notify changes to the pages showing shifts
+            if !restore_form.dry_run {
+                state.sender.reload(organisation.id);
+            }
+
+            Ok((summary, restore_form.dry_run))
+        }
+        Err(e) => Err(e),
+    };
+
+    Ok(render_backup(locale, organisation, Some(result)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_backup, download_backup, restore_backup]
+}
@@ -0,0 +1,201 @@
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use rocket::http::uri::Origin;
+use rocket::http::ContentType;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use crate::data::Tasks;
+use crate::database::{
+    insert_db_date, query_served_shifts, query_tasks, query_volunteer, Organisation,
+};
+use crate::download_database::{parse_date, ExportFile, DATE_FORMAT, INVALID_DATE_MESSAGE};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::session::{check_volunteer_access, Session};
+use crate::menu::{Menu, MenuAdministration};
+use crate::print::{Certificate, CertificateShift};
+use crate::shifts::shifts_uri;
+use crate::time::{format_date_time, Date};
+use crate::{AppState, SHIFTS_ROUTE};
+
+// Format of dates written in certificates
+const CERTIFICATE_DATE_FORMAT: &str = "%d/%m/%Y";
+
+// Messages
+const HEADING_MESSAGE: &str = "certificate-heading";
+const CERTIFICATE_TITLE: &str = "certificate-title";
+const FROM_MESSAGE: &str = "period-from";
+const TO_MESSAGE: &str = "period-to";
+const PRINT_MESSAGE: &str = "certificate-print";
+const SHIFTS_MESSAGE: &str = "certificate-shifts";
+
+// Format a duration as hours and minutes
+fn format_duration(minutes: u32) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+// Describe a duration in words
+fn describe_duration(locale: Locale, minutes: u32) -> String {
+    locale.format(
+        "certificate-duration-words",
+        &[
+            ("hours", (minutes / 60).into()),
+            ("minutes", (minutes % 60).into()),
+        ],
+    )
+}
+
+// Today and the first day of the current year
+fn current_year_bounds(
+    timezone: Tz,
+    uri: &Origin<'_>,
+) -> Result<(NaiveDate, NaiveDate), InternalError> {
+    let date = Date::current(timezone);
+    insert_db_date((date.year(), 1, 1))
+        .map(|first| (first, date.naive_date()))
+        .ok_or_else(|| InternalError::text(uri, "error-dates"))
+}
+
+#[get("/attestato?<id>")]
+pub(crate) async fn show_certificate(
+    id: i16,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the user or the administrator is authenticated
+    let is_administration = check_volunteer_access(&session, id, uri)?;
+
+    let volunteer = query_error(query_volunteer(&state.pool, organisation.id, id), uri).await?;
+
+    // Propose the current year as period
+    let (from, to) = current_year_bounds(state.timezone, uri)?;
+
+    Ok(Template::render(
+        "certificate",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            is_administration,
+            menu: Menu::render(locale, organisation, id),
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            volunteer_message: format!("({}) {} {}", id, volunteer.surname, volunteer.name),
+            certificate_route: shifts_uri!(download_certificate(id, _)),
+            card_id: id,
+            from_message: locale.text(FROM_MESSAGE),
+            from_value: from.format(DATE_FORMAT).to_string(),
+            to_message: locale.text(TO_MESSAGE),
+            to_value: to.format(DATE_FORMAT).to_string(),
+            max_value: to.format(DATE_FORMAT).to_string(),
+            print_message: locale.text(PRINT_MESSAGE),
+            shifts_route: shifts_uri!(crate::shifts::show_shifts(id)),
+            shifts_message: locale.text(SHIFTS_MESSAGE),
+        },
+    ))
+}
+
+// Period chosen in the certificate page
+#[derive(FromForm, Default)]
+struct CertificatePeriod<'r> {
+    from: Option<&'r str>,
+    to: Option<&'r str>,
+}
+
+#[get("/attestato/pdf?<id>&<period..>")]
+async fn download_certificate(
+    id: i16,
+    period: Option<CertificatePeriod<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<ExportFile, InternalError> {
+    // Check if the user or the administrator is authenticated
+    check_volunteer_access(&session, id, uri)?;
+
+    // Only shifts already served can be certified
+    let (first_day, today) = current_year_bounds(state.timezone, uri)?;
+    let period = period.unwrap_or_default();
+    let from = parse_date(period.from)
+        .map_err(|_| InternalError::text(uri, INVALID_DATE_MESSAGE))?
+        .unwrap_or(first_day);
+    let to = parse_date(period.to)
+        .map_err(|_| InternalError::text(uri, INVALID_DATE_MESSAGE))?
+        .map_or(today, |to| to.min(today));
+    if from > to {
+        return Err(InternalError::text(uri, "error-date-order"));
+    }
+
+    let volunteer = query_error(query_volunteer(&state.pool, organisation.id, id), uri).await?;
+    let shifts = query_error(
+        query_served_shifts(&state.pool, organisation.id, id, from, to),
+        uri,
+    )
+    .await?;
+    if shifts.is_empty() {
+        return Err(InternalError::text(uri, "certificate-empty"));
+    }
+
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let total: u32 = shifts
+        .iter()
+        .map(|shift| tasks.minutes_from_id(shift.task))
+        .sum();
+    let shifts = shifts
+        .into_iter()
+        .map(|shift| CertificateShift {
+            date: shift.date.format(CERTIFICATE_DATE_FORMAT).to_string(),
+            task: tasks.task_from_id(shift.task),
+            hours: tasks.hours_from_id(shift.task),
+            duration: format_duration(tasks.minutes_from_id(shift.task)),
+        })
+        .collect();
+
+    let certificate = Certificate {
+        association_name: organisation.name.clone(),
+        title: locale.text(CERTIFICATE_TITLE),
+        statement: locale.format(
+            "certificate-statement",
+            &[
+                ("name", volunteer.name.as_str().into()),
+                ("surname", volunteer.surname.as_str().into()),
+                ("card_id", volunteer.card_id.to_string().into()),
+                ("association", organisation.name.as_str().into()),
+                (
+                    "from",
+                    from.format(CERTIFICATE_DATE_FORMAT).to_string().into(),
+                ),
+                ("to", to.format(CERTIFICATE_DATE_FORMAT).to_string().into()),
+                ("duration", describe_duration(locale, total).into()),
+            ],
+        ),
+        shifts,
+        total: format_duration(total),
+        generated_at: format_date_time(state.timezone, Utc::now()),
+        locale,
+    };
+    let content = certificate
+        .pdf()
+        .map_err(|e| InternalError::unexpected(uri, e))?;
+
+    Ok(ExportFile::inline(
+        content,
+        ContentType::PDF,
+        format!(
+            "attestato_{}_{}_{}.pdf",
+            volunteer.card_id,
+            from.format(DATE_FORMAT),
+            to.format(DATE_FORMAT)
+        ),
+    ))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_certificate, download_certificate]
+}
@@ -0,0 +1,193 @@
+use chrono_tz::Tz;
+
+use rocket::http::uri::Origin;
+use rocket::response::Redirect;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::database::{
+    approve_change_request, query_change_requests, reject_change_request, ChangeRequest,
+    Organisation,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::format_date_time;
+use crate::volunteer_profile::notify_volunteer;
+use crate::{AppState, ADMINISTRATION_ROUTE, VOLUNTEERS_ROUTE};
+
+// Messages
+const HEADING_MESSAGE: &str = "change-requests-heading";
+const EXPLAIN_MESSAGE: &str = "change-requests-explain";
+const EMPTY_MESSAGE: &str = "change-requests-empty";
+const SURNAME_MESSAGE: &str = "change-requests-surname";
+const NAME_MESSAGE: &str = "change-requests-name";
+const PHONE_MESSAGE: &str = "change-requests-phone";
+const CURRENT_MESSAGE: &str = "change-requests-current";
+const REQUESTED_MESSAGE: &str = "change-requests-requested";
+const APPROVE_MESSAGE: &str = "change-requests-approve";
+const REJECT_MESSAGE: &str = "change-requests-reject";
+const PROFILE_MESSAGE: &str = "change-requests-profile";
+
+// Value of the volunteer data, compared with the requested one
+#[derive(Serialize)]
+struct ChangedValue {
+    // Field name
+    field: String,
+    // Value currently saved
+    current: String,
+    // Requested value
+    requested: String,
+    // Whether the value changes
+    is_changed: bool,
+}
+
+impl ChangedValue {
+    fn new(field: String, current: String, requested: String) -> Self {
+        Self {
+            is_changed: current != requested,
+            field,
+            current,
+            requested,
+        }
+    }
+}
+
+// Data change asked by a volunteer and the routes to handle it
+#[derive(Serialize)]
+struct RequestView {
+    // Card identification
+    card_id: i16,
+    // Volunteer surname and name, as currently saved
+    volunteer: String,
+    // Creation date and time
+    created_at: String,
+    // Compared values
+    values: Vec<ChangedValue>,
+    // Route to the volunteer profile
+    profile_route: Origin<'static>,
+    // Route to approve or reject the request
+    request_route: Origin<'static>,
+}
+
+impl RequestView {
+    fn requests(locale: Locale, timezone: Tz, requests: Vec<ChangeRequest>) -> Vec<Self> {
+        requests
+            .into_iter()
+            .map(|request| Self {
+                card_id: request.card_id,
+                volunteer: format!("{} {}", request.current_surname, request.current_name),
+                created_at: format_date_time(timezone, request.created_at),
+                values: vec![
+                    ChangedValue::new(
+                        locale.text(SURNAME_MESSAGE),
+                        request.current_surname,
+                        request.surname,
+                    ),
+                    ChangedValue::new(
+                        locale.text(NAME_MESSAGE),
+                        request.current_name,
+                        request.name,
+                    ),
+                    ChangedValue::new(
+                        locale.text(PHONE_MESSAGE),
+                        request.current_phone_number,
+                        request.phone_number,
+                    ),
+                ],
+                profile_route: rocket::uri!(
+                    VOLUNTEERS_ROUTE,
+                    crate::volunteer_profile::show_profile(request.card_id)
+                ),
+                request_route: administration_uri!(approve_request(request.id)),
+            })
+            .collect()
+    }
+}
+
+#[get("/richieste")]
+pub(crate) async fn show_change_requests(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let requests = RequestView::requests(
+        locale,
+        state.timezone,
+        query_error(query_change_requests(&state.pool, organisation.id), uri).await?,
+    );
+
+    Ok(Template::render(
+        "change_requests",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            explain_message: locale.text(EXPLAIN_MESSAGE),
+            no_requests_message: requests.is_empty().then(|| locale.text(EMPTY_MESSAGE)),
+            requests,
+            current_message: locale.text(CURRENT_MESSAGE),
+            requested_message: locale.text(REQUESTED_MESSAGE),
+            approve_message: locale.text(APPROVE_MESSAGE),
+            reject_message: locale.text(REJECT_MESSAGE),
+            profile_message: locale.text(PROFILE_MESSAGE),
+        },
+    ))
+}
+
+// The requested values are saved as local changes, so they are kept when
+// the volunteers file is imported again
+#[put("/richieste/<id>")]
+async fn approve_request(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    if let Some(card_id) = query_error(
+        approve_change_request(&state.pool, organisation.id, id),
+        uri,
+    )
+    .await?
+    {
+        notify_volunteer(state, organisation.id, card_id).await;
+    }
+
+    // Redirect to change requests page
+    Ok(Redirect::to(administration_uri!(show_change_requests)))
+}
+
+#[delete("/richieste/<id>")]
+async fn reject_request(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(reject_change_request(&state.pool, organisation.id, id), uri).await?;
+
+    // Redirect to change requests page
+    Ok(Redirect::to(administration_uri!(show_change_requests)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_change_requests, approve_request, reject_request]
+}
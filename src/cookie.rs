@@ -7,37 +7,39 @@ use rocket_dyn_templates::{context, Template};
 
 use serde::Serialize;
 
-use crate::{AppState, APP_TITLE, COOKIE_ROUTE, POLICY_COOKIE};
+use crate::database::Organisation;
+use crate::locale::Locale;
+use crate::{AppState, COOKIE_ROUTE, POLICY_COOKIE};
 
 #[derive(Serialize, Default)]
 pub(crate) struct CookieMessage {
     // Check whether it is the first time for cookie popup
     is_first: bool,
     // Cookie title
-    title: Option<&'static str>,
+    title: Option<String>,
     // Route
     route: Option<Origin<'static>>,
     // Link text
-    link: Option<&'static str>,
+    link: Option<String>,
     // Accept button
-    accept: Option<&'static str>,
+    accept: Option<String>,
     // Hide cookie dialog route
     hide_dialog_route: Option<String>,
     // Decline button
-    decline: Option<&'static str>,
+    decline: Option<String>,
 }
 
 impl CookieMessage {
-    pub(crate) fn render(jar: &CookieJar<'_>, uri: Option<&str>) -> Self {
+    pub(crate) fn render(locale: Locale, jar: &CookieJar<'_>, uri: Option<&str>) -> Self {
         if jar.get(POLICY_COOKIE).is_none() {
             Self {
                 is_first: true,
-                title: Some("Questo sito usa i cookie!"),
+                title: Some(locale.text("cookie-title")),
                 route: Some(COOKIE_ROUTE),
-                link: Some("Ma cosa sono i cookie?"),
+                link: Some(locale.text("cookie-link")),
                 hide_dialog_route: Some(format!("{COOKIE_ROUTE}{}", uri.unwrap_or_default())),
-                accept: Some("Accetto"),
-                decline: Some("Declino"),
+                accept: Some(locale.text("cookie-accept")),
+                decline: Some(locale.text("cookie-decline")),
             }
         } else {
             Self::default()
@@ -58,11 +60,16 @@ async fn hide_pages_cookie_dialog(redirect: &str, jar: &CookieJar<'_>) -> Redire
 }
 
 #[get("/")]
-async fn show_cookie_policy(state: &State<AppState>) -> Template {
+async fn show_cookie_policy(
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Template {
     Template::render(
         "cookie_policy",
         context! {
-            title: APP_TITLE,
+            lang: locale.code(),
+            title: &organisation.name,
             website_link: &state.website,
             email: &state.email,
         },
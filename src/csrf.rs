@@ -0,0 +1,301 @@
+use std::io::Cursor;
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::form::Form;
+use rocket::http::ext::IntoOwned;
+use rocket::http::uri::Origin;
+use rocket::http::{Method, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response};
+
+use crate::error::InternalError;
+use crate::token::{random_token, tokens_match};
+use crate::{API_ROUTE, CSRF_ROUTE};
+
+// Cookie containing the session token
+const CSRF_COOKIE: &str = "csrf";
+// Hidden form field containing the token
+const CSRF_FIELD: &str = "csrf_token";
+// Header containing the token for requests which are not sent by forms
+const CSRF_HEADER: &str = "X-CSRF-Token";
+// Hidden form field containing the method of the request
+const METHOD_FIELD: &str = "_method";
+// Maximum number of bytes which can be peeked from a request body, Rocket
+// does not allow to peek more
+const PEEK_BYTES: usize = 512;
+
+// Token associated to the current session
+pub(crate) struct CsrfToken(String);
+
+impl CsrfToken {
+    // Token value, sent by scripts through the header
+    pub(crate) fn value(&self) -> &str {
+        &self.0
+    }
+}
+
+// Pages sending requests through scripts need the token to fill the header
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r CsrfToken {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(req.local_cache(|| CsrfToken(String::new())))
+    }
+}
+
+// Original uri of a rejected request
+pub(crate) struct RejectedUri(Option<Origin<'static>>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r RejectedUri {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let rejected = req.local_cache(|| RejectedUri(None));
+        if rejected.0.is_some() {
+            Outcome::Success(rejected)
+        } else {
+            Outcome::Forward(Status::NotFound)
+        }
+    }
+}
+
+// Check whether the token sent with a request is the session one
+async fn is_valid_token(req: &Request<'_>, data: &mut Data<'_>, token: &str) -> bool {
+    // Token sent through an header
+    if let Some(header) = req.headers().get_one(CSRF_HEADER) {
+        return tokens_match(header, token);
+    }
+
+    // Token sent through a form. The token field is always the first one of
+    // a form, so it is enough to peek the first bytes.
+    if req.content_type().is_some_and(|content| content.is_form()) {
+        return std::str::from_utf8(data.peek(PEEK_BYTES).await)
+            .ok()
+            .and_then(form_token)
+            .is_some_and(|value| tokens_match(value, token));
+    }
+
+    // Token sent through a form containing files
+    if req
+        .content_type()
+        .is_some_and(|content| content.is_form_data())
+    {
+        return multipart_token(data.peek(PEEK_BYTES).await)
+            .is_some_and(|value| tokens_match(value, token));
+    }
+
+    false
+}
+
+// Retrieve the token from the first field of a form
+fn form_token(form: &str) -> Option<&str> {
+    Form::values(form)
+        .next()
+        .filter(|field| field.name == CSRF_FIELD)
+        .map(|field| field.value)
+}
+
+// Retrieve the method from the field following the token. Rocket looks for
+// the method only inside the first field of a form, where the token is.
+fn form_method(form: &str) -> Option<Method> {
+    Form::values(form)
+        .nth(1)
+        .filter(|field| field.name == METHOD_FIELD)
+        .and_then(|field| field.value.parse().ok())
+}
+
+// Method requested by a form sent through a POST request
+async fn overridden_method(req: &Request<'_>, data: &mut Data<'_>) -> Option<Method> {
+    if req.method() != Method::Post || !req.content_type().is_some_and(|content| content.is_form())
+    {
+        return None;
+    }
+    std::str::from_utf8(data.peek(PEEK_BYTES).await)
+        .ok()
+        .and_then(form_method)
+}
+
+// Retrieve the token from the first part of a multipart body
+fn multipart_token(body: &[u8]) -> Option<&str> {
+    let body = std::str::from_utf8(body)
+        .or_else(|e| std::str::from_utf8(&body[..e.valid_up_to()]))
+        .ok()?;
+    let (headers, rest) = body.split_once("\r\n\r\n")?;
+    headers
+        .contains(&format!(r#"name="{CSRF_FIELD}""#))
+        .then(|| rest.split_once("\r\n").map(|(value, _)| value))
+        .flatten()
+}
+
+// Insert a hidden field containing the token at the beginning of every form
+// of a page, so that it is always inside the peeked bytes of a request
+fn insert_token(html: &str, token: &str) -> String {
+    let input = format!(r#"<input type="hidden" name="{CSRF_FIELD}" value="{token}">"#);
+    let mut page = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<form") {
+        // End of the form opening tag
+        let Some(end) = rest[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        page.push_str(&rest[..end]);
+
+        // Forms sent through GET requests do not change data, and the token
+        // would be exposed inside their addresses
+        if rest[start..end].to_lowercase().contains(r#"method="post""#) {
+            page.push_str(&input);
+        }
+        rest = &rest[end..];
+    }
+    page.push_str(rest);
+    page
+}
+
+// Fairing which protects state-changing requests from cross-site forgeries
+pub(crate) struct Csrf;
+
+#[rocket::async_trait]
+impl Fairing for Csrf {
+    fn info(&self) -> Info {
+        Info {
+            name: "CSRF protection",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, data: &mut Data<'_>) {
+        // API requests are authenticated through tokens sent inside an
+        // header, which browsers never add on their own
+        if req.uri().path().starts_with(API_ROUTE.path().as_str()) {
+            return;
+        }
+
+        // Retrieve the session token or create a new one
+        let token = if let Some(cookie) = req.cookies().get_private(CSRF_COOKIE) {
+            cookie.value().to_string()
+        } else {
+            let token = random_token();
+            req.cookies().add_private((CSRF_COOKIE, token.clone()));
+            token
+        };
+
+        // Forms place the method field after the token, so the method is
+        // overridden here instead of by Rocket
+        if let Some(method) = overridden_method(req, data).await {
+            req.set_method(method);
+        }
+
+        // Redirect requests which change data without a valid token
+        // to the error route
+        if matches!(
+            req.method(),
+            Method::Post | Method::Put | Method::Delete | Method::Patch
+        ) && !is_valid_token(req, data, &token).await
+        {
+            let uri = req.uri().clone().into_owned();
+            req.local_cache(|| RejectedUri(Some(uri)));
+            req.set_method(Method::Get);
+            req.set_uri(CSRF_ROUTE);
+        }
+
+        req.local_cache(|| CsrfToken(token));
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        // Only HTML pages contain forms
+        if !res.content_type().is_some_and(|content| content.is_html()) {
+            return;
+        }
+
+        let token = req.local_cache(|| CsrfToken(String::new()));
+        if token.0.is_empty() {
+            return;
+        }
+
+        if let Ok(body) = res.body_mut().to_string().await {
+            let body = insert_token(&body, &token.0);
+            res.set_sized_body(body.len(), Cursor::new(body));
+        }
+    }
+}
+
+#[get("/")]
+async fn reject_request(rejected: &RejectedUri) -> InternalError {
+    // The rejected uri is always present, otherwise the route is forwarded
+    let route = CSRF_ROUTE;
+    InternalError::invalid_csrf_token(rejected.0.as_ref().unwrap_or(&route))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![reject_request]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    #[test]
+    fn form_token_mismatch() {
+        let form = "csrf_token=fedcba9876543210&_method=put";
+        assert!(!form_token(form).is_some_and(|value| tokens_match(value, TOKEN)));
+        assert!(!tokens_match("0123456789abcdeF", TOKEN));
+        assert!(!tokens_match("0123456789abcde", TOKEN));
+    }
+
+    #[test]
+    fn form_token_missing() {
+        assert_eq!(form_token(""), None);
+        assert_eq!(form_token("_method=delete&card_id=3"), None);
+        assert_eq!(multipart_token(b""), None);
+    }
+
+    #[test]
+    fn form_token_and_method() {
+        let form = format!("csrf_token={TOKEN}&_method=delete&card_id=3");
+        assert_eq!(form_token(&form), Some(TOKEN));
+        assert_eq!(form_method(&form), Some(Method::Delete));
+        assert_eq!(form_method(&format!("csrf_token={TOKEN}&card_id=3")), None);
+    }
+
+    #[test]
+    fn multipart_token_first_part() {
+        let body = format!(
+            "--boundary\r\nContent-Disposition: form-data; name=\"csrf_token\"\r\n\r\n{TOKEN}\r\n\
+             --boundary\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\ndata\r\n"
+        );
+        assert_eq!(multipart_token(body.as_bytes()), Some(TOKEN));
+    }
+
+    #[test]
+    fn token_beyond_peek_window() {
+        // A token following other fields could be cut out of the peeked bytes
+        let form = format!("note={}&csrf_token={TOKEN}", "a".repeat(PEEK_BYTES));
+        assert_eq!(form_token(&form[..PEEK_BYTES]), None);
+
+        // Inserted tokens come first, so they are always peeked
+        let page = format!(
+            r#"<form action="/" method="post"><input type="hidden" name="_method" value="put"><textarea name="note">{}</textarea></form>"#,
+            "a".repeat(PEEK_BYTES)
+        );
+        let page = insert_token(&page, TOKEN);
+        let form = format!(
+            "csrf_token={TOKEN}&_method=put&note={}",
+            "a".repeat(PEEK_BYTES)
+        );
+        assert!(page.contains(&format!(
+            r#"method="post"><input type="hidden" name="csrf_token" value="{TOKEN}"><input type="hidden" name="_method""#
+        )));
+        assert_eq!(form_token(&form[..PEEK_BYTES]), Some(TOKEN));
+        assert_eq!(form_method(&form[..PEEK_BYTES]), Some(Method::Put));
+    }
+
+    #[test]
+    fn get_forms_without_token() {
+        let page = r#"<form action="/" method="get"><input name="q"></form>"#;
+        assert_eq!(insert_token(page, TOKEN), page);
+    }
+}
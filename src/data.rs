@@ -1,9 +1,20 @@
 use std::collections::HashSet;
 
+use anyhow::Result;
+
+use chrono::{Datelike, Weekday};
+
 use serde::Serialize;
 
-use crate::database::{insert_db_date, Shift};
+use sqlx::{PgConnection, PgPool};
+
+use crate::database::{
+    insert_db_date, insert_shift, lock_booking, query_current_shifts, query_location_tasks,
+    query_locations, Location, LocationTask, Shift, Task,
+};
+use crate::locale::Locale;
 use crate::time::Date;
+use crate::DISABLED_MESSAGE;
 
 // Fake values for explaining options in selects
 pub(crate) const FAKE_DAY_VALUE: u32 = 100;
@@ -13,22 +24,22 @@ pub(crate) const FAKE_TASKS_VALUE: i16 = 100;
 #[derive(Serialize)]
 pub(crate) struct InputTypeNumberErrors {
     // Missing value
-    missing: &'static str,
+    missing: String,
     // Invalid value
-    invalid: &'static str,
+    invalid: String,
     // Underflow
-    underflow: &'static str,
+    underflow: String,
     // Overflow
-    overflow: &'static str,
+    overflow: String,
 }
 
 impl InputTypeNumberErrors {
-    pub(crate) fn text() -> Self {
+    pub(crate) fn text(locale: Locale) -> Self {
         Self {
-            missing: "Inserire un numero di tessera senza spazi iniziali e finali.",
-            invalid: "Inserire un numero di tessera come nell'esempio.",
-            underflow: "Il numero di tessera deve essere positivo.",
-            overflow: "Il numero di tessera deve essere inferiore o uguale a 100.",
+            missing: locale.text("card-id-missing"),
+            invalid: locale.text("card-id-invalid"),
+            underflow: locale.text("card-id-underflow"),
+            overflow: locale.text("card-id-overflow"),
         }
     }
 }
@@ -37,61 +48,147 @@ impl InputTypeNumberErrors {
 #[derive(Serialize)]
 pub(crate) struct Button {
     // Button text
-    text: &'static str,
+    text: String,
 }
 
 impl Button {
-    pub(crate) fn authentication_button() -> Self {
-        Self { text: "Accedi" }
+    pub(crate) fn authentication_button(locale: Locale) -> Self {
+        Self {
+            text: locale.text("button-login"),
+        }
     }
-    pub(crate) fn shifts_buttons() -> Self {
+    pub(crate) fn shifts_buttons(locale: Locale) -> Self {
         Self {
-            text: "Salva turni",
+            text: locale.text("button-save-shifts"),
         }
     }
 
-    pub(crate) fn add_shift() -> Self {
+    pub(crate) fn add_shift(locale: Locale) -> Self {
         Self {
-            text: "Aggiungi turno",
+            text: locale.text("button-add-shift"),
         }
     }
 }
 
-// Tasks
-#[derive(Serialize)]
-pub(crate) struct Tasks {
-    pub(crate) task_value: usize,
-    pub(crate) task_name: &'static str,
-    pub(crate) task_hours: &'static str,
-}
+// Tasks of an organisation
+pub(crate) struct Tasks(Vec<Task>);
 
 impl Tasks {
-    const TASKS: &'static [Self] = &[
-        Tasks::new(0, "Aiuto Cucina", "10:00-14:00"),
-        Tasks::new(1, "Accoglienza", "11:30-13:30"),
-        Tasks::new(2, "Servizio tavoli", "11:00-14:00"),
-        Tasks::new(3, "Pomeriggio", "14:00-16:00"),
-        Tasks::new(4, "Accoglienza notturna", "19:00-21:00"),
-    ];
-
-    const fn new(task_value: usize, task_name: &'static str, task_hours: &'static str) -> Self {
-        Self {
-            task_value,
-            task_name,
-            task_hours,
-        }
+    pub(crate) fn new(tasks: Vec<Task>) -> Self {
+        Self(tasks)
+    }
+
+    pub(crate) fn render(&self) -> &[Task] {
+        &self.0
+    }
+
+    fn find(&self, id: i16) -> Option<&Task> {
+        self.0.iter().find(|task| task.task_value == id)
+    }
+
+    pub(crate) fn contains(&self, id: i16) -> bool {
+        self.find(id).is_some()
+    }
+
+    pub(crate) fn task_from_id(&self, id: i16) -> String {
+        self.find(id)
+            .map(|task| task.task_name.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn hours_from_id(&self, id: i16) -> String {
+        self.find(id)
+            .map(|task| task.task_hours.clone())
+            .unwrap_or_default()
+    }
+
+    // Duration of a task in minutes, computed from its hours
+    pub(crate) fn minutes_from_id(&self, id: i16) -> u32 {
+        let minutes = |time: &str| {
+            time.split_once(':')
+                .and_then(|(hours, minutes)| {
+                    Some(hours.parse::<u32>().ok()? * 60 + minutes.parse::<u32>().ok()?)
+                })
+                .unwrap_or_default()
+        };
+        self.find(id)
+            .and_then(|task| task.task_hours.split_once('-'))
+            .map_or(0, |(start, end)| {
+                minutes(end).saturating_sub(minutes(start))
+            })
+    }
+}
+
+// Locations of an organisation, together with the tasks they offer
+pub(crate) struct Locations {
+    // Locations
+    locations: Vec<Location>,
+    // Tasks offered by each location
+    offered: Vec<LocationTask>,
+}
+
+impl Locations {
+    // Retrieve the locations of an organisation
+    pub(crate) async fn load(pool: &PgPool, organisation_id: i32) -> Result<Self> {
+        Ok(Self {
+            locations: query_locations(pool, organisation_id).await?,
+            offered: query_location_tasks(pool, organisation_id).await?,
+        })
+    }
+
+    pub(crate) fn render(&self) -> &[Location] {
+        &self.locations
+    }
+
+    fn find(&self, id: i16) -> Option<&Location> {
+        self.locations.iter().find(|location| location.id == id)
+    }
+
+    pub(crate) fn contains(&self, id: i16) -> bool {
+        self.find(id).is_some()
+    }
+
+    // First location, chosen when none is specified
+    pub(crate) fn first(&self) -> Option<i16> {
+        self.locations.first().map(|location| location.id)
     }
 
-    pub(crate) const fn render() -> &'static [Self] {
-        Self::TASKS
+    fn offered(&self, location_id: i16, task: i16) -> Option<&LocationTask> {
+        self.offered
+            .iter()
+            .find(|offered| offered.location_id == location_id && offered.task_id == task)
+    }
+
+    // Check whether a location offers a task
+    pub(crate) fn offers(&self, location_id: i16, task: i16) -> bool {
+        self.offered(location_id, task).is_some()
+    }
+
+    // Maximum number of volunteers of a task in a day, none when unlimited
+    pub(crate) fn capacity(&self, location_id: i16, task: i16) -> Option<i16> {
+        self.offered(location_id, task)
+            .and_then(|offered| offered.capacity)
+    }
+
+    // Tasks offered by a location
+    pub(crate) fn tasks<'a>(&self, location_id: i16, tasks: &'a Tasks) -> Vec<&'a Task> {
+        tasks
+            .render()
+            .iter()
+            .filter(|task| self.offers(location_id, task.task_value))
+            .collect()
     }
 
-    pub(crate) fn task_from_id(id: usize) -> &'static str {
-        Self::TASKS[id].task_name
+    pub(crate) fn name_from_id(&self, id: i16) -> String {
+        self.find(id)
+            .map(|location| location.name.clone())
+            .unwrap_or_default()
     }
 
-    pub(crate) fn hours_from_id(id: usize) -> &'static str {
-        Self::TASKS[id].task_hours
+    pub(crate) fn address_from_id(&self, id: i16) -> String {
+        self.find(id)
+            .map(|location| location.address.clone())
+            .unwrap_or_default()
     }
 }
 
@@ -105,10 +202,10 @@ pub(crate) struct Day {
 }
 
 impl Day {
-    fn new(date: Date) -> Self {
+    fn new(locale: Locale, date: Date) -> Self {
         Self {
             day_value: date.day_as_number(),
-            day_text: format!("{} {}", date.weekday(), date.month_date()),
+            day_text: format!("{} {}", date.weekday(locale), date.month_date(locale)),
         }
     }
 }
@@ -135,20 +232,20 @@ pub(crate) struct WeekData {
 }
 
 impl WeekData {
-    pub(crate) fn week_info(date: &Date) -> Self {
+    pub(crate) fn week_info(locale: Locale, date: &Date) -> Self {
         Self {
-            week_bounds: Self::week_bounds(date),
-            week_days: Self::days(date),
+            week_bounds: Self::week_bounds(locale, date),
+            week_days: Self::days(locale, date),
         }
     }
 
-    pub(crate) fn week_bounds(date: &Date) -> String {
-        let (monday, saturday) = date.week_bounds();
+    pub(crate) fn week_bounds(locale: Locale, date: &Date) -> String {
+        let (monday, saturday) = date.week_bounds(locale);
         format!("{monday} - {saturday}")
     }
 
-    pub(crate) fn days(date: &Date) -> Vec<Day> {
-        iterate_over_days(date, Day::new)
+    pub(crate) fn days(locale: Locale, date: &Date) -> Vec<Day> {
+        iterate_over_days(date, |date| Day::new(locale, date))
     }
 }
 
@@ -162,9 +259,13 @@ pub(crate) struct SelectDay {
 }
 
 impl SelectDay {
-    pub(crate) fn selected_days(date: &Date, current_day_as_number: u32) -> Vec<Self> {
+    pub(crate) fn selected_days(
+        locale: Locale,
+        date: &Date,
+        current_day_as_number: u32,
+    ) -> Vec<Self> {
         iterate_over_days(date, |date| {
-            let day = Day::new(date);
+            let day = Day::new(locale, date);
             Self {
                 is_selected: (day.day_value == current_day_as_number).then_some("selected"),
                 day,
@@ -172,10 +273,10 @@ impl SelectDay {
         })
     }
 
-    pub(crate) fn days(date: &Date) -> Vec<Self> {
+    pub(crate) fn days(locale: Locale, date: &Date) -> Vec<Self> {
         iterate_over_days(date, |date| Self {
             is_selected: None,
-            day: Day::new(date),
+            day: Day::new(locale, date),
         })
     }
 }
@@ -184,23 +285,141 @@ impl SelectDay {
 #[derive(Serialize)]
 pub(crate) struct ShiftLabels {
     // Week name label
-    week_name: &'static str,
+    week_name: String,
     // Week date label
-    week_date_label: &'static str,
+    week_date_label: String,
     // Task label
-    task_label: &'static str,
+    task_label: String,
+    // Location label
+    location_label: String,
 }
 
 impl ShiftLabels {
-    pub(crate) fn render() -> Self {
+    pub(crate) fn render(locale: Locale) -> Self {
         Self {
-            week_name: "Scegli settimana",
-            week_date_label: "Scegli giorno",
-            task_label: "Scegli mansione",
+            week_name: locale.text("label-choose-week"),
+            week_date_label: locale.text("label-choose-day"),
+            task_label: locale.text("label-choose-task"),
+            location_label: locale.text("label-choose-location"),
         }
     }
 }
 
+// Reasons why a shift cannot be booked
+pub(crate) enum ShiftRejection {
+    // The task does not exist
+    InvalidTask,
+    // The location does not exist or it does not offer the task
+    InvalidLocation,
+    // The date is not contained in the current or in the next week, or it
+    // is already passed
+    InvalidDate,
+    // The volunteer has already booked the same shift
+    AlreadyBooked,
+    // The location has no more places for the task
+    Full,
+    // The volunteer does not exist
+    UnknownVolunteer,
+    // The volunteer is disabled and cannot book shifts
+    Disabled,
+}
+
+impl ShiftRejection {
+    pub(crate) fn message(&self, locale: Locale) -> String {
+        locale.text(match self {
+            Self::InvalidTask => "shifts-manager-invalid-task",
+            Self::InvalidLocation => "shifts-manager-invalid-location",
+            Self::InvalidDate => "shifts-manager-invalid-date",
+            Self::AlreadyBooked => "shifts-manager-already-booked",
+            Self::Full => "shifts-manager-full",
+            Self::UnknownVolunteer => "shifts-manager-unknown-volunteer",
+            Self::Disabled => DISABLED_MESSAGE,
+        })
+    }
+}
+
+// Check whether a volunteer has already booked the same task in the same
+// day, at any location
+fn is_booked<'a>(mut shifts: impl Iterator<Item = &'a Shift>, shift: &Shift) -> bool {
+    shifts.any(|saved| saved.date == shift.date && saved.task == shift.task)
+}
+
+// Check whether a shift can be booked: the task must exist and be offered by
+// the location, the date must go from the current date until Saturday of
+// next week, Sundays excluded, and the shift must not be already saved
+pub(crate) fn check_shift(
+    shift: &Shift,
+    tasks: &Tasks,
+    locations: &Locations,
+    current_date: &Date,
+    already_saved_shifts: &HashSet<Shift>,
+) -> Result<(), ShiftRejection> {
+    if !tasks.contains(shift.task) {
+        return Err(ShiftRejection::InvalidTask);
+    }
+
+    if !locations.offers(shift.location_id, shift.task) {
+        return Err(ShiftRejection::InvalidLocation);
+    }
+
+    let first_day = current_date.naive_date();
+    let last_day = insert_db_date(current_date.next_week().day_date(5));
+    let in_window =
+        last_day.is_some_and(|last_day| shift.date >= first_day && shift.date <= last_day);
+    if !in_window || shift.date.weekday() == Weekday::Sun {
+        return Err(ShiftRejection::InvalidDate);
+    }
+
+    if is_booked(already_saved_shifts.iter(), shift) {
+        return Err(ShiftRejection::AlreadyBooked);
+    }
+
+    Ok(())
+}
+
+// Book shifts inside a transaction, applying the same checks of the shifts
+// manager page to each of them. The volunteers and the tasks of the locations
+// are locked first, so that concurrent bookings cannot book the same shift
+// twice or exceed the capacity of a location. Returns, in the same order of
+// the shifts, the identifier of each booked shift or the reason why it has
+// been rejected.
+pub(crate) async fn book_shifts(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    shifts: &[Shift],
+    tasks: &Tasks,
+    locations: &Locations,
+    current_date: &Date,
+) -> Result<Vec<Result<i32, ShiftRejection>>> {
+    let volunteers = lock_booking(&mut *conn, organisation_id, shifts).await?;
+    let mut results = Vec::with_capacity(shifts.len());
+    for shift in shifts {
+        let result = match volunteers.get(&shift.card_id) {
+            None => Err(ShiftRejection::UnknownVolunteer),
+            Some(true) => Err(ShiftRejection::Disabled),
+            Some(false) => {
+                // Shifts booked before inside the same transaction are
+                // retrieved as well
+                let saved_shifts = query_current_shifts(
+                    &mut *conn,
+                    organisation_id,
+                    shift.card_id,
+                    current_date.naive_date(),
+                )
+                .await?;
+                match check_shift(shift, tasks, locations, current_date, &saved_shifts) {
+                    Ok(()) => insert_shift(&mut *conn, organisation_id, shift)
+                        .await?
+                        .ok_or(ShiftRejection::Full),
+                    Err(rejection) => Err(rejection),
+                }
+            }
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
 #[derive(FromForm)]
 pub(crate) struct ShiftsData {
     pub(crate) card_id: i16,
@@ -210,17 +429,21 @@ pub(crate) struct ShiftsData {
     pub(crate) days: Vec<u32>,
     #[field(name = "tasks")]
     pub(crate) tasks: Vec<i16>,
+    #[field(name = "locations")]
+    pub(crate) locations: Vec<i16>,
 }
 
 impl ShiftsData {
-    // Create all shifts to be inserted into the database
-    pub(crate) fn create_shifts(&self, already_saved_shifts: HashSet<Shift>) -> HashSet<Shift> {
-        let mut shifts = HashSet::new();
-        for (week, (day, task)) in self
-            .weeks
-            .iter()
-            .zip(self.days.chunks(2).zip(self.tasks.iter()))
-        {
+    // Create all shifts to be inserted into the database, in the same order
+    // of the form. They are checked when they are booked, so that every
+    // rejected shift can be reported to the volunteer.
+    pub(crate) fn create_shifts(&self, current_date: &Date) -> Vec<Shift> {
+        let mut shifts = Vec::new();
+        for (week, (day, (task, location_id))) in self.weeks.iter().zip(
+            self.days
+                .chunks(2)
+                .zip(self.tasks.iter().zip(self.locations.iter())),
+        ) {
             // Skip fake value used to help a volunteer in discriminate
             // among already compiled shifts and new ones. 100 is
             // a symbolic value.
@@ -231,7 +454,6 @@ impl ShiftsData {
             if day == FAKE_DAY_VALUE {
                 continue;
             }
-            let current_date = Date::current();
             let date = if *week {
                 current_date.day_date(day)
             } else {
@@ -243,13 +465,12 @@ impl ShiftsData {
                     date,
                     task: *task,
                     card_id: self.card_id,
+                    location_id: *location_id,
                 }
             } else {
                 continue;
             };
-            if !already_saved_shifts.contains(&shift) {
-                shifts.insert(shift);
-            }
+            shifts.push(shift);
         }
         shifts
     }
@@ -1,92 +1,426 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use anyhow::{anyhow, Result};
 
 use serde::{Deserialize, Serialize};
 
 use sqlx::postgres::PgQueryResult;
-use sqlx::types::chrono::NaiveDate;
-use sqlx::{Executor, FromRow, PgPool};
-
-use crate::time::Date;
+use sqlx::types::chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Executor, FromRow, PgConnection, PgPool, Postgres};
 
 const INSERT_VOLUNTEERS: &str =
-    "INSERT INTO volunteers(card_id, surname, name, phone_number, disabled)
-     VALUES ($1, $2, $3, $4, $5)";
+    "INSERT INTO volunteers(organisation_id, card_id, surname, name, phone_number, disabled)
+     VALUES ($1, $2, $3, $4, $5, $6)";
 const UPDATE_VOLUNTEER: &str = "UPDATE volunteers
-     SET surname = $2,
-         name = $3,
-         phone_number = $4,
-         disabled = $5
-     WHERE card_id = $1";
-const INSERT_SHIFTS: &str = "INSERT INTO shifts(date, task, card_id)
-     VALUES ($1, $2, $3)";
-const GET_ALL_VOLUNTEERS: &str =
-    "SELECT card_id, surname, name, phone_number, disabled FROM volunteers ORDER BY card_id ASC";
-const GET_ALL_SHIFTS: &str =
-    "SELECT date, task, card_id FROM shifts WHERE date >= $1 ORDER BY date ASC";
-const GET_VOLUNTEER_SHIFTS: &str =
-    "SELECT id, date, task, card_id FROM shifts WHERE card_id = $1 AND date >= $2 ORDER BY date ASC";
-const GET_VOLUNTEER_CURRENT_SHIFTS: &str =
-    "SELECT date, task, card_id FROM shifts WHERE card_id = $1 AND date >= $2 ORDER BY date ASC";
-const GET_VOLUNTEER_NAME: &str = "SELECT name FROM volunteers WHERE card_id = $1";
-const GET_VOLUNTEER_NAME_SURNAME: &str = "SELECT name, surname FROM volunteers WHERE card_id = $1";
-const GET_VOLUNTEERS_NAMES: &str =
-    "SELECT name, surname FROM volunteers JOIN shifts ON volunteers.card_id = shifts.card_id
-     WHERE date = $1 AND task = $2";
-const CHECK_VOLUNTEER_FROM_CARD_ID: &str = "SELECT COUNT(*) FROM volunteers WHERE card_id = $1";
-const CHECK_VOLUNTEER_FROM_SURNAME: &str = "SELECT COUNT(*) FROM volunteers WHERE surname = $1";
-const CHECK_VOLUNTEER_CARD_ID_TO_SURNAME: &str =
-    "SELECT COUNT(*) FROM volunteers WHERE card_id = $1 AND surname = $2";
-const CHECK_VOLUNTEER_IS_DISABLED: &str =
-    "SELECT COUNT(*) FROM volunteers WHERE card_id = $1 AND disabled = TRUE";
-const DELETE_SHIFT: &str = "DELETE FROM shifts WHERE id = $1";
-const DELETE_OLD_SHIFTS: &str = "DELETE FROM shifts WHERE date < CURRENT_DATE - interval '3 weeks'";
-
-// FIXME: Leave this hack until a new sqlx version is released
-#[inline(always)]
-fn italian_day(day: &str) -> &'static str {
-    match day {
-        "Monday" => "Lunedì",
-        "Tuesday" => "Martedì",
-        "Wednesday" => "Mercoledì",
-        "Thursday" => "Giovedì",
-        "Friday" => "Venerdì",
-        "Saturday" => "Sabato",
-        "Sunday" => "Domenica",
-        _ => unreachable!(),
-    }
-}
+     SET surname = $3,
+         name = $4,
+         phone_number = $5,
+         disabled = $6
+     WHERE organisation_id = $1 AND card_id = $2";
+const GET_ALL_VOLUNTEERS: &str = "SELECT card_id, surname, name, phone_number, disabled
+     FROM volunteers WHERE organisation_id = $1 ORDER BY card_id ASC";
+const COUNT_VOLUNTEERS: &str = "SELECT COUNT(*) FROM volunteers WHERE organisation_id = $1";
+const GET_VOLUNTEERS_PAGE: &str = "SELECT card_id, surname, name, phone_number, disabled
+     FROM volunteers WHERE organisation_id = $1 ORDER BY card_id ASC
+     LIMIT $2 OFFSET $3";
+// Volunteers containing the searched text, with the chosen state and with or
+// without shifts inside a period
+const COUNT_SEARCHED_VOLUNTEERS: &str = "SELECT COUNT(*) FROM volunteers
+     WHERE organisation_id = $1
+         AND ($2 = '' OR card_id::text = $2 OR surname ILIKE $3 OR name ILIKE $3
+             OR surname || ' ' || name ILIKE $3 OR name || ' ' || surname ILIKE $3
+             OR REPLACE(phone_number, ' ', '') ILIKE REPLACE($3, ' ', ''))
+         AND ($4::boolean IS NULL OR disabled = $4)
+         AND ($5::boolean IS NULL OR EXISTS (SELECT 1 FROM shifts
+             WHERE shifts.organisation_id = volunteers.organisation_id
+                 AND shifts.card_id = volunteers.card_id AND date >= $6 AND date <= $7) = $5)";
+const SEARCH_VOLUNTEERS: &str = "SELECT card_id, surname, name, phone_number, disabled
+     FROM volunteers
+     WHERE organisation_id = $1
+         AND ($2 = '' OR card_id::text = $2 OR surname ILIKE $3 OR name ILIKE $3
+             OR surname || ' ' || name ILIKE $3 OR name || ' ' || surname ILIKE $3
+             OR REPLACE(phone_number, ' ', '') ILIKE REPLACE($3, ' ', ''))
+         AND ($4::boolean IS NULL OR disabled = $4)
+         AND ($5::boolean IS NULL OR EXISTS (SELECT 1 FROM shifts
+             WHERE shifts.organisation_id = volunteers.organisation_id
+                 AND shifts.card_id = volunteers.card_id AND date >= $6 AND date <= $7) = $5)
+     ORDER BY CASE $8 WHEN 'surname' THEN surname WHEN 'name' THEN name END ASC,
+         CASE $8 WHEN 'surname' THEN name WHEN 'name' THEN surname END ASC,
+         card_id ASC
+     LIMIT $9 OFFSET $10";
+const GET_EXPORTED_SHIFTS: &str = "SELECT date, task, shifts.card_id, location_id, surname, name
+     FROM shifts JOIN volunteers ON shifts.organisation_id = volunteers.organisation_id
+         AND shifts.card_id = volunteers.card_id
+     WHERE shifts.organisation_id = $1
+         AND ($2::date IS NULL OR date >= $2) AND ($3::date IS NULL OR date <= $3)
+         AND ($4::smallint IS NULL OR shifts.card_id = $4)
+     ORDER BY date ASC, location_id ASC, task ASC, surname ASC, name ASC";
+const COUNT_EXPORTED_SHIFTS: &str = "SELECT COUNT(*) FROM shifts
+     WHERE organisation_id = $1
+         AND ($2::date IS NULL OR date >= $2) AND ($3::date IS NULL OR date <= $3)
+         AND ($4::smallint IS NULL OR card_id = $4)";
+const GET_EXPORTED_SHIFTS_PAGE: &str =
+    "SELECT date, task, shifts.card_id, location_id, surname, name
+     FROM shifts JOIN volunteers ON shifts.organisation_id = volunteers.organisation_id
+         AND shifts.card_id = volunteers.card_id
+     WHERE shifts.organisation_id = $1
+         AND ($2::date IS NULL OR date >= $2) AND ($3::date IS NULL OR date <= $3)
+         AND ($4::smallint IS NULL OR shifts.card_id = $4)
+     ORDER BY date ASC, location_id ASC, task ASC, surname ASC, name ASC, shifts.card_id ASC
+     LIMIT $5 OFFSET $6";
+const GET_PLANNED_SHIFTS: &str = "SELECT id, date, task, shifts.card_id, location_id, surname, name
+     FROM shifts JOIN volunteers ON shifts.organisation_id = volunteers.organisation_id
+         AND shifts.card_id = volunteers.card_id
+     WHERE shifts.organisation_id = $1 AND date >= $2 AND date <= $3 AND location_id = $4
+     ORDER BY surname ASC, name ASC";
+const GET_EVERY_SHIFT: &str = "SELECT date, task, card_id, location_id FROM shifts
+     WHERE organisation_id = $1 ORDER BY date ASC, location_id ASC, task ASC, card_id ASC";
+const GET_VOLUNTEER_SHIFTS: &str = "SELECT id, date, task, card_id, location_id FROM shifts
+     WHERE organisation_id = $1 AND card_id = $2 AND date >= $3 ORDER BY date ASC";
+const GET_VOLUNTEER_CURRENT_SHIFTS: &str = "SELECT date, task, card_id, location_id FROM shifts
+     WHERE organisation_id = $1 AND card_id = $2 AND date >= $3 ORDER BY date ASC";
+const GET_VOLUNTEER_SERVED_SHIFTS: &str = "SELECT date, task, card_id, location_id FROM shifts
+     WHERE organisation_id = $1 AND card_id = $2 AND date >= $3 AND date <= $4
+     ORDER BY date ASC, task ASC";
+const GET_VOLUNTEER: &str = "SELECT card_id, surname, name, disabled FROM volunteers
+     WHERE organisation_id = $1 AND card_id = $2";
+const GET_VOLUNTEER_NAME: &str =
+    "SELECT name FROM volunteers WHERE organisation_id = $1 AND card_id = $2";
+const GET_VOLUNTEER_NAME_SURNAME: &str =
+    "SELECT name, surname FROM volunteers WHERE organisation_id = $1 AND card_id = $2";
+const GET_VOLUNTEERS_NAMES: &str = "SELECT name, surname
+     FROM volunteers JOIN shifts ON volunteers.organisation_id = shifts.organisation_id
+         AND volunteers.card_id = shifts.card_id
+     WHERE shifts.organisation_id = $1 AND date = $2 AND location_id = $3 AND task = $4";
+const CHECK_VOLUNTEER_FROM_CARD_ID: &str =
+    "SELECT COUNT(*) FROM volunteers WHERE organisation_id = $1 AND card_id = $2";
+const CHECK_VOLUNTEER_FROM_SURNAME: &str =
+    "SELECT COUNT(*) FROM volunteers WHERE organisation_id = $1 AND surname = $2";
+const CHECK_VOLUNTEER_CARD_ID_TO_SURNAME: &str = "SELECT COUNT(*) FROM volunteers
+     WHERE organisation_id = $1 AND card_id = $2 AND surname = $3";
+const CHECK_VOLUNTEER_IS_DISABLED: &str = "SELECT COUNT(*) FROM volunteers
+     WHERE organisation_id = $1 AND card_id = $2 AND disabled = TRUE";
+const UPSERT_VOLUNTEER: &str =
+    "INSERT INTO volunteers(organisation_id, card_id, surname, name, phone_number, disabled)
+     VALUES ($1, $2, $3, $4, $5, $6)
+     ON CONFLICT (organisation_id, card_id) DO UPDATE
+     SET surname = $3, name = $4, phone_number = $5, disabled = $6
+     RETURNING (xmax = 0)";
+const INSERT_MISSING_SHIFT: &str =
+    "INSERT INTO shifts(organisation_id, date, task, card_id, location_id)
+     VALUES ($1, $2, $3, $4, $5)
+     ON CONFLICT (organisation_id, date, task, card_id) DO NOTHING";
+const DELETE_ALL_SHIFTS: &str = "DELETE FROM shifts WHERE organisation_id = $1";
+const DELETE_OTHER_VOLUNTEERS: [&str; 9] = [
+    "DELETE FROM sessions WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM volunteer_preferences WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM volunteer_change_requests WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM volunteer_notes WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM volunteer_overrides WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM invitations WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM login_codes WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM shifts WHERE organisation_id = $1 AND card_id <> ALL($2)",
+    "DELETE FROM volunteers WHERE organisation_id = $1 AND card_id <> ALL($2)",
+];
+const UPSERT_TASK: &str = "INSERT INTO tasks(organisation_id, id, name, hours)
+     VALUES ($1, $2, $3, $4)
+     ON CONFLICT (organisation_id, id) DO UPDATE SET name = $3, hours = $4";
+const UPSERT_LOCATION: &str = "INSERT INTO locations(organisation_id, id, name, address)
+     VALUES ($1, $2, $3, $4)
+     ON CONFLICT (organisation_id, id) DO UPDATE SET name = $3, address = $4";
+const UPSERT_LOCATION_TASK: &str =
+    "INSERT INTO location_tasks(organisation_id, location_id, task_id, capacity)
+     VALUES ($1, $2, $3, $4)
+     ON CONFLICT (organisation_id, location_id, task_id) DO UPDATE SET capacity = $4";
+const DELETE_LOCATION_TASKS: &str = "DELETE FROM location_tasks WHERE organisation_id = $1";
+const DELETE_OTHER_TASKS: &str = "DELETE FROM tasks WHERE organisation_id = $1 AND id <> ALL($2)";
+const DELETE_OTHER_LOCATIONS: &str =
+    "DELETE FROM locations WHERE organisation_id = $1 AND id <> ALL($2)";
+// The shift is not inserted when its location has no more places for the task
+const INSERT_SHIFT: &str = "INSERT INTO shifts(organisation_id, date, task, card_id, location_id)
+     SELECT $1, $2, $3, $4, $5
+     WHERE (SELECT COUNT(*) FROM shifts
+         WHERE organisation_id = $1 AND date = $2 AND task = $3 AND location_id = $5)
+         < COALESCE((SELECT capacity FROM location_tasks
+             WHERE organisation_id = $1 AND location_id = $5 AND task_id = $3), 32767)
+     RETURNING id";
+const LOCK_VOLUNTEERS: &str = "SELECT card_id, disabled FROM volunteers
+     WHERE organisation_id = $1 AND card_id = ANY($2) ORDER BY card_id ASC FOR UPDATE";
+const LOCK_LOCATION_TASK: &str = "SELECT capacity FROM location_tasks
+     WHERE organisation_id = $1 AND location_id = $2 AND task_id = $3 FOR UPDATE";
+const DELETE_CURRENT_SHIFT: &str = "DELETE FROM shifts
+     WHERE organisation_id = $1 AND id = $2 AND date >= $3
+     RETURNING date, task, card_id, location_id";
+const DELETE_SHIFT: &str = "DELETE FROM shifts
+     WHERE organisation_id = $1 AND id = $2 AND card_id = $3
+     RETURNING date, task, card_id, location_id";
+const DELETE_OLD_SHIFTS: &str = "DELETE FROM shifts WHERE organisation_id = $1 AND date < $2";
+const GET_ARCHIVED_SHIFTS: &str =
+    "SELECT COUNT(*), MIN(date) FROM shifts WHERE organisation_id = $1 AND date < $2";
+const GET_LAST_SHIFTS: &str = "SELECT volunteers.card_id, surname, name, MAX(date) AS last_shift
+     FROM volunteers LEFT JOIN shifts ON volunteers.organisation_id = shifts.organisation_id
+         AND volunteers.card_id = shifts.card_id AND date <= $2
+     WHERE volunteers.organisation_id = $1 AND NOT disabled
+     GROUP BY volunteers.card_id, surname, name
+     ORDER BY last_shift ASC NULLS FIRST, surname ASC, name ASC";
+const GET_TASKS: &str = "SELECT id AS task_value, name AS task_name, hours AS task_hours
+     FROM tasks WHERE organisation_id = $1 ORDER BY id ASC";
+const GET_LOCATIONS: &str =
+    "SELECT id, name, address FROM locations WHERE organisation_id = $1 ORDER BY id ASC";
+const GET_LOCATION_TASKS: &str = "SELECT location_id, task_id, capacity FROM location_tasks
+     WHERE organisation_id = $1 ORDER BY location_id ASC, task_id ASC";
+const GET_ORGANISATIONS: &str =
+    "SELECT id, slug, name, volunteers_url, password_hash, logo_path FROM organisations
+     ORDER BY id ASC";
+const UPDATE_DEFAULT_ORGANISATION: &str = "UPDATE organisations
+     SET name = $1, volunteers_url = $2, password_hash = $3
+     WHERE id = (SELECT MIN(id) FROM organisations)";
+const INSERT_API_TOKEN: &str =
+    "INSERT INTO api_tokens(organisation_id, name, token_hash, is_administration, created_at)
+     VALUES ($1, $2, $3, $4, $5)";
+const GET_API_TOKENS: &str =
+    "SELECT id, name, is_administration, created_at, last_used FROM api_tokens
+     WHERE organisation_id = $1 ORDER BY created_at DESC";
+const USE_API_TOKEN: &str = "UPDATE api_tokens SET last_used = $3
+     WHERE organisation_id = $1 AND token_hash = $2
+     RETURNING id, name, is_administration, created_at, last_used";
+const DELETE_API_TOKEN: &str = "DELETE FROM api_tokens WHERE organisation_id = $1 AND id = $2";
+const INSERT_WEBHOOK: &str = "INSERT INTO webhooks(organisation_id, url, secret, created_at)
+     VALUES ($1, $2, $3, $4)";
+const GET_WEBHOOKS: &str = "SELECT id, url, secret, created_at FROM webhooks
+     WHERE organisation_id = $1 ORDER BY created_at ASC";
+const DELETE_WEBHOOK: &str = "DELETE FROM webhooks WHERE organisation_id = $1 AND id = $2";
+const INSERT_WEBHOOK_DELIVERIES: &str = "INSERT INTO webhook_deliveries
+         (webhook_id, event, payload, status, attempts, created_at, next_attempt)
+     SELECT id, $2, $3, $4, 0, $5, $5 FROM webhooks WHERE organisation_id = $1";
+// Deliveries are sent in background for every organisation
+const GET_PENDING_DELIVERIES: &str =
+    "SELECT webhook_deliveries.id, url, secret, event, payload, attempts
+     FROM webhook_deliveries JOIN webhooks ON webhook_id = webhooks.id
+     WHERE status = $1 AND next_attempt <= $2
+     ORDER BY webhook_deliveries.id ASC LIMIT $3";
+const UPDATE_DELIVERY: &str = "UPDATE webhook_deliveries
+     SET status = $2, attempts = $3, response = $4, next_attempt = $5, last_attempt = $6
+     WHERE id = $1";
+const GET_DELIVERIES: &str = "SELECT webhook_deliveries.id, url, event, status, attempts,
+         response, webhook_deliveries.created_at, last_attempt
+     FROM webhook_deliveries JOIN webhooks ON webhook_id = webhooks.id
+     WHERE organisation_id = $1
+     ORDER BY webhook_deliveries.id DESC LIMIT $2";
+const RETRY_DELIVERY: &str = "UPDATE webhook_deliveries SET status = $3, next_attempt = $4
+     WHERE id = $2
+         AND webhook_id IN (SELECT id FROM webhooks WHERE organisation_id = $1)";
+const DELETE_OLD_DELIVERIES: &str =
+    "DELETE FROM webhook_deliveries WHERE created_at < $1 AND status <> $2";
+const GET_SETTING: &str = "SELECT value FROM settings WHERE organisation_id = $1 AND key = $2";
+const GET_ALL_SETTINGS: &str =
+    "SELECT key, value FROM settings WHERE organisation_id = $1 ORDER BY key ASC";
+const UPDATE_SETTING: &str = "INSERT INTO settings(organisation_id, key, value) VALUES ($1, $2, $3)
+     ON CONFLICT (organisation_id, key) DO UPDATE SET value = $3";
+const INSERT_FAILED_LOGIN: &str =
+    "INSERT INTO login_attempts(organisation_id, kind, identifier, attempts)
+     VALUES ($1, $2, $3, 1)
+     ON CONFLICT (organisation_id, kind, identifier)
+     DO UPDATE SET attempts = login_attempts.attempts + 1
+     RETURNING attempts";
+const LOCK_LOGIN: &str = "UPDATE login_attempts SET locked_until = $4
+     WHERE organisation_id = $1 AND kind = $2 AND identifier = $3";
+const GET_LOGIN_LOCK: &str = "SELECT locked_until FROM login_attempts
+     WHERE organisation_id = $1 AND kind = $2 AND identifier = $3 AND locked_until > $4";
+const GET_LOCKED_LOGINS: &str =
+    "SELECT kind, identifier, attempts, locked_until FROM login_attempts
+     WHERE organisation_id = $1 AND locked_until > $2 ORDER BY locked_until DESC";
+const DELETE_LOGIN_ATTEMPTS: &str = "DELETE FROM login_attempts
+     WHERE organisation_id = $1 AND kind = $2 AND identifier = $3";
+const INSERT_SESSION: &str = "INSERT INTO sessions
+         (organisation_id, token, card_id, is_administration, user_agent, created_at, last_seen)
+     VALUES ($1, $2, $3, $4, $5, $6, $6)";
+const REFRESH_SESSION: &str = "UPDATE sessions SET last_seen = $3
+     WHERE organisation_id = $1 AND token = $2 AND last_seen > $4 AND created_at > $5
+     RETURNING id, card_id, is_administration";
+const GET_ACTIVE_SESSIONS: &str =
+    "SELECT id, sessions.card_id, surname, name, is_administration, user_agent, created_at, last_seen
+     FROM sessions JOIN volunteers ON sessions.organisation_id = volunteers.organisation_id
+         AND sessions.card_id = volunteers.card_id
+     WHERE sessions.organisation_id = $1 AND last_seen > $2 AND created_at > $3
+     ORDER BY last_seen DESC";
+const DELETE_SESSION: &str = "DELETE FROM sessions WHERE organisation_id = $1 AND id = $2";
+const DELETE_SESSION_FROM_TOKEN: &str =
+    "DELETE FROM sessions WHERE organisation_id = $1 AND token = $2";
+const GET_VOLUNTEER_PHONE_NUMBER: &str =
+    "SELECT phone_number FROM volunteers WHERE organisation_id = $1 AND card_id = $2";
+const INSERT_LOGIN_CODE: &str =
+    "INSERT INTO login_codes(organisation_id, card_id, code_hash, expires_at, attempts)
+     VALUES ($1, $2, $3, $4, 0)
+     ON CONFLICT (organisation_id, card_id) DO UPDATE
+     SET code_hash = $3, expires_at = $4, attempts = 0";
+const GET_LOGIN_CODE: &str = "UPDATE login_codes SET attempts = attempts + 1
+     WHERE organisation_id = $1 AND card_id = $2 AND expires_at > $3 AND attempts < $4
+     RETURNING code_hash";
+const DELETE_LOGIN_CODE: &str =
+    "DELETE FROM login_codes WHERE organisation_id = $1 AND card_id = $2";
+// Expired sessions and invitations are deleted for every organisation
+const DELETE_EXPIRED_SESSIONS: &str =
+    "DELETE FROM sessions WHERE last_seen <= $1 OR created_at <= $2";
+const INSERT_INVITATION: &str = "INSERT INTO invitations
+         (organisation_id, token, card_id, single_use, created_at, expires_at)
+     VALUES ($1, $2, $3, $4, $5, $6)";
+const GET_INVITATIONS: &str =
+    "SELECT id, token, invitations.card_id, surname, name, single_use, created_at, expires_at
+     FROM invitations JOIN volunteers ON invitations.organisation_id = volunteers.organisation_id
+         AND invitations.card_id = volunteers.card_id
+     WHERE invitations.organisation_id = $1 AND expires_at > $2 ORDER BY created_at DESC";
+const GET_INVITATION_CARD_ID: &str = "SELECT card_id FROM invitations
+     WHERE organisation_id = $1 AND token = $2 AND expires_at > $3";
+const USE_INVITATION: &str = "WITH used AS (
+         DELETE FROM invitations
+         WHERE organisation_id = $1 AND token = $2 AND single_use AND expires_at > $3
+         RETURNING card_id
+     )
+     SELECT card_id FROM used
+     UNION ALL
+     SELECT card_id FROM invitations
+     WHERE organisation_id = $1 AND token = $2 AND NOT single_use AND expires_at > $3";
+const DELETE_INVITATION: &str = "DELETE FROM invitations WHERE organisation_id = $1 AND id = $2";
+const DELETE_EXPIRED_INVITATIONS: &str = "DELETE FROM invitations WHERE expires_at <= $1";
+const GET_VOLUNTEER_PROFILE: &str = "SELECT card_id, surname, name, phone_number, disabled
+     FROM volunteers WHERE organisation_id = $1 AND card_id = $2";
+const GET_VOLUNTEER_PAST_SHIFTS: &str = "SELECT id, date, task, card_id, location_id FROM shifts
+     WHERE organisation_id = $1 AND card_id = $2 AND date < $3
+     ORDER BY date DESC, task ASC LIMIT $4";
+const GET_VOLUNTEER_SERVED_TASKS: &str = "SELECT task, COUNT(*) AS shifts FROM shifts
+     WHERE organisation_id = $1 AND card_id = $2 AND date < $3
+     GROUP BY task ORDER BY shifts DESC, task ASC";
+const INSERT_VOLUNTEER_NOTE: &str = "INSERT INTO volunteer_notes
+         (organisation_id, card_id, author, text, created_at)
+     VALUES ($1, $2, $3, $4, $5)";
+const GET_VOLUNTEER_NOTES: &str = "SELECT id, author, text, created_at FROM volunteer_notes
+     WHERE organisation_id = $1 AND card_id = $2 ORDER BY created_at DESC";
+const DELETE_VOLUNTEER_NOTE: &str =
+    "DELETE FROM volunteer_notes WHERE organisation_id = $1 AND id = $2 AND card_id = $3";
+const GET_EVERY_VOLUNTEER_NOTE: &str =
+    "SELECT card_id, id, author, text, created_at FROM volunteer_notes
+     WHERE organisation_id = $1 ORDER BY card_id ASC, created_at ASC";
+const INSERT_MISSING_VOLUNTEER_NOTE: &str = "INSERT INTO volunteer_notes
+         (organisation_id, card_id, author, text, created_at)
+     SELECT $1, $2, $3, $4, $5
+     WHERE NOT EXISTS (SELECT 1 FROM volunteer_notes
+         WHERE organisation_id = $1 AND card_id = $2 AND author = $3 AND text = $4
+             AND created_at = $5)";
+const DELETE_ALL_VOLUNTEER_NOTES: &str = "DELETE FROM volunteer_notes WHERE organisation_id = $1";
+const GET_VOLUNTEER_OVERRIDES: &str = "SELECT card_id, surname, name, phone_number, disabled,
+         source_surname, source_name, source_phone_number, source_disabled
+     FROM volunteer_overrides WHERE organisation_id = $1";
+const GET_VOLUNTEER_OVERRIDE: &str = "SELECT card_id, surname, name, phone_number, disabled,
+         source_surname, source_name, source_phone_number, source_disabled
+     FROM volunteer_overrides WHERE organisation_id = $1 AND card_id = $2";
+// The values of the file are remembered the first time they are replaced, and
+// each value stops being replaced when it goes back to the one of the file
+const SET_VOLUNTEER_OVERRIDE: &str = "INSERT INTO volunteer_overrides
+         (organisation_id, card_id, surname, name, phone_number, disabled,
+          source_surname, source_name, source_phone_number, source_disabled)
+     SELECT organisation_id, card_id, NULLIF($3, surname), NULLIF($4, name),
+         NULLIF($5, phone_number), NULLIF($6, disabled), surname, name, phone_number, disabled
+     FROM volunteers
+     WHERE organisation_id = $1 AND card_id = $2
+     ON CONFLICT (organisation_id, card_id) DO UPDATE
+     SET surname = NULLIF($3, volunteer_overrides.source_surname),
+         name = NULLIF($4, volunteer_overrides.source_name),
+         phone_number = NULLIF($5, volunteer_overrides.source_phone_number),
+         disabled = NULLIF($6, volunteer_overrides.source_disabled)";
+const DELETE_EMPTY_OVERRIDE: &str = "DELETE FROM volunteer_overrides
+     WHERE organisation_id = $1 AND card_id = $2
+         AND surname IS NULL AND name IS NULL AND phone_number IS NULL AND disabled IS NULL";
+const UPDATE_OVERRIDE_SOURCE: &str = "UPDATE volunteer_overrides
+     SET source_surname = $3, source_name = $4, source_phone_number = $5, source_disabled = $6
+     WHERE organisation_id = $1 AND card_id = $2";
+const RESTORE_VOLUNTEER_SOURCE: &str = "UPDATE volunteers
+     SET surname = source_surname, name = source_name, phone_number = source_phone_number,
+         disabled = source_disabled
+     FROM volunteer_overrides
+     WHERE volunteers.organisation_id = $1 AND volunteers.card_id = $2
+         AND volunteer_overrides.organisation_id = volunteers.organisation_id
+         AND volunteer_overrides.card_id = volunteers.card_id";
+const DELETE_VOLUNTEER_OVERRIDE: &str =
+    "DELETE FROM volunteer_overrides WHERE organisation_id = $1 AND card_id = $2";
+const UPSERT_VOLUNTEER_OVERRIDE: &str = "INSERT INTO volunteer_overrides
+         (organisation_id, card_id, surname, name, phone_number, disabled,
+          source_surname, source_name, source_phone_number, source_disabled)
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+     ON CONFLICT (organisation_id, card_id) DO UPDATE
+     SET surname = $3, name = $4, phone_number = $5, disabled = $6, source_surname = $7,
+         source_name = $8, source_phone_number = $9, source_disabled = $10";
+const DELETE_ALL_VOLUNTEER_OVERRIDES: &str =
+    "DELETE FROM volunteer_overrides WHERE organisation_id = $1";
+const GET_VOLUNTEER_PREFERENCES: &str =
+    "SELECT email, notify_email, notify_sms, language FROM volunteer_preferences
+     WHERE organisation_id = $1 AND card_id = $2";
+const SET_VOLUNTEER_PREFERENCES: &str = "INSERT INTO volunteer_preferences
+         (organisation_id, card_id, email, notify_email, notify_sms, language)
+     VALUES ($1, $2, $3, $4, $5, $6)
+     ON CONFLICT (organisation_id, card_id) DO UPDATE
+     SET email = $3, notify_email = $4, notify_sms = $5, language = $6";
+const GET_EVERY_VOLUNTEER_PREFERENCES: &str =
+    "SELECT card_id, email, notify_email, notify_sms, language FROM volunteer_preferences
+     WHERE organisation_id = $1 ORDER BY card_id ASC";
+const DELETE_ALL_VOLUNTEER_PREFERENCES: &str =
+    "DELETE FROM volunteer_preferences WHERE organisation_id = $1";
+// A new request replaces the pending one of the same volunteer
+const SET_CHANGE_REQUEST: &str = "INSERT INTO volunteer_change_requests
+         (organisation_id, card_id, surname, name, phone_number, created_at)
+     VALUES ($1, $2, $3, $4, $5, $6)
+     ON CONFLICT (organisation_id, card_id) DO UPDATE
+     SET surname = $3, name = $4, phone_number = $5, created_at = $6";
+const GET_CHANGE_REQUESTS: &str = "SELECT requests.id, requests.card_id, requests.surname,
+         requests.name, requests.phone_number, requests.created_at,
+         volunteers.surname AS current_surname, volunteers.name AS current_name,
+         volunteers.phone_number AS current_phone_number
+     FROM volunteer_change_requests AS requests
+     JOIN volunteers ON volunteers.organisation_id = requests.organisation_id
+         AND volunteers.card_id = requests.card_id
+     WHERE requests.organisation_id = $1
+     ORDER BY requests.created_at ASC";
+const GET_CHANGE_REQUEST: &str = "SELECT requests.id, requests.card_id, requests.surname,
+         requests.name, requests.phone_number, requests.created_at,
+         volunteers.surname AS current_surname, volunteers.name AS current_name,
+         volunteers.phone_number AS current_phone_number
+     FROM volunteer_change_requests AS requests
+     JOIN volunteers ON volunteers.organisation_id = requests.organisation_id
+         AND volunteers.card_id = requests.card_id
+     WHERE requests.organisation_id = $1 AND requests.card_id = $2";
+const GET_REQUESTED_CHANGES: &str =
+    "SELECT card_id, surname, name, phone_number, created_at FROM volunteer_change_requests
+     WHERE organisation_id = $1 ORDER BY created_at ASC";
+const DELETE_ALL_CHANGE_REQUESTS: &str =
+    "DELETE FROM volunteer_change_requests WHERE organisation_id = $1";
+const DELETE_CHANGE_REQUEST: &str =
+    "DELETE FROM volunteer_change_requests WHERE organisation_id = $1 AND card_id = $2";
+const REJECT_CHANGE_REQUEST: &str =
+    "DELETE FROM volunteer_change_requests WHERE organisation_id = $1 AND id = $2";
+// The disabled state is not changed by volunteers requests
+const APPROVE_CHANGE_REQUEST: &str = "DELETE FROM volunteer_change_requests
+     USING volunteers
+     WHERE volunteer_change_requests.organisation_id = $1 AND volunteer_change_requests.id = $2
+         AND volunteers.organisation_id = volunteer_change_requests.organisation_id
+         AND volunteers.card_id = volunteer_change_requests.card_id
+     RETURNING volunteer_change_requests.card_id, volunteer_change_requests.surname,
+         volunteer_change_requests.name, volunteer_change_requests.phone_number,
+         volunteers.disabled";
 
 #[inline(always)]
 pub(crate) fn insert_db_date(date: (i32, u32, u32)) -> Option<NaiveDate> {
     NaiveDate::from_ymd_opt(date.0, date.1, date.2)
 }
 
-#[inline(always)]
-pub(crate) fn format_db_date(date: &NaiveDate) -> String {
-    format!(
-        "{} {}",
-        italian_day(date.format("%A").to_string().as_str()),
-        date.format("%d/%m/%Y"),
-    )
-}
-
 #[inline(always)]
 fn insert_db_date_error(date: (i32, u32, u32)) -> Result<NaiveDate> {
     insert_db_date(date).ok_or(anyhow!("Error creating database date"))
 }
 
-#[inline(always)]
-fn database_current_date() -> Result<NaiveDate> {
-    let date = Date::current();
-    insert_db_date_error((date.year(), date.month(), date.day()))
-}
-
 /// Volunteer definition
-#[derive(Debug, FromRow, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, FromRow, Serialize, Deserialize)]
 pub(crate) struct Volunteer {
-    /// Card identification (database primary key)
+    /// Card identification (database primary key together with the organisation)
     pub(crate) card_id: i16,
     /// Surname
     pub(crate) surname: String,
@@ -97,7 +431,7 @@ pub(crate) struct Volunteer {
 }
 
 /// Volunteer manager definition
-#[derive(Debug, FromRow, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, FromRow, Serialize, Deserialize)]
 pub(crate) struct VolunteerManager {
     /// Visible volunteer information
     #[sqlx(flatten)]
@@ -106,6 +440,20 @@ pub(crate) struct VolunteerManager {
     pub(crate) phone_number: String,
 }
 
+/// Volunteers search definition
+pub(crate) struct VolunteersSearch<'a> {
+    /// Text contained in card identification, surname, name or phone number
+    pub(crate) text: &'a str,
+    /// Check whether only disabled or enabled volunteers are searched
+    pub(crate) disabled: Option<bool>,
+    /// Check whether only volunteers with or without shifts are searched
+    pub(crate) has_shifts: Option<bool>,
+    /// First and last day of the period containing the shifts
+    pub(crate) period: (NaiveDate, NaiveDate),
+    /// Column used to sort volunteers, the card identification when unknown
+    pub(crate) order: &'static str,
+}
+
 /// Shift definition
 #[derive(PartialEq, Eq, Hash, Clone, FromRow, Serialize, Deserialize)]
 pub(crate) struct Shift {
@@ -115,6 +463,128 @@ pub(crate) struct Shift {
     pub(crate) task: i16,
     /// Card identification
     pub(crate) card_id: i16,
+    /// Location, missing in the archives saved before locations existed
+    #[serde(default = "default_location")]
+    pub(crate) location_id: i16,
+}
+
+// Location of the shifts saved before locations existed
+pub(crate) const DEFAULT_LOCATION: i16 = 1;
+
+fn default_location() -> i16 {
+    DEFAULT_LOCATION
+}
+
+/// Last shift served by an enabled volunteer
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct LastShift {
+    /// Card identification
+    pub(crate) card_id: i16,
+    /// Volunteer surname
+    pub(crate) surname: String,
+    /// Volunteer name
+    pub(crate) name: String,
+    /// Date of the last shift until today, none when no shift has been served
+    pub(crate) last_shift: Option<NaiveDate>,
+}
+
+/// Token used to access the API
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct ApiTokenInfo {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Name describing the token owner
+    pub(crate) name: String,
+    /// Check whether the token can read administration data
+    pub(crate) is_administration: bool,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+    /// Last use date and time
+    pub(crate) last_used: Option<DateTime<Utc>>,
+}
+
+/// Endpoint receiving events through HTTP requests
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct Webhook {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Address receiving the events
+    pub(crate) url: String,
+    /// Secret used to sign the events
+    pub(crate) secret: String,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+/// Event waiting to be delivered to a webhook
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct PendingDelivery {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Address receiving the event
+    pub(crate) url: String,
+    /// Secret used to sign the event
+    pub(crate) secret: String,
+    /// Event name
+    pub(crate) event: String,
+    /// JSON body sent to the webhook
+    pub(crate) payload: String,
+    /// Number of attempts already made
+    pub(crate) attempts: i32,
+}
+
+/// Delivery attempt outcome
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct DeliveryUpdate {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Delivery status
+    pub(crate) status: String,
+    /// Number of attempts made
+    pub(crate) attempts: i32,
+    /// Response status or error of the last attempt
+    pub(crate) response: Option<String>,
+    /// Date and time of the next attempt
+    pub(crate) next_attempt: DateTime<Utc>,
+}
+
+/// Delivery shown in the log
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct Delivery {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Address receiving the event
+    pub(crate) url: String,
+    /// Event name
+    pub(crate) event: String,
+    /// Delivery status
+    pub(crate) status: String,
+    /// Number of attempts made
+    pub(crate) attempts: i32,
+    /// Response status or error of the last attempt
+    pub(crate) response: Option<String>,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+    /// Date and time of the last attempt
+    pub(crate) last_attempt: Option<DateTime<Utc>>,
+}
+
+/// Volunteers changed by an update of the volunteers table
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct VolunteersChanges {
+    /// Card identifications of the new volunteers
+    pub(crate) added: Vec<i16>,
+    /// Card identifications of the volunteers whose data changed
+    pub(crate) updated: Vec<i16>,
+}
+
+/// Setting saved by administrators
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct Setting {
+    /// Setting name
+    pub(crate) key: String,
+    /// Setting value
+    pub(crate) value: String,
 }
 
 /// Shift query information
@@ -127,42 +597,382 @@ pub(crate) struct ShiftQuery {
     pub(crate) shift: Shift,
 }
 
-// Get shifts associated to a volunteer and retrieved through card identification
-pub(crate) async fn query_shifts(pool: &PgPool, card_id: i16) -> Result<Vec<ShiftQuery>> {
-    let date = database_current_date()?;
+/// Exported shift definition
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct ExportedShift {
+    /// Visible shift information
+    #[sqlx(flatten)]
+    pub(crate) shift: Shift,
+    /// Surname
+    pub(crate) surname: String,
+    /// Name
+    pub(crate) name: String,
+}
+
+/// Shift shown in the planning board
+#[derive(FromRow)]
+pub(crate) struct PlannedShift {
+    /// Shift together with its identifier
+    #[sqlx(flatten)]
+    pub(crate) shift: ShiftQuery,
+    /// Surname
+    pub(crate) surname: String,
+    /// Name
+    pub(crate) name: String,
+}
+
+/// Login attempts definition
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct LoginAttempt {
+    /// Kind of identifier (IP address, volunteer or referent card)
+    pub(crate) kind: String,
+    /// Identifier
+    pub(crate) identifier: String,
+    /// Number of consecutive failed attempts
+    pub(crate) attempts: i32,
+    /// Lockout end
+    pub(crate) locked_until: Option<DateTime<Utc>>,
+}
+
+/// Session definition
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct SessionData {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Card identification of the authenticated volunteer
+    pub(crate) card_id: i16,
+    /// Check whether the session belongs to a referent
+    pub(crate) is_administration: bool,
+}
+
+/// Active session information
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct ActiveSession {
+    /// Visible session information
+    #[sqlx(flatten)]
+    pub(crate) session: SessionData,
+    /// Surname
+    pub(crate) surname: String,
+    /// Name
+    pub(crate) name: String,
+    /// Browser used to authenticate
+    pub(crate) user_agent: String,
+    /// Authentication date and time
+    pub(crate) created_at: DateTime<Utc>,
+    /// Last request date and time
+    pub(crate) last_seen: DateTime<Utc>,
+}
+
+/// Invitation definition
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct Invitation {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Secret token contained in the invitation link
+    pub(crate) token: String,
+    /// Card identification of the invited volunteer
+    pub(crate) card_id: i16,
+    /// Surname
+    pub(crate) surname: String,
+    /// Name
+    pub(crate) name: String,
+    /// Check whether the invitation can be used only once
+    pub(crate) single_use: bool,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+    /// Expiration date and time
+    pub(crate) expires_at: DateTime<Utc>,
+}
+
+/// Task served by a volunteer
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct ServedTask {
+    /// Task identification
+    pub(crate) task: i16,
+    /// Number of served shifts
+    pub(crate) shifts: i64,
+}
+
+/// Private note written by a referent about a volunteer
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct VolunteerNote {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Surname and name of the referent who wrote the note
+    pub(crate) author: String,
+    /// Note text
+    pub(crate) text: String,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+/// Note about a volunteer together with the volunteer card identification
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct CardNote {
+    /// Card identification
+    pub(crate) card_id: i16,
+    /// Note content
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub(crate) note: VolunteerNote,
+}
+
+/// Volunteer values changed by referents
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct VolunteerOverride {
+    /// Card identification
+    pub(crate) card_id: i16,
+    /// Local surname, the one of the file is kept when missing
+    pub(crate) surname: Option<String>,
+    /// Local name, the one of the file is kept when missing
+    pub(crate) name: Option<String>,
+    /// Local phone number, the one of the file is kept when missing
+    pub(crate) phone_number: Option<String>,
+    /// Local disabled state, the one of the file is kept when missing
+    pub(crate) disabled: Option<bool>,
+    /// Surname contained in the volunteers file
+    pub(crate) source_surname: String,
+    /// Name contained in the volunteers file
+    pub(crate) source_name: String,
+    /// Phone number contained in the volunteers file
+    pub(crate) source_phone_number: String,
+    /// Disabled state contained in the volunteers file
+    pub(crate) source_disabled: bool,
+}
+
+/// Contact preferences chosen by a volunteer
+#[derive(Default, FromRow, Serialize, Deserialize)]
+pub(crate) struct VolunteerPreferences {
+    /// Email address
+    pub(crate) email: Option<String>,
+    /// Whether the volunteer wants to be contacted by email
+    pub(crate) notify_email: bool,
+    /// Whether the volunteer wants to be contacted by SMS
+    pub(crate) notify_sms: bool,
+    /// Language code of the messages sent to the volunteer
+    pub(crate) language: Option<String>,
+}
+
+/// Contact preferences together with the volunteer card identification
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct CardPreferences {
+    /// Card identification
+    pub(crate) card_id: i16,
+    /// Contact preferences
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub(crate) preferences: VolunteerPreferences,
+}
+
+/// Data change asked by a volunteer, without the saved values
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct RequestedChange {
+    /// Card identification
+    pub(crate) card_id: i16,
+    /// Requested surname
+    pub(crate) surname: String,
+    /// Requested name
+    pub(crate) name: String,
+    /// Requested phone number
+    pub(crate) phone_number: String,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+}
+
+/// Data change asked by a volunteer, waiting for a referent approval
+#[derive(FromRow, Serialize, Deserialize)]
+pub(crate) struct ChangeRequest {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Card identification
+    pub(crate) card_id: i16,
+    /// Requested surname
+    pub(crate) surname: String,
+    /// Requested name
+    pub(crate) name: String,
+    /// Requested phone number
+    pub(crate) phone_number: String,
+    /// Creation date and time
+    pub(crate) created_at: DateTime<Utc>,
+    /// Surname currently saved
+    pub(crate) current_surname: String,
+    /// Name currently saved
+    pub(crate) current_name: String,
+    /// Phone number currently saved
+    pub(crate) current_phone_number: String,
+}
+
+/// Organisation sharing the web app with the other ones
+#[derive(Clone, FromRow, Serialize, Deserialize)]
+pub(crate) struct Organisation {
+    /// Id to discriminate the rows (database primary key)
+    pub(crate) id: i32,
+    /// Name contained in subdomains and path prefixes
+    pub(crate) slug: String,
+    /// Name shown in pages and certificates
+    pub(crate) name: String,
+    /// Address of the volunteers csv file
+    pub(crate) volunteers_url: String,
+    /// Hash of the referents password
+    pub(crate) password_hash: String,
+    /// Logo shown in menus
+    pub(crate) logo_path: String,
+}
+
+/// Task which can be booked by volunteers
+#[derive(Clone, FromRow, Serialize, Deserialize)]
+pub(crate) struct Task {
+    /// Task identifier, unique inside an organisation
+    pub(crate) task_value: i16,
+    /// Task name
+    pub(crate) task_name: String,
+    /// Task hours, such as 10:00-14:00
+    pub(crate) task_hours: String,
+}
 
+/// Place where shifts are served
+#[derive(Clone, FromRow, Serialize, Deserialize)]
+pub(crate) struct Location {
+    /// Location identifier, unique inside an organisation
+    pub(crate) id: i16,
+    /// Location name
+    pub(crate) name: String,
+    /// Location address
+    pub(crate) address: String,
+}
+
+/// Task offered by a location
+#[derive(Clone, FromRow, Serialize, Deserialize)]
+pub(crate) struct LocationTask {
+    /// Location identifier
+    pub(crate) location_id: i16,
+    /// Task identifier
+    pub(crate) task_id: i16,
+    /// Maximum number of volunteers for each day, none when unlimited
+    pub(crate) capacity: Option<i16>,
+}
+
+// Get shifts associated to a volunteer and retrieved through card identification,
+// starting from the given date
+pub(crate) async fn query_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    today: NaiveDate,
+) -> Result<Vec<ShiftQuery>> {
     Ok(sqlx::query_as(GET_VOLUNTEER_SHIFTS)
+        .bind(organisation_id)
         .bind(card_id)
-        .bind(date)
+        .bind(today)
         .fetch_all(pool)
         .await?)
 }
 
-// Get all shifts
-pub(crate) async fn query_all_shifts(pool: &PgPool) -> Result<Vec<Shift>> {
-    let date = database_current_date()?;
-    Ok(sqlx::query_as(GET_ALL_SHIFTS)
-        .bind(date)
+// Get all shifts contained in a date range, past shifts included, together
+// with the names of their volunteers, optionally only for a volunteer
+pub(crate) async fn query_exported_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    card_id: Option<i16>,
+) -> Result<Vec<ExportedShift>> {
+    Ok(sqlx::query_as(GET_EXPORTED_SHIFTS)
+        .bind(organisation_id)
+        .bind(from)
+        .bind(to)
+        .bind(card_id)
         .fetch_all(pool)
         .await?)
 }
 
-// Get shifts associated to a volunteer (without id) and retrieved through card identification
-pub(crate) async fn query_current_shifts(pool: &PgPool, card_id: i16) -> Result<HashSet<Shift>> {
-    let date = database_current_date()?;
-    let shifts = sqlx::query_as(GET_VOLUNTEER_CURRENT_SHIFTS)
+// Count the shifts contained in a date range, optionally only for a volunteer
+pub(crate) async fn query_exported_shifts_count(
+    pool: &PgPool,
+    organisation_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    card_id: Option<i16>,
+) -> Result<i64> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i64);
+    let count: Temp = sqlx::query_as(COUNT_EXPORTED_SHIFTS)
+        .bind(organisation_id)
+        .bind(from)
+        .bind(to)
         .bind(card_id)
-        .bind(date)
+        .fetch_one(pool)
+        .await?;
+    Ok(count.0)
+}
+
+// Get a page of the shifts contained in a date range, together with the
+// names of their volunteers, optionally only for a volunteer
+pub(crate) async fn query_exported_shifts_page(
+    pool: &PgPool,
+    organisation_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    card_id: Option<i16>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<ExportedShift>> {
+    Ok(sqlx::query_as(GET_EXPORTED_SHIFTS_PAGE)
+        .bind(organisation_id)
+        .bind(from)
+        .bind(to)
+        .bind(card_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the shifts of a location contained in a date range, together with
+// their identifiers and volunteers names
+pub(crate) async fn query_planned_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+    location_id: i16,
+) -> Result<Vec<PlannedShift>> {
+    Ok(sqlx::query_as(GET_PLANNED_SHIFTS)
+        .bind(organisation_id)
+        .bind(from)
+        .bind(to)
+        .bind(location_id)
         .fetch_all(pool)
+        .await?)
+}
+
+// Get shifts associated to a volunteer (without id) and retrieved through card identification,
+// starting from the given date
+pub(crate) async fn query_current_shifts<'c>(
+    executor: impl Executor<'c, Database = Postgres>,
+    organisation_id: i32,
+    card_id: i16,
+    today: NaiveDate,
+) -> Result<HashSet<Shift>> {
+    let shifts = sqlx::query_as(GET_VOLUNTEER_CURRENT_SHIFTS)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(today)
+        .fetch_all(executor)
         .await?;
     Ok(HashSet::from_iter(shifts.iter().cloned()))
 }
 
 // Check whether a volunteer exists from card identification
-pub(crate) async fn query_check_card_id(pool: &PgPool, card_id: i16) -> Result<bool> {
+pub(crate) async fn query_check_card_id(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<bool> {
     #[derive(FromRow, Serialize, Deserialize)]
     struct Temp(i64);
     let single_row: Temp = sqlx::query_as(CHECK_VOLUNTEER_FROM_CARD_ID)
+        .bind(organisation_id)
         .bind(card_id)
         .fetch_one(pool)
         .await?;
@@ -170,10 +980,15 @@ pub(crate) async fn query_check_card_id(pool: &PgPool, card_id: i16) -> Result<b
 }
 
 // Check whether a volunteer exists from surname
-pub(crate) async fn query_check_surname(pool: &PgPool, surname: &str) -> Result<bool> {
+pub(crate) async fn query_check_surname(
+    pool: &PgPool,
+    organisation_id: i32,
+    surname: &str,
+) -> Result<bool> {
     #[derive(FromRow, Serialize, Deserialize)]
     struct Temp(i64);
     let single_row: Temp = sqlx::query_as(CHECK_VOLUNTEER_FROM_SURNAME)
+        .bind(organisation_id)
         .bind(surname)
         .fetch_one(pool)
         .await?;
@@ -183,12 +998,14 @@ pub(crate) async fn query_check_surname(pool: &PgPool, surname: &str) -> Result<
 // Check whether a volunteer exists from surname
 pub(crate) async fn query_card_id_to_surname(
     pool: &PgPool,
+    organisation_id: i32,
     card_id: i16,
     surname: &str,
 ) -> Result<bool> {
     #[derive(FromRow, Serialize, Deserialize)]
     struct Temp(i64);
     let single_row: Temp = sqlx::query_as(CHECK_VOLUNTEER_CARD_ID_TO_SURNAME)
+        .bind(organisation_id)
         .bind(card_id)
         .bind(surname)
         .fetch_one(pool)
@@ -197,10 +1014,15 @@ pub(crate) async fn query_card_id_to_surname(
 }
 
 // Check whether a volunteer is disabled
-pub(crate) async fn query_is_disabled(pool: &PgPool, card_id: i16) -> Result<bool> {
+pub(crate) async fn query_is_disabled(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<bool> {
     #[derive(FromRow, Serialize, Deserialize)]
     struct Temp(i64);
     let single_row: Temp = sqlx::query_as(CHECK_VOLUNTEER_IS_DISABLED)
+        .bind(organisation_id)
         .bind(card_id)
         .fetch_one(pool)
         .await?;
@@ -208,13 +1030,18 @@ pub(crate) async fn query_is_disabled(pool: &PgPool, card_id: i16) -> Result<boo
 }
 
 // Get volunteer name and surname
-pub(crate) async fn query_volunteer_surname_name(pool: &PgPool, id: i16) -> Result<String> {
+pub(crate) async fn query_volunteer_surname_name(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i16,
+) -> Result<String> {
     #[derive(FromRow, Serialize, Deserialize)]
     struct Temp {
         name: String,
         surname: String,
     }
     let volunteer: Temp = sqlx::query_as(GET_VOLUNTEER_NAME_SURNAME)
+        .bind(organisation_id)
         .bind(id)
         .fetch_one(pool)
         .await?;
@@ -222,10 +1049,42 @@ pub(crate) async fn query_volunteer_surname_name(pool: &PgPool, id: i16) -> Resu
     Ok(format!("{} {}", volunteer.surname, volunteer.name))
 }
 
+// Get volunteer data from card identification
+pub(crate) async fn query_volunteer(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<Volunteer> {
+    Ok(sqlx::query_as(GET_VOLUNTEER)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_one(pool)
+        .await?)
+}
+
+// Get the shifts served by a volunteer inside a date range
+pub(crate) async fn query_served_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Shift>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_SERVED_SHIFTS)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await?)
+}
+
 // Get volunteers names associated to specific shifts
 pub(crate) async fn query_volunteers_shifts(
     pool: &PgPool,
+    organisation_id: i32,
     date: (i32, u32, u32),
+    location_id: i16,
     task: i16,
 ) -> Result<Vec<String>> {
     #[derive(FromRow, Serialize, Deserialize)]
@@ -235,7 +1094,9 @@ pub(crate) async fn query_volunteers_shifts(
     }
     let date = insert_db_date_error(date)?;
     let volunteers: Vec<Temp> = sqlx::query_as(GET_VOLUNTEERS_NAMES)
+        .bind(organisation_id)
         .bind(date)
+        .bind(location_id)
         .bind(task)
         .fetch_all(pool)
         .await?;
@@ -247,12 +1108,17 @@ pub(crate) async fn query_volunteers_shifts(
 }
 
 // Get volunteer name from card identification
-pub(crate) async fn query_volunteer_name(pool: &PgPool, card_id: i16) -> Result<String> {
+pub(crate) async fn query_volunteer_name(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<String> {
     #[derive(FromRow, Serialize, Deserialize)]
     struct Temp {
         name: String,
     }
     let volunteer: Temp = sqlx::query_as(GET_VOLUNTEER_NAME)
+        .bind(organisation_id)
         .bind(card_id)
         .fetch_one(pool)
         .await?;
@@ -260,75 +1126,1484 @@ pub(crate) async fn query_volunteer_name(pool: &PgPool, card_id: i16) -> Result<
 }
 
 // Get all volunteers data
-pub(crate) async fn query_volunteers(pool: &PgPool) -> Result<Vec<VolunteerManager>> {
-    Ok(sqlx::query_as(GET_ALL_VOLUNTEERS).fetch_all(pool).await?)
+pub(crate) async fn query_volunteers(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<VolunteerManager>> {
+    Ok(sqlx::query_as(GET_ALL_VOLUNTEERS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
 }
 
-// Fill volunteers table
-pub(crate) async fn fill_volunteers_table(pool: &PgPool, volunteers_url: &str) -> Result<()> {
-    // Download volunteers from Google Sheet file and return them
-    let volunteers = download_file(volunteers_url).await?;
+// Count all volunteers
+pub(crate) async fn query_volunteers_count(pool: &PgPool, organisation_id: i32) -> Result<i64> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i64);
+    let count: Temp = sqlx::query_as(COUNT_VOLUNTEERS)
+        .bind(organisation_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(count.0)
+}
 
-    // Insert data inside volunteers table
-    for volunteer in &volunteers {
-        insert_update_volunteer(pool, INSERT_VOLUNTEERS, volunteer).await?;
-    }
+// Get a page of all volunteers data
+pub(crate) async fn query_volunteers_page(
+    pool: &PgPool,
+    organisation_id: i32,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<VolunteerManager>> {
+    Ok(sqlx::query_as(GET_VOLUNTEERS_PAGE)
+        .bind(organisation_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?)
+}
 
-    Ok(())
+// Pattern matching the searched text, whose wildcards are escaped
+fn search_pattern(text: &str) -> String {
+    let text = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{text}%")
+}
+
+// Count the volunteers found by a search
+pub(crate) async fn query_searched_volunteers_count(
+    pool: &PgPool,
+    organisation_id: i32,
+    search: &VolunteersSearch<'_>,
+) -> Result<i64> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i64);
+    let count: Temp = sqlx::query_as(COUNT_SEARCHED_VOLUNTEERS)
+        .bind(organisation_id)
+        .bind(search.text)
+        .bind(search_pattern(search.text))
+        .bind(search.disabled)
+        .bind(search.has_shifts)
+        .bind(search.period.0)
+        .bind(search.period.1)
+        .fetch_one(pool)
+        .await?;
+    Ok(count.0)
+}
+
+// Get a page of the volunteers found by a search
+pub(crate) async fn search_volunteers(
+    pool: &PgPool,
+    organisation_id: i32,
+    search: &VolunteersSearch<'_>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<VolunteerManager>> {
+    Ok(sqlx::query_as(SEARCH_VOLUNTEERS)
+        .bind(organisation_id)
+        .bind(search.text)
+        .bind(search_pattern(search.text))
+        .bind(search.disabled)
+        .bind(search.has_shifts)
+        .bind(search.period.0)
+        .bind(search.period.1)
+        .bind(search.order)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?)
 }
 
 // Refill volunteers table
-pub(crate) async fn refill_volunteers_table(pool: &PgPool, volunteers_url: &str) -> Result<()> {
+pub(crate) async fn refill_volunteers_table(
+    pool: &PgPool,
+    organisation_id: i32,
+    volunteers_url: &str,
+) -> Result<VolunteersChanges> {
     // Download volunteers from Google Sheet file and return them
-    let volunteers = download_file(volunteers_url).await?;
+    let mut volunteers = download_file(volunteers_url).await?;
+
+    // Values changed by referents replace the ones of the file, whose
+    // current values are remembered to restore them
+    let overrides: HashMap<i16, VolunteerOverride> =
+        query_volunteer_overrides(pool, organisation_id)
+            .await?
+            .into_iter()
+            .map(|local| (local.card_id, local))
+            .collect();
+    for volunteer in &mut volunteers {
+        let Some(local) = overrides.get(&volunteer.volunteer.card_id) else {
+            continue;
+        };
+        if local.source_surname != volunteer.volunteer.surname
+            || local.source_name != volunteer.volunteer.name
+            || local.source_phone_number != volunteer.phone_number
+            || local.source_disabled != volunteer.volunteer.disabled
+        {
+            insert_update_volunteer(pool, organisation_id, UPDATE_OVERRIDE_SOURCE, volunteer)
+                .await?;
+        }
+        if let Some(surname) = &local.surname {
+            volunteer.volunteer.surname.clone_from(surname);
+        }
+        if let Some(name) = &local.name {
+            volunteer.volunteer.name.clone_from(name);
+        }
+        if let Some(phone_number) = &local.phone_number {
+            volunteer.phone_number.clone_from(phone_number);
+        }
+        if let Some(disabled) = local.disabled {
+            volunteer.volunteer.disabled = disabled;
+        }
+    }
+
+    // Saved volunteers, used to find out which ones changed
+    let saved_volunteers: HashMap<i16, VolunteerManager> = query_volunteers(pool, organisation_id)
+        .await?
+        .into_iter()
+        .map(|volunteer| (volunteer.volunteer.card_id, volunteer))
+        .collect();
 
     // Update or insert new data inside volunteers table
+    let mut changes = VolunteersChanges::default();
     for volunteer in &volunteers {
         // If a volunteer is already present in the table, update data,
         // otherwise insert new data
-        if query_check_card_id(pool, volunteer.volunteer.card_id).await? {
-            insert_update_volunteer(pool, UPDATE_VOLUNTEER, volunteer).await?;
-        } else {
-            insert_update_volunteer(pool, INSERT_VOLUNTEERS, volunteer).await?;
+        match saved_volunteers.get(&volunteer.volunteer.card_id) {
+            Some(saved) if saved == volunteer => {}
+            Some(_) => {
+                insert_update_volunteer(pool, organisation_id, UPDATE_VOLUNTEER, volunteer).await?;
+                changes.updated.push(volunteer.volunteer.card_id);
+            }
+            None => {
+                insert_update_volunteer(pool, organisation_id, INSERT_VOLUNTEERS, volunteer)
+                    .await?;
+                changes.added.push(volunteer.volunteer.card_id);
+            }
         }
     }
 
-    Ok(())
-}
-
-// Fill shifts table
-//
-// Do not check whether a shift is already present through card id and date
-// because a cookie to avoid inserting the same data again
-pub(crate) async fn fill_shifts_table(pool: &PgPool, shifts: HashSet<Shift>) -> Result<()> {
-    // Insert data inside shifts table
-    for shift in shifts {
-        sqlx::query(INSERT_SHIFTS)
-            .bind(shift.date)
-            .bind(shift.task)
-            .bind(shift.card_id)
-            .execute(pool)
-            .await?;
-    }
-    Ok(())
+    Ok(changes)
 }
 
-// Delete a shift using the id
-pub(crate) async fn delete_shift(pool: &PgPool, id: i32) -> Result<()> {
-    // Delete a shift
-    sqlx::query(DELETE_SHIFT).bind(id).execute(pool).await?;
+// Delete a shift of a volunteer using the id
+pub(crate) async fn delete_shift(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i32,
+    card_id: i16,
+) -> Result<Option<Shift>> {
+    // Delete a shift, returning it if it existed
+    Ok(sqlx::query_as(DELETE_SHIFT)
+        .bind(organisation_id)
+        .bind(id)
+        .bind(card_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Insert a single shift, returning its identifier, or none when its
+// location has no more places for the task. It can be run inside a
+// transaction.
+pub(crate) async fn insert_shift<'c>(
+    executor: impl Executor<'c, Database = Postgres>,
+    organisation_id: i32,
+    shift: &Shift,
+) -> Result<Option<i32>> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i32);
+    let single_row: Option<Temp> = sqlx::query_as(INSERT_SHIFT)
+        .bind(organisation_id)
+        .bind(shift.date)
+        .bind(shift.task)
+        .bind(shift.card_id)
+        .bind(shift.location_id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(single_row.map(|row| row.0))
+}
+
+// Lock the volunteers and the tasks of the locations of some shifts until the
+// end of a transaction, so that the shifts of the volunteers and the places of
+// the locations cannot change before the shifts are inserted. Volunteers are
+// always locked before locations, both in ascending order, so that concurrent
+// bookings cannot deadlock. Returns whether each existing volunteer is
+// disabled.
+pub(crate) async fn lock_booking(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    shifts: &[Shift],
+) -> Result<HashMap<i16, bool>> {
+    let card_ids: BTreeSet<i16> = shifts.iter().map(|shift| shift.card_id).collect();
+    let location_tasks: BTreeSet<(i16, i16)> = shifts
+        .iter()
+        .map(|shift| (shift.location_id, shift.task))
+        .collect();
+
+    #[derive(FromRow)]
+    struct Temp(i16, bool);
+    let volunteers: Vec<Temp> = sqlx::query_as(LOCK_VOLUNTEERS)
+        .bind(organisation_id)
+        .bind(Vec::from_iter(card_ids))
+        .fetch_all(&mut *conn)
+        .await?;
+    for (location_id, task) in location_tasks {
+        sqlx::query(LOCK_LOCATION_TASK)
+            .bind(organisation_id)
+            .bind(location_id)
+            .bind(task)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(volunteers.into_iter().map(|row| (row.0, row.1)).collect())
+}
+
+// Delete a shift which is not passed at the given date, returning it if it existed
+pub(crate) async fn delete_current_shift(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i32,
+    today: NaiveDate,
+) -> Result<Option<Shift>> {
+    Ok(sqlx::query_as(DELETE_CURRENT_SHIFT)
+        .bind(organisation_id)
+        .bind(id)
+        .bind(today)
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Delete shifts with a date older than the given one, returning their number
+// https://www.postgresqltutorial.com/postgresql-tutorial/postgresql-delete/
+pub(crate) async fn delete_old_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    before: NaiveDate,
+) -> Result<u64> {
+    Ok(sqlx::query(DELETE_OLD_SHIFTS)
+        .bind(organisation_id)
+        .bind(before)
+        .execute(pool)
+        .await?
+        .rows_affected())
+}
+
+// Get the number of archived shifts, older than the given date, and the date
+// of the oldest one
+pub(crate) async fn query_archived_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    today: NaiveDate,
+) -> Result<(i64, Option<NaiveDate>)> {
+    Ok(sqlx::query_as(GET_ARCHIVED_SHIFTS)
+        .bind(organisation_id)
+        .bind(today)
+        .fetch_one(pool)
+        .await?)
+}
+
+// Get the last shift served until the given date by each enabled volunteer
+pub(crate) async fn query_last_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    today: NaiveDate,
+) -> Result<Vec<LastShift>> {
+    Ok(sqlx::query_as(GET_LAST_SHIFTS)
+        .bind(organisation_id)
+        .bind(today)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the tasks of an organisation
+pub(crate) async fn query_tasks(pool: &PgPool, organisation_id: i32) -> Result<Vec<Task>> {
+    Ok(sqlx::query_as(GET_TASKS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the locations of an organisation
+pub(crate) async fn query_locations(pool: &PgPool, organisation_id: i32) -> Result<Vec<Location>> {
+    Ok(sqlx::query_as(GET_LOCATIONS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the tasks offered by the locations of an organisation
+pub(crate) async fn query_location_tasks(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<LocationTask>> {
+    Ok(sqlx::query_as(GET_LOCATION_TASKS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get all organisations
+pub(crate) async fn query_organisations(pool: &PgPool) -> Result<Vec<Organisation>> {
+    Ok(sqlx::query_as(GET_ORGANISATIONS).fetch_all(pool).await?)
+}
+
+// Save the data of the first organisation, which are contained in the secrets
+pub(crate) async fn update_default_organisation(
+    pool: &PgPool,
+    name: &str,
+    volunteers_url: &str,
+    password_hash: &str,
+) -> Result<()> {
+    sqlx::query(UPDATE_DEFAULT_ORGANISATION)
+        .bind(name)
+        .bind(volunteers_url)
+        .bind(password_hash)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Save a new API token, only its hash is stored
+pub(crate) async fn insert_api_token(
+    pool: &PgPool,
+    organisation_id: i32,
+    name: &str,
+    token_hash: &str,
+    is_administration: bool,
+) -> Result<()> {
+    sqlx::query(INSERT_API_TOKEN)
+        .bind(organisation_id)
+        .bind(name)
+        .bind(token_hash)
+        .bind(is_administration)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get all API tokens
+pub(crate) async fn query_api_tokens(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<ApiTokenInfo>> {
+    Ok(sqlx::query_as(GET_API_TOKENS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Retrieve the API token with the given hash, recording its use
+pub(crate) async fn use_api_token(
+    pool: &PgPool,
+    organisation_id: i32,
+    token_hash: &str,
+) -> Result<Option<ApiTokenInfo>> {
+    Ok(sqlx::query_as(USE_API_TOKEN)
+        .bind(organisation_id)
+        .bind(token_hash)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Revoke an API token
+pub(crate) async fn delete_api_token(pool: &PgPool, organisation_id: i32, id: i32) -> Result<()> {
+    sqlx::query(DELETE_API_TOKEN)
+        .bind(organisation_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Save a new webhook
+pub(crate) async fn insert_webhook(
+    pool: &PgPool,
+    organisation_id: i32,
+    url: &str,
+    secret: &str,
+) -> Result<()> {
+    sqlx::query(INSERT_WEBHOOK)
+        .bind(organisation_id)
+        .bind(url)
+        .bind(secret)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get all webhooks
+pub(crate) async fn query_webhooks(pool: &PgPool, organisation_id: i32) -> Result<Vec<Webhook>> {
+    Ok(sqlx::query_as(GET_WEBHOOKS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Delete a webhook together with its deliveries
+pub(crate) async fn delete_webhook(pool: &PgPool, organisation_id: i32, id: i32) -> Result<()> {
+    sqlx::query(DELETE_WEBHOOK)
+        .bind(organisation_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Create a delivery of an event for every webhook
+pub(crate) async fn insert_webhook_deliveries(
+    pool: &PgPool,
+    organisation_id: i32,
+    event: &str,
+    payload: &str,
+    status: &str,
+) -> Result<()> {
+    sqlx::query(INSERT_WEBHOOK_DELIVERIES)
+        .bind(organisation_id)
+        .bind(event)
+        .bind(payload)
+        .bind(status)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get the deliveries whose next attempt is due
+pub(crate) async fn query_pending_deliveries(
+    pool: &PgPool,
+    status: &str,
+    limit: i64,
+) -> Result<Vec<PendingDelivery>> {
+    Ok(sqlx::query_as(GET_PENDING_DELIVERIES)
+        .bind(status)
+        .bind(Utc::now())
+        .bind(limit)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Save the outcome of a delivery attempt
+pub(crate) async fn update_delivery(pool: &PgPool, delivery: &DeliveryUpdate) -> Result<()> {
+    sqlx::query(UPDATE_DELIVERY)
+        .bind(delivery.id)
+        .bind(&delivery.status)
+        .bind(delivery.attempts)
+        .bind(&delivery.response)
+        .bind(delivery.next_attempt)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get the most recent deliveries
+pub(crate) async fn query_deliveries(
+    pool: &PgPool,
+    organisation_id: i32,
+    limit: i64,
+) -> Result<Vec<Delivery>> {
+    Ok(sqlx::query_as(GET_DELIVERIES)
+        .bind(organisation_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Schedule a new attempt of a delivery as soon as possible
+pub(crate) async fn retry_delivery(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i32,
+    status: &str,
+) -> Result<()> {
+    sqlx::query(RETRY_DELIVERY)
+        .bind(organisation_id)
+        .bind(id)
+        .bind(status)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Delete the completed deliveries created before the given date and time
+pub(crate) async fn delete_old_deliveries(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+    pending_status: &str,
+) -> Result<u64> {
+    Ok(sqlx::query(DELETE_OLD_DELIVERIES)
+        .bind(before)
+        .bind(pending_status)
+        .execute(pool)
+        .await?
+        .rows_affected())
+}
+
+// Get the value of a setting, if it has ever been saved
+pub(crate) async fn query_setting(
+    pool: &PgPool,
+    organisation_id: i32,
+    key: &str,
+) -> Result<Option<String>> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(String);
+    let single_row: Option<Temp> = sqlx::query_as(GET_SETTING)
+        .bind(organisation_id)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(single_row.map(|row| row.0))
+}
+
+// Get all saved settings
+pub(crate) async fn query_settings(pool: &PgPool, organisation_id: i32) -> Result<Vec<Setting>> {
+    Ok(sqlx::query_as(GET_ALL_SETTINGS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Save the value of a setting
+pub(crate) async fn update_setting(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    setting: &Setting,
+) -> Result<()> {
+    sqlx::query(UPDATE_SETTING)
+        .bind(organisation_id)
+        .bind(&setting.key)
+        .bind(&setting.value)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Increment failed login attempts and return their number
+pub(crate) async fn record_failed_login(
+    pool: &PgPool,
+    organisation_id: i32,
+    kind: &str,
+    identifier: &str,
+) -> Result<i32> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i32);
+    let single_row: Temp = sqlx::query_as(INSERT_FAILED_LOGIN)
+        .bind(organisation_id)
+        .bind(kind)
+        .bind(identifier)
+        .fetch_one(pool)
+        .await?;
+    Ok(single_row.0)
+}
+
+// Lock login until the given time
+pub(crate) async fn lock_login(
+    pool: &PgPool,
+    organisation_id: i32,
+    kind: &str,
+    identifier: &str,
+    locked_until: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(LOCK_LOGIN)
+        .bind(organisation_id)
+        .bind(kind)
+        .bind(identifier)
+        .bind(locked_until)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get the lockout end of a login, if it is still locked
+pub(crate) async fn query_login_lock(
+    pool: &PgPool,
+    organisation_id: i32,
+    kind: &str,
+    identifier: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(DateTime<Utc>);
+    let single_row: Option<Temp> = sqlx::query_as(GET_LOGIN_LOCK)
+        .bind(organisation_id)
+        .bind(kind)
+        .bind(identifier)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await?;
+    Ok(single_row.map(|row| row.0))
+}
+
+// Get all currently locked logins
+pub(crate) async fn query_locked_logins(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<LoginAttempt>> {
+    Ok(sqlx::query_as(GET_LOCKED_LOGINS)
+        .bind(organisation_id)
+        .bind(Utc::now())
+        .fetch_all(pool)
+        .await?)
+}
+
+// Delete login attempts, unlocking the login
+pub(crate) async fn delete_login_attempts(
+    pool: &PgPool,
+    organisation_id: i32,
+    kind: &str,
+    identifier: &str,
+) -> Result<()> {
+    sqlx::query(DELETE_LOGIN_ATTEMPTS)
+        .bind(organisation_id)
+        .bind(kind)
+        .bind(identifier)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Insert a new session
+pub(crate) async fn insert_session(
+    pool: &PgPool,
+    organisation_id: i32,
+    token: &str,
+    card_id: i16,
+    is_administration: bool,
+    user_agent: &str,
+) -> Result<()> {
+    sqlx::query(INSERT_SESSION)
+        .bind(organisation_id)
+        .bind(token)
+        .bind(card_id)
+        .bind(is_administration)
+        .bind(user_agent)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get a session which is not expired, refreshing its last request time
+pub(crate) async fn query_refresh_session(
+    pool: &PgPool,
+    organisation_id: i32,
+    token: &str,
+    idle_limit: DateTime<Utc>,
+    absolute_limit: DateTime<Utc>,
+) -> Result<Option<SessionData>> {
+    Ok(sqlx::query_as(REFRESH_SESSION)
+        .bind(organisation_id)
+        .bind(token)
+        .bind(Utc::now())
+        .bind(idle_limit)
+        .bind(absolute_limit)
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Get all sessions which are not expired
+pub(crate) async fn query_active_sessions(
+    pool: &PgPool,
+    organisation_id: i32,
+    idle_limit: DateTime<Utc>,
+    absolute_limit: DateTime<Utc>,
+) -> Result<Vec<ActiveSession>> {
+    Ok(sqlx::query_as(GET_ACTIVE_SESSIONS)
+        .bind(organisation_id)
+        .bind(idle_limit)
+        .bind(absolute_limit)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Delete a session using the id
+pub(crate) async fn delete_session(pool: &PgPool, organisation_id: i32, id: i32) -> Result<()> {
+    sqlx::query(DELETE_SESSION)
+        .bind(organisation_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Delete a session using its token
+pub(crate) async fn delete_session_from_token(
+    pool: &PgPool,
+    organisation_id: i32,
+    token: &str,
+) -> Result<()> {
+    sqlx::query(DELETE_SESSION_FROM_TOKEN)
+        .bind(organisation_id)
+        .bind(token)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Delete expired sessions
+pub(crate) async fn delete_expired_sessions(
+    pool: &PgPool,
+    idle_limit: DateTime<Utc>,
+    absolute_limit: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(DELETE_EXPIRED_SESSIONS)
+        .bind(idle_limit)
+        .bind(absolute_limit)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get volunteer phone number from card identification
+pub(crate) async fn query_volunteer_phone_number(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<String> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(String);
+    let single_row: Temp = sqlx::query_as(GET_VOLUNTEER_PHONE_NUMBER)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(single_row.0)
+}
+
+// Save the hash of a login code, replacing the previous one
+pub(crate) async fn insert_login_code(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    code_hash: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(INSERT_LOGIN_CODE)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(code_hash)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get the hash of a login code which is not expired, counting the
+// verification attempt
+pub(crate) async fn query_login_code(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    max_attempts: i32,
+) -> Result<Option<String>> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(String);
+    let single_row: Option<Temp> = sqlx::query_as(GET_LOGIN_CODE)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(Utc::now())
+        .bind(max_attempts)
+        .fetch_optional(pool)
+        .await?;
+    Ok(single_row.map(|row| row.0))
+}
+
+// Delete the login code of a volunteer
+pub(crate) async fn delete_login_code(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<()> {
+    sqlx::query(DELETE_LOGIN_CODE)
+        .bind(organisation_id)
+        .bind(card_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Insert a new invitation
+pub(crate) async fn insert_invitation(
+    pool: &PgPool,
+    organisation_id: i32,
+    token: &str,
+    card_id: i16,
+    single_use: bool,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(INSERT_INVITATION)
+        .bind(organisation_id)
+        .bind(token)
+        .bind(card_id)
+        .bind(single_use)
+        .bind(Utc::now())
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get all invitations which are not expired
+pub(crate) async fn query_invitations(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<Invitation>> {
+    Ok(sqlx::query_as(GET_INVITATIONS)
+        .bind(organisation_id)
+        .bind(Utc::now())
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the card identification of the volunteer invited by an invitation
+// which is not expired, without using it
+pub(crate) async fn query_invitation_card_id(
+    pool: &PgPool,
+    organisation_id: i32,
+    token: &str,
+) -> Result<Option<i16>> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i16);
+    let single_row: Option<Temp> = sqlx::query_as(GET_INVITATION_CARD_ID)
+        .bind(organisation_id)
+        .bind(token)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await?;
+    Ok(single_row.map(|row| row.0))
+}
+
+// Use an invitation which is not expired, returning the card identification
+// of the invited volunteer. Single-use invitations are deleted.
+pub(crate) async fn use_invitation(
+    pool: &PgPool,
+    organisation_id: i32,
+    token: &str,
+) -> Result<Option<i16>> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(i16);
+    let single_row: Option<Temp> = sqlx::query_as(USE_INVITATION)
+        .bind(organisation_id)
+        .bind(token)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await?;
+    Ok(single_row.map(|row| row.0))
+}
+
+// Delete an invitation using the id
+pub(crate) async fn delete_invitation(pool: &PgPool, organisation_id: i32, id: i32) -> Result<()> {
+    sqlx::query(DELETE_INVITATION)
+        .bind(organisation_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
-// Delete shifts with a date older than the current date
-// https://www.postgresqltutorial.com/postgresql-tutorial/postgresql-delete/
-pub(crate) async fn delete_old_shifts(pool: &PgPool) -> Result<()> {
-    // Delete all shifts
-    sqlx::query(DELETE_OLD_SHIFTS).execute(pool).await?;
+// Delete all expired invitations
+pub(crate) async fn delete_expired_invitations(pool: &PgPool) -> Result<()> {
+    sqlx::query(DELETE_EXPIRED_INVITATIONS)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get volunteer data, contacts included, when the card exists
+pub(crate) async fn query_volunteer_profile(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<Option<VolunteerManager>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_PROFILE)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Get the most recent shifts served by a volunteer
+pub(crate) async fn query_past_shifts(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    today: NaiveDate,
+    limit: i64,
+) -> Result<Vec<ShiftQuery>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_PAST_SHIFTS)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(today)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the tasks served by a volunteer, the most frequent first
+pub(crate) async fn query_served_tasks(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    today: NaiveDate,
+) -> Result<Vec<ServedTask>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_SERVED_TASKS)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(today)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Insert a note about a volunteer
+pub(crate) async fn insert_volunteer_note(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    author: &str,
+    text: &str,
+) -> Result<()> {
+    sqlx::query(INSERT_VOLUNTEER_NOTE)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(author)
+        .bind(text)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get all notes about a volunteer, the most recent first
+pub(crate) async fn query_volunteer_notes(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<Vec<VolunteerNote>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_NOTES)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Delete a note about a volunteer
+pub(crate) async fn delete_volunteer_note(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i32,
+    card_id: i16,
+) -> Result<()> {
+    sqlx::query(DELETE_VOLUNTEER_NOTE)
+        .bind(organisation_id)
+        .bind(id)
+        .bind(card_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get all notes about volunteers
+pub(crate) async fn query_every_volunteer_note(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<CardNote>> {
+    Ok(sqlx::query_as(GET_EVERY_VOLUNTEER_NOTE)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the volunteer values changed by referents
+pub(crate) async fn query_volunteer_overrides(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<VolunteerOverride>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_OVERRIDES)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the values of a volunteer changed by referents, if any
+pub(crate) async fn query_volunteer_override(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<Option<VolunteerOverride>> {
+    Ok(sqlx::query_as(GET_VOLUNTEER_OVERRIDE)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Change the data of a volunteer locally, the changes are kept when the
+// volunteers file is imported again
+pub(crate) async fn set_volunteer_override(
+    pool: &PgPool,
+    organisation_id: i32,
+    volunteer: &VolunteerManager,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    override_volunteer(&mut tx, organisation_id, volunteer).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn override_volunteer(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    volunteer: &VolunteerManager,
+) -> Result<()> {
+    for query in [SET_VOLUNTEER_OVERRIDE, UPDATE_VOLUNTEER] {
+        sqlx::query(query)
+            .bind(organisation_id)
+            .bind(volunteer.volunteer.card_id)
+            .bind(&volunteer.volunteer.surname)
+            .bind(&volunteer.volunteer.name)
+            .bind(&volunteer.phone_number)
+            .bind(volunteer.volunteer.disabled)
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query(DELETE_EMPTY_OVERRIDE)
+        .bind(organisation_id)
+        .bind(volunteer.volunteer.card_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Bring back the data of the volunteers file, removing the local changes
+pub(crate) async fn delete_volunteer_override(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    for query in [RESTORE_VOLUNTEER_SOURCE, DELETE_VOLUNTEER_OVERRIDE] {
+        sqlx::query(query)
+            .bind(organisation_id)
+            .bind(card_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// Get the contact preferences of a volunteer, the default ones when never
+// chosen
+pub(crate) async fn query_volunteer_preferences(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<VolunteerPreferences> {
+    let preferences: Option<VolunteerPreferences> = sqlx::query_as(GET_VOLUNTEER_PREFERENCES)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(preferences.unwrap_or_default())
+}
+
+// Save the contact preferences of a volunteer. It can be run inside a
+// transaction.
+pub(crate) async fn set_volunteer_preferences<'c>(
+    executor: impl Executor<'c, Database = Postgres>,
+    organisation_id: i32,
+    card_id: i16,
+    preferences: &VolunteerPreferences,
+) -> Result<()> {
+    sqlx::query(SET_VOLUNTEER_PREFERENCES)
+        .bind(organisation_id)
+        .bind(card_id)
+        .bind(&preferences.email)
+        .bind(preferences.notify_email)
+        .bind(preferences.notify_sms)
+        .bind(&preferences.language)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+// Save the data change asked by a volunteer, replacing the pending one
+pub(crate) async fn set_change_request(
+    pool: &PgPool,
+    organisation_id: i32,
+    volunteer: &VolunteerManager,
+) -> Result<()> {
+    sqlx::query(SET_CHANGE_REQUEST)
+        .bind(organisation_id)
+        .bind(volunteer.volunteer.card_id)
+        .bind(&volunteer.volunteer.surname)
+        .bind(&volunteer.volunteer.name)
+        .bind(&volunteer.phone_number)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Get the contact preferences of all volunteers
+pub(crate) async fn query_every_volunteer_preferences(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<CardPreferences>> {
+    Ok(sqlx::query_as(GET_EVERY_VOLUNTEER_PREFERENCES)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Save a data change asked by a volunteer, keeping its creation date and time
+pub(crate) async fn insert_requested_change(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    change: &RequestedChange,
+) -> Result<()> {
+    sqlx::query(SET_CHANGE_REQUEST)
+        .bind(organisation_id)
+        .bind(change.card_id)
+        .bind(&change.surname)
+        .bind(&change.name)
+        .bind(&change.phone_number)
+        .bind(change.created_at)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Get the data changes asked by volunteers, the oldest first
+pub(crate) async fn query_requested_changes(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<RequestedChange>> {
+    Ok(sqlx::query_as(GET_REQUESTED_CHANGES)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Delete the contact preferences and the data changes asked by all
+// volunteers
+pub(crate) async fn delete_all_preferences_changes(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+) -> Result<()> {
+    for query in [DELETE_ALL_VOLUNTEER_PREFERENCES, DELETE_ALL_CHANGE_REQUESTS] {
+        sqlx::query(query)
+            .bind(organisation_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+// Get the data changes waiting for a referent approval, the oldest first
+pub(crate) async fn query_change_requests(
+    pool: &PgPool,
+    organisation_id: i32,
+) -> Result<Vec<ChangeRequest>> {
+    Ok(sqlx::query_as(GET_CHANGE_REQUESTS)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Get the pending data change of a volunteer, if any
+pub(crate) async fn query_change_request(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<Option<ChangeRequest>> {
+    Ok(sqlx::query_as(GET_CHANGE_REQUEST)
+        .bind(organisation_id)
+        .bind(card_id)
+        .fetch_optional(pool)
+        .await?)
+}
+
+// Withdraw the pending data change of a volunteer
+pub(crate) async fn delete_change_request(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+) -> Result<()> {
+    sqlx::query(DELETE_CHANGE_REQUEST)
+        .bind(organisation_id)
+        .bind(card_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Discard a data change asked by a volunteer
+pub(crate) async fn reject_change_request(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i32,
+) -> Result<()> {
+    sqlx::query(REJECT_CHANGE_REQUEST)
+        .bind(organisation_id)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Apply a data change asked by a volunteer as local values, returning the
+// card identifier of the volunteer when the request still exists
+pub(crate) async fn approve_change_request(
+    pool: &PgPool,
+    organisation_id: i32,
+    id: i32,
+) -> Result<Option<i16>> {
+    let mut tx = pool.begin().await?;
+    let volunteer: Option<VolunteerManager> = sqlx::query_as(APPROVE_CHANGE_REQUEST)
+        .bind(organisation_id)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let Some(volunteer) = volunteer else {
+        return Ok(None);
+    };
+    override_volunteer(&mut tx, organisation_id, &volunteer).await?;
+    tx.commit().await?;
+    Ok(Some(volunteer.volunteer.card_id))
+}
+
+// Get all shifts, past ones included
+pub(crate) async fn query_every_shift(pool: &PgPool, organisation_id: i32) -> Result<Vec<Shift>> {
+    Ok(sqlx::query_as(GET_EVERY_SHIFT)
+        .bind(organisation_id)
+        .fetch_all(pool)
+        .await?)
+}
+
+// Insert a volunteer or update its data, returning whether the volunteer
+// has been inserted
+pub(crate) async fn upsert_volunteer(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    volunteer: &VolunteerManager,
+) -> Result<bool> {
+    #[derive(FromRow, Serialize, Deserialize)]
+    struct Temp(bool);
+    let single_row: Temp = sqlx::query_as(UPSERT_VOLUNTEER)
+        .bind(organisation_id)
+        .bind(volunteer.volunteer.card_id)
+        .bind(&volunteer.volunteer.surname)
+        .bind(&volunteer.volunteer.name)
+        .bind(&volunteer.phone_number)
+        .bind(volunteer.volunteer.disabled)
+        .fetch_one(conn)
+        .await?;
+    Ok(single_row.0)
+}
+
+// Insert a shift only if it is not already present, returning whether
+// the shift has been inserted
+pub(crate) async fn insert_missing_shift(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    shift: &Shift,
+) -> Result<bool> {
+    let result = sqlx::query(INSERT_MISSING_SHIFT)
+        .bind(organisation_id)
+        .bind(shift.date)
+        .bind(shift.task)
+        .bind(shift.card_id)
+        .bind(shift.location_id)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Insert a note about a volunteer only if it is not already present,
+// returning whether it has been inserted
+pub(crate) async fn insert_missing_volunteer_note(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    note: &CardNote,
+) -> Result<bool> {
+    let result = sqlx::query(INSERT_MISSING_VOLUNTEER_NOTE)
+        .bind(organisation_id)
+        .bind(note.card_id)
+        .bind(&note.note.author)
+        .bind(&note.note.text)
+        .bind(note.note.created_at)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Delete all notes about volunteers
+pub(crate) async fn delete_all_volunteer_notes(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+) -> Result<()> {
+    sqlx::query(DELETE_ALL_VOLUNTEER_NOTES)
+        .bind(organisation_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Save the values of a volunteer changed by referents, together with the
+// values of the file
+pub(crate) async fn upsert_volunteer_override(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    volunteer_override: &VolunteerOverride,
+) -> Result<()> {
+    sqlx::query(UPSERT_VOLUNTEER_OVERRIDE)
+        .bind(organisation_id)
+        .bind(volunteer_override.card_id)
+        .bind(&volunteer_override.surname)
+        .bind(&volunteer_override.name)
+        .bind(&volunteer_override.phone_number)
+        .bind(volunteer_override.disabled)
+        .bind(&volunteer_override.source_surname)
+        .bind(&volunteer_override.source_name)
+        .bind(&volunteer_override.source_phone_number)
+        .bind(volunteer_override.source_disabled)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Delete all volunteer values changed by referents
+pub(crate) async fn delete_all_volunteer_overrides(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+) -> Result<()> {
+    sqlx::query(DELETE_ALL_VOLUNTEER_OVERRIDES)
+        .bind(organisation_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Delete all shifts, returning their number
+pub(crate) async fn delete_all_shifts(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+) -> Result<u64> {
+    Ok(sqlx::query(DELETE_ALL_SHIFTS)
+        .bind(organisation_id)
+        .execute(conn)
+        .await?
+        .rows_affected())
+}
+
+// Delete all volunteers whose card identification is not contained in
+// the given ones, together with their data. Returns the number of deleted
+// volunteers.
+pub(crate) async fn delete_other_volunteers(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    card_ids: &[i16],
+) -> Result<u64> {
+    let mut deleted = 0;
+    for query in DELETE_OTHER_VOLUNTEERS {
+        deleted = sqlx::query(query)
+            .bind(organisation_id)
+            .bind(card_ids)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    }
+    Ok(deleted)
+}
+
+// Insert a task or update the saved one
+pub(crate) async fn upsert_task(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    task: &Task,
+) -> Result<()> {
+    sqlx::query(UPSERT_TASK)
+        .bind(organisation_id)
+        .bind(task.task_value)
+        .bind(&task.task_name)
+        .bind(&task.task_hours)
+        .execute(conn)
+        .await?;
     Ok(())
 }
 
-// Create volunteers and shifts tables at the start of application
+// Insert a location or update the saved one
+pub(crate) async fn upsert_location(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    location: &Location,
+) -> Result<()> {
+    sqlx::query(UPSERT_LOCATION)
+        .bind(organisation_id)
+        .bind(location.id)
+        .bind(&location.name)
+        .bind(&location.address)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Offer a task in a location or update its capacity
+pub(crate) async fn upsert_location_task(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    location_task: &LocationTask,
+) -> Result<()> {
+    sqlx::query(UPSERT_LOCATION_TASK)
+        .bind(organisation_id)
+        .bind(location_task.location_id)
+        .bind(location_task.task_id)
+        .bind(location_task.capacity)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Delete the tasks offered by every location
+pub(crate) async fn delete_location_tasks(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+) -> Result<()> {
+    sqlx::query(DELETE_LOCATION_TASKS)
+        .bind(organisation_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Delete the tasks and the locations whose identifiers are not contained in
+// the given ones. They must not be offered or referred by shifts anymore.
+pub(crate) async fn delete_other_tasks_locations(
+    conn: &mut PgConnection,
+    organisation_id: i32,
+    task_ids: &[i16],
+    location_ids: &[i16],
+) -> Result<()> {
+    sqlx::query(DELETE_OTHER_TASKS)
+        .bind(organisation_id)
+        .bind(task_ids)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(DELETE_OTHER_LOCATIONS)
+        .bind(organisation_id)
+        .bind(location_ids)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+// Create missing tables at the start of application, saved data are kept
 pub(crate) async fn create_volunteers_shifts_tables(pool: &PgPool) -> Result<PgQueryResult> {
     Ok(pool.execute(include_str!("../sql/schema.sql")).await?)
 }
@@ -370,10 +2645,12 @@ async fn download_file(volunteers_url: &str) -> Result<Vec<VolunteerManager>> {
 // Run insert and update volunteers queries
 async fn insert_update_volunteer(
     pool: &PgPool,
+    organisation_id: i32,
     query: &str,
     volunteer: &VolunteerManager,
 ) -> Result<()> {
     sqlx::query(query)
+        .bind(organisation_id)
         .bind(volunteer.volunteer.card_id)
         .bind(&volunteer.volunteer.surname)
         .bind(&volunteer.volunteer.name)
@@ -383,3 +2660,76 @@ async fn insert_update_volunteer(
         .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+    use super::*;
+
+    // Schema of the first release, which has neither organisations nor
+    // locations
+    const BASELINE_SCHEMA: &str = "CREATE TABLE volunteers (
+          card_id smallint NOT NULL PRIMARY KEY,
+          phone_number VARCHAR(20) NOT NULL,
+          name VARCHAR(60) NOT NULL,
+          surname VARCHAR(50) NOT NULL,
+          disabled BOOLEAN NOT NULL
+        );
+        CREATE TABLE shifts (
+          id serial PRIMARY KEY,
+          date DATE NOT NULL,
+          task smallint NOT NULL,
+          card_id smallint NOT NULL,
+          FOREIGN KEY(card_id)
+              REFERENCES volunteers(card_id)
+        );
+        INSERT INTO volunteers VALUES (1, '3331112222', 'Mario', 'Rossi', false);
+        INSERT INTO shifts(date, task, card_id) VALUES ('2024-03-04', 1, 1);";
+
+    // Schema used by the test, dropped at its end
+    const TEST_SCHEMA: &str = "upgrade_test";
+
+    // The upgrade needs a database, so the test is skipped without one
+    #[rocket::async_test]
+    async fn upgrade_from_baseline_schema() {
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let options = PgConnectOptions::from_str(&url)
+            .unwrap()
+            .options([("search_path", TEST_SCHEMA)]);
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+        pool.execute(
+            format!("DROP SCHEMA IF EXISTS {TEST_SCHEMA} CASCADE; CREATE SCHEMA {TEST_SCHEMA}")
+                .as_str(),
+        )
+        .await
+        .unwrap();
+        pool.execute(BASELINE_SCHEMA).await.unwrap();
+
+        // Tables are upgraded once, then left untouched at each start
+        create_volunteers_shifts_tables(&pool).await.unwrap();
+        create_volunteers_shifts_tables(&pool).await.unwrap();
+
+        let organisation_id = query_organisations(&pool).await.unwrap()[0].id;
+        let volunteers = query_volunteers(&pool, organisation_id).await.unwrap();
+        assert_eq!(volunteers.len(), 1);
+        assert_eq!(volunteers[0].volunteer.surname, "Rossi");
+        let shifts = query_exported_shifts(&pool, organisation_id, None, None, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(shifts[0].shift.location_id, DEFAULT_LOCATION);
+
+        pool.execute(format!("DROP SCHEMA {TEST_SCHEMA} CASCADE").as_str())
+            .await
+            .unwrap();
+    }
+}
@@ -1,29 +1,336 @@
+use anyhow::Result;
+
+use chrono::{Datelike, NaiveDate, ParseError};
+
 use rocket::http::uri::Origin;
-use rocket::http::CookieJar;
-use rocket::serde::json::Json;
+use rocket::http::{ContentType, Header};
 use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+use serde::Serialize;
 
-use crate::database::{query_all_shifts, Shift};
+use crate::data::{Locations, Tasks};
+use crate::database::{
+    insert_db_date, query_exported_shifts, query_tasks, ExportedShift, Organisation,
+};
 use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::Date;
+use crate::{AppState, DOWNLOAD_DATABASE_ROUTE};
+
+// Columns of exported files
+const HEADERS: [&str; 8] = [
+    "export-date",
+    "export-task",
+    "export-hours",
+    "export-card-id",
+    "export-surname",
+    "export-name",
+    "export-location",
+    "export-address",
+];
+
+// Format of dates received from forms and written in file names
+pub(crate) const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Messages
+const HEADING_MESSAGE: &str = "export-heading";
+const FROM_MESSAGE: &str = "period-from";
+const TO_MESSAGE: &str = "period-to";
+const FORMAT_MESSAGE: &str = "export-format";
+const EXPORT_MESSAGE: &str = "export-download";
+const SHEET_MESSAGE: &str = "export-sheet";
+// Shown when a date received from a form is not valid
+pub(crate) const INVALID_DATE_MESSAGE: &str = "error-invalid-date";
+
+// Format of an exported file
+#[derive(FromFormField, Default)]
+enum ExportFormat {
+    Csv,
+    Xlsx,
+    #[default]
+    Json,
+}
+
+// Format and period chosen in the export page
+#[derive(FromForm, Default)]
+struct ExportFilter<'r> {
+    format: Option<ExportFormat>,
+    from: Option<&'r str>,
+    to: Option<&'r str>,
+}
+
+// Shift written in exported files
+#[derive(Serialize)]
+struct ExportRow {
+    // Shift date
+    date: NaiveDate,
+    // Task name
+    task: String,
+    // Task hours
+    hours: String,
+    // Volunteer card identifier
+    card_id: i16,
+    // Volunteer surname
+    surname: String,
+    // Volunteer name
+    name: String,
+    // Location name
+    location: String,
+    // Location address
+    address: String,
+}
+
+impl ExportRow {
+    fn rows(tasks: &Tasks, locations: &Locations, shifts: Vec<ExportedShift>) -> Vec<Self> {
+        shifts
+            .into_iter()
+            .map(|exported| Self {
+                date: exported.shift.date,
+                task: tasks.task_from_id(exported.shift.task),
+                hours: tasks.hours_from_id(exported.shift.task),
+                card_id: exported.shift.card_id,
+                surname: exported.surname,
+                name: exported.name,
+                location: locations.name_from_id(exported.shift.location_id),
+                address: locations.address_from_id(exported.shift.location_id),
+            })
+            .collect()
+    }
+}
+
+// Exported file sent as attachment
+#[derive(Responder)]
+pub(crate) struct ExportFile {
+    // File content
+    content: Vec<u8>,
+    // File type
+    content_type: ContentType,
+    // File name
+    disposition: Header<'static>,
+}
+
+impl ExportFile {
+    pub(crate) fn new(content: Vec<u8>, content_type: ContentType, file_name: String) -> Self {
+        Self::with_disposition(content, content_type, "attachment", file_name)
+    }
+
+    // File shown by the browser instead of being downloaded
+    pub(crate) fn inline(content: Vec<u8>, content_type: ContentType, file_name: String) -> Self {
+        Self::with_disposition(content, content_type, "inline", file_name)
+    }
+
+    fn with_disposition(
+        content: Vec<u8>,
+        content_type: ContentType,
+        disposition: &str,
+        file_name: String,
+    ) -> Self {
+        Self {
+            content,
+            content_type,
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("{disposition}; filename=\"{file_name}\""),
+            ),
+        }
+    }
+}
+
+// Parse a date received from a form, an empty value means no date
+pub(crate) fn parse_date(date: Option<&str>) -> Result<Option<NaiveDate>, ParseError> {
+    date.filter(|date| !date.is_empty())
+        .map(|date| NaiveDate::parse_from_str(date, DATE_FORMAT))
+        .transpose()
+}
 
-use crate::login::ADMINISTRATION_COOKIE;
-use crate::AppState;
+// First and last days of a month
+pub(crate) fn month_bounds(year: i32, month: u32) -> Option<(NaiveDate, NaiveDate)> {
+    let first = insert_db_date((year, month, 1))?;
+    let next = if month == 12 {
+        insert_db_date((year + 1, 1, 1))?
+    } else {
+        insert_db_date((year, month + 1, 1))?
+    };
+    Some((first, next.pred_opt()?))
+}
+
+// Name of an exported file, which describes the chosen date range
+pub(crate) fn file_name(
+    name: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    extension: &str,
+) -> String {
+    let range = match (from, to) {
+        (Some(from), Some(to)) if month_bounds(from.year(), from.month()) == Some((from, to)) => {
+            format!("_{}", from.format("%Y-%m"))
+        }
+        (Some(from), Some(to)) => {
+            format!("_{}_{}", from.format(DATE_FORMAT), to.format(DATE_FORMAT))
+        }
+        (Some(from), None) => format!("_dal_{}", from.format(DATE_FORMAT)),
+        (None, Some(to)) => format!("_al_{}", to.format(DATE_FORMAT)),
+        (None, None) => String::new(),
+    };
+    format!("{name}{range}.{extension}")
+}
+
+// Column names of exported files, in the language of the request
+fn headers(locale: Locale) -> [String; 8] {
+    HEADERS.map(|header| locale.text(header))
+}
+
+// Write shifts as a CSV file
+fn csv_file(locale: Locale, rows: &[ExportRow]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(headers(locale))?;
+    for row in rows {
+        writer.write_record([
+            &row.date.format(DATE_FORMAT).to_string(),
+            &row.task,
+            &row.hours,
+            &row.card_id.to_string(),
+            &row.surname,
+            &row.name,
+            &row.location,
+            &row.address,
+        ])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+// Write shifts as an Excel file
+fn xlsx_file(locale: Locale, rows: &[ExportRow]) -> Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(locale.text(SHEET_MESSAGE))?;
+
+    let header_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("dd/mm/yyyy");
+
+    for (column, header) in headers(locale).iter().enumerate() {
+        worksheet.write_string_with_format(0, column as u16, header, &header_format)?;
+    }
+
+    for (index, row) in rows.iter().enumerate() {
+        let line = index as u32 + 1;
+        let date = ExcelDateTime::from_ymd(
+            row.date.year() as u16,
+            row.date.month() as u8,
+            row.date.day() as u8,
+        )?;
+        worksheet.write_datetime_with_format(line, 0, &date, &date_format)?;
+        worksheet.write_string(line, 1, &row.task)?;
+        worksheet.write_string(line, 2, &row.hours)?;
+        worksheet.write_number(line, 3, row.card_id)?;
+        worksheet.write_string(line, 4, &row.surname)?;
+        worksheet.write_string(line, 5, &row.name)?;
+        worksheet.write_string(line, 6, &row.location)?;
+        worksheet.write_string(line, 7, &row.address)?;
+    }
+    worksheet.autofit();
 
-#[get("/")]
+    Ok(workbook.save_to_buffer()?)
+}
+
+// Write shifts as a JSON file
+fn json_file(rows: &[ExportRow]) -> Result<Vec<u8>> {
+    Ok(rocket::serde::json::to_string(&rows)?.into_bytes())
+}
+
+#[get("/?<filter..>")]
 async fn download_database(
+    filter: Option<ExportFilter<'_>>,
+    session: Option<Session>,
     state: &State<AppState>,
-    jar: &CookieJar<'_>,
+    organisation: &Organisation,
     uri: &Origin<'_>,
-) -> Result<Json<Vec<Shift>>, InternalError> {
+    locale: Locale,
+) -> Result<ExportFile, InternalError> {
     // Check if the administrator is authenticated
-    if jar.get_private(ADMINISTRATION_COOKIE).is_none() {
-        return Err(InternalError::not_authenticated_administrator(uri));
+    check_administration_access(&session, uri)?;
+
+    // Retrieve the date range, without bounds all shifts are exported
+    let filter = filter.unwrap_or_default();
+    let from =
+        parse_date(filter.from).map_err(|_| InternalError::text(uri, INVALID_DATE_MESSAGE))?;
+    let to = parse_date(filter.to).map_err(|_| InternalError::text(uri, INVALID_DATE_MESSAGE))?;
+    if from.zip(to).is_some_and(|(from, to)| from > to) {
+        return Err(InternalError::text(uri, "error-date-order"));
     }
 
-    // Retrieve all shifts
-    let all_shifts = query_error(query_all_shifts(&state.pool), uri).await?;
+    // Retrieve shifts contained in the date range
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let rows = ExportRow::rows(
+        &tasks,
+        &locations,
+        query_error(
+            query_exported_shifts(&state.pool, organisation.id, from, to, None),
+            uri,
+        )
+        .await?,
+    );
+
+    let (content, content_type, extension) = match filter.format.unwrap_or_default() {
+        ExportFormat::Csv => (csv_file(locale, &rows), ContentType::CSV, "csv"),
+        ExportFormat::Xlsx => (
+            xlsx_file(locale, &rows),
+            ContentType::new(
+                "application",
+                "vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            ),
+            "xlsx",
+        ),
+        ExportFormat::Json => (json_file(&rows), ContentType::JSON, "json"),
+    };
+    let content = content.map_err(|e| InternalError::unexpected(uri, e))?;
+
+    Ok(ExportFile::new(
+        content,
+        content_type,
+        file_name("turni", from, to, extension),
+    ))
+}
+
+#[get("/esporta")]
+pub(crate) async fn show_export(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Propose the current month as date range
+    let date = Date::current(state.timezone);
+    let (from, to) = month_bounds(date.year(), date.month())
+        .ok_or_else(|| InternalError::text(uri, "error-dates"))?;
 
-    Ok(Json(all_shifts))
+    Ok(Template::render(
+        "export",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            export_route: DOWNLOAD_DATABASE_ROUTE,
+            from_message: locale.text(FROM_MESSAGE),
+            from_value: from.format(DATE_FORMAT).to_string(),
+            to_message: locale.text(TO_MESSAGE),
+            to_value: to.format(DATE_FORMAT).to_string(),
+            format_message: locale.text(FORMAT_MESSAGE),
+            formats: [("csv", "CSV"), ("xlsx", "Excel (XLSX)"), ("json", "JSON")],
+            export_message: locale.text(EXPORT_MESSAGE),
+        },
+    ))
 }
 
 pub(crate) fn routes() -> Vec<rocket::Route> {
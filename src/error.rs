@@ -1,100 +1,154 @@
+use std::fmt::Display;
+
 use rocket::http::uri::Origin;
-use rocket::http::Status;
-use rocket::Request;
+use rocket::http::{ContentType, Status};
+use rocket::response::{self, Responder};
+use rocket::{Request, Response};
 
 use rocket_dyn_templates::{context, Template};
 
+use crate::locale::Locale;
 use crate::{ADMINISTRATION_ROUTE, APP_TITLE};
 
-const MESSAGE_404: &str = "L'indirizzo non esiste";
-const MESSAGE_422: &str = "Il valore presente nell'indirizzo non è corretto";
-const UNDEFINED_ERROR_MESSAGE: &str = "Errore non identificato";
-const BUTTON_MESSAGE: &str = "Accedi di nuovo";
+const MESSAGE_404: &str = "error-not-found";
+const MESSAGE_422: &str = "error-unprocessable";
+const UNDEFINED_ERROR_MESSAGE: &str = "error-undefined";
+const INTERNAL_ERROR_MESSAGE: &str = "error-internal";
+const BUTTON_MESSAGE: &str = "error-login-again";
 
 struct RenderTemplate;
 
 impl RenderTemplate {
-    fn text(uri: &Origin<'_>, status: u16, error_message: &str) -> Template {
-        Self::render(uri, "/", status, error_message)
-    }
-
-    fn administration(uri: &Origin<'_>, status: u16, error_message: &str) -> Template {
-        Self::render(
-            uri,
-            ADMINISTRATION_ROUTE.path().as_str(),
-            status,
-            error_message,
-        )
+    fn text(locale: Locale, uri: &str, status: u16, error_message: &str) -> Template {
+        Self::render(locale, uri, "/", status, error_message)
     }
 
-    fn render(uri: &Origin<'_>, route: &str, status: u16, error_message: &str) -> Template {
+    fn render(
+        locale: Locale,
+        uri: &str,
+        route: &str,
+        status: u16,
+        error_message: &str,
+    ) -> Template {
         Template::render(
             "error",
             context! {
+                lang: locale.code(),
                 title: APP_TITLE,
                 route,
                 uri,
                 status,
-                error_message,
-                button_message: BUTTON_MESSAGE,
+                error_message: locale.text(error_message),
+                button_message: locale.text(BUTTON_MESSAGE),
             },
         )
     }
 }
 
-#[derive(Responder)]
-#[response(status = 500, content_type = "html")]
-pub(crate) struct InternalError(Template);
+// Error page, translated into the language of the request when it is sent
+pub(crate) struct InternalError {
+    // Requested address
+    uri: String,
+    // Route of the button shown below the error
+    route: String,
+    // Error status
+    status: u16,
+    // Message identifier
+    message: String,
+}
 
 impl InternalError {
+    fn new(uri: &Origin<'_>, route: &str, status: u16, message: &str) -> Self {
+        Self {
+            uri: uri.to_string(),
+            route: route.to_string(),
+            status,
+            message: message.to_string(),
+        }
+    }
+
     // Render a text containing an internal error
     pub(crate) fn text(uri: &Origin<'_>, error_message: &str) -> Self {
-        Self(RenderTemplate::text(uri, 500, error_message))
+        Self::new(uri, "/", 500, error_message)
+    }
+
+    // Render a generic text for an unexpected error, whose details are only
+    // logged since they could reveal how the application works
+    pub(crate) fn unexpected(uri: &Origin<'_>, error: impl Display) -> Self {
+        error!("Error in {uri}: {error}");
+        Self::text(uri, INTERNAL_ERROR_MESSAGE)
     }
 
     // Render a text containing an attempt to access without authentication
     pub(crate) fn not_authenticated(uri: &Origin<'_>) -> Self {
-        Self(RenderTemplate::text(
-            uri,
-            403,
-            "Bisogna autenticarsi per vedere questa pagina",
-        ))
+        Self::new(uri, "/", 403, "error-not-authenticated")
     }
 
     // Render a text containing an attempt to access without authentication
     pub(crate) fn not_authenticated_administrator(uri: &Origin<'_>) -> Self {
-        Self(RenderTemplate::administration(
+        Self::new(
             uri,
+            ADMINISTRATION_ROUTE.path().as_str(),
             403,
-            "Bisogna autenticarsi come amministratori per vedere questa pagina",
-        ))
+            "error-not-authenticated-administrator",
+        )
     }
 
     // Arise an error when the inserted card is not associated to
     // the current user
     pub(crate) fn wrong_card_id(uri: &Origin<'_>) -> Self {
-        Self(RenderTemplate::text(
-            uri,
-            403,
-            "Il numero di tessera inserito non è il tuo",
-        ))
+        Self::new(uri, "/", 403, "error-wrong-card-id")
+    }
+
+    // Arise an error when a request changing data does not contain
+    // a valid CSRF token
+    pub(crate) fn invalid_csrf_token(uri: &Origin<'_>) -> Self {
+        Self::new(uri, "/", 403, "error-invalid-csrf-token")
+    }
+
+    // Arise an error when an invitation link does not exist, is expired or
+    // has already been used
+    pub(crate) fn invalid_invitation(uri: &Origin<'_>) -> Self {
+        Self::new(uri, "/", 403, "error-invalid-invitation")
+    }
+
+    // Arise an error when no volunteer has the requested card
+    pub(crate) fn unknown_volunteer(uri: &Origin<'_>) -> Self {
+        Self::new(uri, "/", 404, "error-unknown-volunteer")
+    }
+}
+
+impl<'r> Responder<'r, 'static> for InternalError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let template = RenderTemplate::render(
+            Locale::negotiate(req),
+            &self.uri,
+            &self.route,
+            self.status,
+            &self.message,
+        );
+        Response::build_from(template.respond_to(req)?)
+            .status(Status::from_code(self.status).unwrap_or(Status::InternalServerError))
+            .header(ContentType::HTML)
+            .ok()
     }
 }
 
-pub(crate) async fn query_error<T, K: ToString>(
+pub(crate) async fn query_error<T, K: Display>(
     function: impl std::future::Future<Output = Result<T, K>>,
     uri: &Origin<'_>,
 ) -> Result<T, InternalError> {
     function
         .await
-        .map_err(|e| InternalError::text(uri, &e.to_string()))
+        .map_err(|e| InternalError::unexpected(uri, e))
 }
 
 // Renders the template for any other kind of catchers
 #[catch(default)]
 pub(crate) fn default(status: Status, req: &Request<'_>) -> Template {
     RenderTemplate::text(
-        req.uri(),
+        Locale::negotiate(req),
+        &req.uri().to_string(),
         status.code,
         status.reason().unwrap_or(UNDEFINED_ERROR_MESSAGE),
     )
@@ -103,13 +157,23 @@ pub(crate) fn default(status: Status, req: &Request<'_>) -> Template {
 // Renders the template for an non-processable entity
 #[catch(422)]
 pub(crate) async fn unprocessable_entity(req: &Request<'_>) -> Template {
-    RenderTemplate::text(req.uri(), 422, MESSAGE_422)
+    RenderTemplate::text(
+        Locale::negotiate(req),
+        &req.uri().to_string(),
+        422,
+        MESSAGE_422,
+    )
 }
 
 // Renders the template for a not found route
 #[catch(404)]
 pub(crate) async fn not_found(req: &Request<'_>) -> Template {
-    RenderTemplate::text(req.uri(), 404, MESSAGE_404)
+    RenderTemplate::text(
+        Locale::negotiate(req),
+        &req.uri().to_string(),
+        404,
+        MESSAGE_404,
+    )
 }
 
 // Returns all defined catchers
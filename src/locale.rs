@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{accepted_languages, negotiate_languages, NegotiationStrategy};
+
+use rocket::http::uri::{Absolute, Origin};
+use rocket::http::{Cookie, CookieJar};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Redirect;
+use rocket::Request;
+use rocket_dyn_templates::handlebars::{
+    Context, Handlebars, Helper, HelperDef, JsonValue, RenderContext, RenderError, ScopedJson,
+};
+
+use serde::Serialize;
+
+use unic_langid::LanguageIdentifier;
+
+use crate::LANGUAGE_ROUTE;
+
+// Cookie containing the language chosen by the user
+const LANGUAGE_COOKIE: &str = "lang";
+
+// Template variable containing the language code, read by the `t` helper
+const LANGUAGE_VARIABLE: &str = "lang";
+
+// Message catalogues, one for each language
+const CATALOGUES: [(Locale, &str); 2] = [
+    (Locale::It, include_str!("../locales/it.ftl")),
+    (Locale::En, include_str!("../locales/en.ftl")),
+];
+
+// Languages the app is translated into
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) enum Locale {
+    // Italian, used when no other language can be chosen
+    #[default]
+    It,
+    // English
+    En,
+}
+
+impl Locale {
+    // All languages, in the order they are proposed to users
+    pub(crate) const ALL: [Self; 2] = [Self::It, Self::En];
+
+    // Language code, as used inside the Accept-Language header
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Self::It => "it",
+            Self::En => "en",
+        }
+    }
+
+    // Language name, written in the language itself
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::It => "Italiano",
+            Self::En => "English",
+        }
+    }
+
+    pub(crate) fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.code().eq_ignore_ascii_case(code))
+    }
+
+    fn identifier(self) -> LanguageIdentifier {
+        self.code().parse().unwrap_or_default()
+    }
+
+    // Choose the language of a request: the one chosen by the user, then
+    // the ones accepted by the browser, then Italian
+    pub(crate) fn negotiate(req: &Request<'_>) -> Self {
+        if let Some(locale) = req
+            .cookies()
+            .get(LANGUAGE_COOKIE)
+            .and_then(|cookie| Self::from_code(cookie.value()))
+        {
+            return locale;
+        }
+
+        let requested =
+            accepted_languages::parse(req.headers().get_one("Accept-Language").unwrap_or_default());
+        let available: Vec<LanguageIdentifier> =
+            Self::ALL.into_iter().map(Self::identifier).collect();
+        let default = Self::default().identifier();
+        negotiate_languages(
+            &requested,
+            &available,
+            Some(&default),
+            NegotiationStrategy::Lookup,
+        )
+        .first()
+        .and_then(|identifier| Self::from_code(identifier.language.as_str()))
+        .unwrap_or_default()
+    }
+
+    // Format a decimal number with one fractional digit
+    pub(crate) fn format_decimal(self, value: f64) -> String {
+        let value = format!("{value:.1}");
+        match self {
+            Self::It => value.replace('.', ","),
+            Self::En => value,
+        }
+    }
+
+    // Translate a message. Text which is not a message identifier, such as
+    // database errors, is returned unchanged.
+    pub(crate) fn text(self, id: &str) -> String {
+        self.format(id, &[])
+    }
+
+    // Translate a message containing variables
+    pub(crate) fn format(self, id: &str, args: &[(&str, FluentValue<'_>)]) -> String {
+        let args = FluentArgs::from_iter(args.iter().cloned());
+        [self, Self::default()]
+            .into_iter()
+            .find_map(|locale| {
+                let bundle = catalogues().get(&locale)?;
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                Some(
+                    bundle
+                        .format_pattern(pattern, Some(&args), &mut errors)
+                        .into_owned(),
+                )
+            })
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Locale {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self::negotiate(req))
+    }
+}
+
+// Parse the catalogues once, the first time a message is translated
+fn catalogues() -> &'static HashMap<Locale, FluentBundle<FluentResource>> {
+    static BUNDLES: OnceLock<HashMap<Locale, FluentBundle<FluentResource>>> = OnceLock::new();
+    BUNDLES.get_or_init(|| {
+        CATALOGUES
+            .into_iter()
+            .map(|(locale, catalogue)| {
+                // Invalid messages are skipped, the valid ones are kept
+                let resource = FluentResource::try_new(catalogue.to_string())
+                    .unwrap_or_else(|(resource, _)| resource);
+                let mut bundle = FluentBundle::new_concurrent(vec![locale.identifier()]);
+                // Unicode isolation marks would be shown inside HTML pages
+                bundle.set_use_isolating(false);
+                let _res = bundle.add_resource(resource);
+                (locale, bundle)
+            })
+            .collect()
+    })
+}
+
+// Link to choose a language
+#[derive(Serialize)]
+pub(crate) struct LanguageLink {
+    // Language name
+    name: &'static str,
+    // Route to choose the language
+    route: Origin<'static>,
+    // Whether it is the current language
+    is_active: bool,
+}
+
+impl LanguageLink {
+    pub(crate) fn render(current: Locale) -> Vec<Self> {
+        Locale::ALL
+            .into_iter()
+            .map(|locale| Self {
+                name: locale.name(),
+                route: uri!(LANGUAGE_ROUTE, choose_language(locale.code())),
+                is_active: locale == current,
+            })
+            .collect()
+    }
+}
+
+// Translate a message inside templates: {{t "message-id" variable=value}}.
+// The language is read from the `lang` variable of the template.
+pub(crate) struct TranslateHelper;
+
+impl HelperDef for TranslateHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let id = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or_else(|| RenderError::new("The t helper requires a message identifier"))?;
+        let locale = ctx
+            .data()
+            .get(LANGUAGE_VARIABLE)
+            .and_then(JsonValue::as_str)
+            .and_then(Locale::from_code)
+            .unwrap_or_default();
+        let args: Vec<(&str, FluentValue<'_>)> = h
+            .hash()
+            .iter()
+            .map(|(name, value)| {
+                let value = match value.value() {
+                    JsonValue::Number(number) => {
+                        FluentValue::from(number.as_f64().unwrap_or_default())
+                    }
+                    JsonValue::String(text) => FluentValue::from(text.as_str()),
+                    value => FluentValue::from(value.to_string()),
+                };
+                (*name, value)
+            })
+            .collect();
+        Ok(ScopedJson::Derived(JsonValue::String(
+            locale.format(id, &args),
+        )))
+    }
+}
+
+// Remember the language chosen by the user
+pub(crate) fn save_language(jar: &CookieJar<'_>, locale: Locale) {
+    jar.add(Cookie::build((LANGUAGE_COOKIE, locale.code())).permanent());
+}
+
+#[get("/<code>")]
+fn choose_language(code: &str, jar: &CookieJar<'_>, req_referer: Referer) -> Redirect {
+    if let Some(locale) = Locale::from_code(code) {
+        save_language(jar, locale);
+    }
+
+    // Go back to the page the language has been chosen from
+    Redirect::to(req_referer.0.unwrap_or_else(|| "/".to_string()))
+}
+
+// Path of the page which sent a request, other websites are ignored
+struct Referer(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Referer {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let host = req.host().map(|host| host.to_string());
+        Outcome::Success(Self(
+            req.headers()
+                .get_one("Referer")
+                .and_then(|referer| Absolute::parse(referer).ok())
+                .filter(|referer| {
+                    referer.authority().map(|authority| authority.to_string()) == host
+                })
+                .map(|referer| match referer.query() {
+                    Some(query) => format!("{}?{query}", referer.path()),
+                    None => referer.path().to_string(),
+                }),
+        ))
+    }
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![choose_language]
+}
@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use rocket::form::Form;
 use rocket::http::uri::Origin;
 use rocket::http::CookieJar;
@@ -10,11 +12,20 @@ use serde::Serialize;
 
 use crate::cookie::CookieMessage;
 use crate::data::{Button, InputTypeNumberErrors};
-use crate::database::{query_check_card_id, query_is_disabled};
+use crate::database::{query_check_card_id, query_is_disabled, Organisation};
 use crate::error::{query_error, InternalError};
-use crate::{AppState, ADMINISTRATION_ROUTE, APP_TITLE, VOLUNTEERS_ROUTE};
-
-use super::{get_cookie_value, get_cookie_value_str, ADMINISTRATION_COOKIE, AUTHENTICATION_COOKIE};
+use crate::locale::{LanguageLink, Locale};
+use crate::token::password_matches;
+// Query parameters of the volunteers route, needed by its uri
+use crate::volunteers::VolunteersFilter;
+use crate::{AppState, ADMINISTRATION_ROUTE, VOLUNTEERS_ROUTE};
+
+use super::attempts::{AttemptKind, LoginAttempts};
+use super::session::{start_session, UserAgent};
+use super::{
+    credentials_message, get_cookie_value, get_cookie_value_str, ADMINISTRATION_CARD_COOKIE,
+    LOCKED_FLASH, WRONG_CREDENTIALS_FLASH,
+};
 
 // Macro which redirects to volunteers route
 macro_rules! volunteers_uri {
@@ -26,37 +37,38 @@ macro_rules! administration_uri {
     ($($t:tt)*) => (rocket::uri!(ADMINISTRATION_ROUTE, $($t)*))
 }
 
+pub(crate) use administration_uri;
 use administration_uri as uri;
 
 // Administration information
 #[derive(Serialize)]
 struct AdministrationInfo {
     // Generic description
-    description: &'static str,
+    description: String,
     // Card identifier text
-    card_id_text: &'static str,
+    card_id_text: String,
     // Card identifier placeholder
-    card_id_placeholder: &'static str,
+    card_id_placeholder: String,
     // Card identifier value
     card_id_value: &'static str,
     // Card identifier error messages
     card_id_error: InputTypeNumberErrors,
     // Password text
-    password_text: &'static str,
+    password_text: String,
     // Erroneous password
-    password_error_message: &'static str,
+    password_error_message: String,
 }
 
 impl AdministrationInfo {
-    fn render(jar: &CookieJar) -> Self {
+    fn render(locale: Locale, jar: &CookieJar) -> Self {
         Self {
-            description: "Autenticati come referente",
-            card_id_text: "Numero tessera (senza sigle iniziali)",
-            card_id_placeholder: "es. 001",
-            card_id_value: get_cookie_value_str(jar, ADMINISTRATION_COOKIE),
-            card_id_error: InputTypeNumberErrors::text(),
-            password_text: "Password",
-            password_error_message: "Inserire la password (almeno 8 caratteri)",
+            description: locale.text("administration-description"),
+            card_id_text: locale.text("login-card-id"),
+            card_id_placeholder: locale.text("login-card-id-placeholder"),
+            card_id_value: get_cookie_value_str(jar, ADMINISTRATION_CARD_COOKIE),
+            card_id_error: InputTypeNumberErrors::text(locale),
+            password_text: locale.text("administration-password"),
+            password_error_message: locale.text("administration-password-empty"),
         }
     }
 }
@@ -65,27 +77,33 @@ impl AdministrationInfo {
 #[derive(Default, Serialize)]
 struct ErrorMessage {
     card_id: Option<String>,
-    password: Option<&'static str>,
+    password: Option<String>,
+    credentials: Option<String>,
 }
 
 impl ErrorMessage {
-    fn card_id_text(&mut self, card_id: Option<String>) {
-        self.card_id =
-            card_id.map(|card_id| format!("Il numero di tessera \"{card_id}\" non esiste"));
+    fn card_id_text(&mut self, locale: Locale, card_id: Option<String>) {
+        self.card_id = card_id
+            .map(|card_id| locale.format("login-card-id-missing", &[("card_id", card_id.into())]));
     }
 
-    fn card_id_disabled_text(&mut self, card_id: Option<String>) {
-        self.card_id =
-            card_id.map(|card_id| format!("Il numero di tessera \"{card_id}\" è disabilitato"));
+    fn card_id_disabled_text(&mut self, locale: Locale, card_id: Option<String>) {
+        self.card_id = card_id
+            .map(|card_id| locale.format("login-card-id-disabled", &[("card_id", card_id.into())]));
     }
 
-    fn wrong_password_text(&mut self) {
-        self.password = Some("La password inserita non è corretta");
+    fn wrong_password_text(&mut self, locale: Locale) {
+        self.password = Some(locale.text("administration-wrong-password"));
     }
 }
 
 #[get("/")]
-async fn show_administration(flash: Option<FlashMessage<'_>>, jar: &CookieJar<'_>) -> Template {
+async fn show_administration(
+    flash: Option<FlashMessage<'_>>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    locale: Locale,
+) -> Template {
     // Get flash message
     let flash = flash.as_ref().map(FlashMessage::message);
 
@@ -95,32 +113,32 @@ async fn show_administration(flash: Option<FlashMessage<'_>>, jar: &CookieJar<'_
     // Fill error message
     if let Some(error) = flash {
         match error {
-            "card_id-non-existent" => {
-                error_messages.card_id_text(get_cookie_value(jar, ADMINISTRATION_COOKIE))
-            }
-            "card_id-disabled" => {
-                error_messages.card_id_disabled_text(get_cookie_value(jar, ADMINISTRATION_COOKIE))
-            }
-            "wrong-password" => error_messages.wrong_password_text(),
-            _ => (),
+            "card_id-non-existent" => error_messages
+                .card_id_text(locale, get_cookie_value(jar, ADMINISTRATION_CARD_COOKIE)),
+            "card_id-disabled" => error_messages
+                .card_id_disabled_text(locale, get_cookie_value(jar, ADMINISTRATION_CARD_COOKIE)),
+            "wrong-password" => error_messages.wrong_password_text(locale),
+            _ => error_messages.credentials = credentials_message(locale, error),
         }
     }
 
     // Administration text
-    let administration = AdministrationInfo::render(jar);
+    let administration = AdministrationInfo::render(locale, jar);
 
     // Administration button
-    let button = Button::authentication_button();
+    let button = Button::authentication_button(locale);
 
     Template::render(
         "administration",
         context! {
-            title: APP_TITLE,
+            lang: locale.code(),
+            title: &organisation.name,
             route: ADMINISTRATION_ROUTE,
             administration,
             error_messages,
             button,
-            cookie: CookieMessage::render(jar, Some(ADMINISTRATION_ROUTE.path().as_str())),
+            languages: LanguageLink::render(locale),
+            cookie: CookieMessage::render(locale, jar, Some(ADMINISTRATION_ROUTE.path().as_str())),
         },
     )
 }
@@ -131,62 +149,104 @@ struct Administration<'r> {
     password: &'r str,
 }
 
+// Check referent credentials, returning the flash message associated to
+// the first error found
+async fn credentials_error(
+    state: &AppState,
+    organisation: &Organisation,
+    administration: &Administration<'_>,
+) -> anyhow::Result<Option<&'static str>> {
+    // Check whether the card identifier is present in the volunteers table
+    if !query_check_card_id(&state.pool, organisation.id, administration.card_id).await? {
+        return Ok(Some("card_id-non-existent"));
+    }
+
+    // Check whether the volunteer is disabled
+    if query_is_disabled(&state.pool, organisation.id, administration.card_id).await? {
+        return Ok(Some("card_id-disabled"));
+    }
+
+    // Check the password against the organisation hash
+    if !password_matches(administration.password, &organisation.password_hash) {
+        return Ok(Some("wrong-password"));
+    }
+
+    Ok(None)
+}
+
 #[post("/", data = "<administration_form>")]
 async fn check_administration<'r>(
     administration_form: Form<Administration<'r>>,
     state: &State<AppState>,
+    organisation: &Organisation,
     jar: &CookieJar<'_>,
     uri: &Origin<'_>,
+    ip: Option<IpAddr>,
+    user_agent: UserAgent,
 ) -> Result<Flash<Redirect>, InternalError> {
     // Retrieve form data
     let administration = administration_form.into_inner();
 
-    // Check whether the card identifier is present in the volunteers table
-    let is_card_id = query_error(
-        query_check_card_id(&state.pool, administration.card_id),
-        uri,
-    )
-    .await?;
-
     // Save card identification cookie
-    jar.add_private((ADMINISTRATION_COOKIE, administration.card_id.to_string()));
-
-    // If there is no card identifier, redirects to administration page
-    if !is_card_id {
+    jar.add_private((
+        ADMINISTRATION_CARD_COOKIE,
+        administration.card_id.to_string(),
+    ));
+
+    // Login attempts of the client
+    let attempts = LoginAttempts::new(
+        &state.pool,
+        organisation.id,
+        AttemptKind::Administration,
+        administration.card_id,
+        ip,
+    );
+
+    // If there are too many failed attempts, redirects to administration page
+    if let Some(minutes) = query_error(attempts.locked_minutes(), uri).await? {
         return Ok(Flash::error(
             Redirect::to(uri!(show_administration)),
-            "card_id-non-existent",
+            format!("{LOCKED_FLASH}{minutes}"),
         ));
     }
 
-    // Check whether the volunteer is disabled
-    let volunteer_is_disabled =
-        query_error(query_is_disabled(&state.pool, administration.card_id), uri).await?;
-
-    // If volunteer is disabled, redirects to administration page
-    if volunteer_is_disabled {
+    // If credentials are wrong, redirects to administration page
+    if let Some(error) =
+        query_error(credentials_error(state, organisation, &administration), uri).await?
+    {
+        query_error(attempts.failed(), uri).await?;
         return Ok(Flash::error(
             Redirect::to(uri!(show_administration)),
-            "card_id-disabled",
+            if state.uniform_login_errors {
+                WRONG_CREDENTIALS_FLASH
+            } else {
+                error
+            },
         ));
     }
 
-    // Compare passwords
-    if administration.password == state.administration_password {
-        // Remove authentication cookie
-        jar.remove_private(AUTHENTICATION_COOKIE);
-        // If everything is correct, redirect to administration page
-        Ok(Flash::success(
-            Redirect::to(volunteers_uri!(crate::volunteers::show_volunteers)),
-            "Successful authentication.",
-        ))
-    } else {
-        // If there is an error, redirect to administration authentication
-        Ok(Flash::error(
-            Redirect::to(uri!(show_administration)),
-            "wrong-password",
-        ))
-    }
+    // Reset failed attempts
+    query_error(attempts.succeeded(), uri).await?;
+
+    // Start a referent session
+    query_error(
+        start_session(
+            state,
+            organisation.id,
+            jar,
+            administration.card_id,
+            true,
+            &user_agent,
+        ),
+        uri,
+    )
+    .await?;
+
+    // If everything is correct, redirect to administration page
+    Ok(Flash::success(
+        Redirect::to(volunteers_uri!(crate::volunteers::show_volunteers(_))),
+        "Successful authentication.",
+    ))
 }
 
 pub(crate) fn routes() -> Vec<rocket::Route> {
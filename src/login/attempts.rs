@@ -0,0 +1,325 @@
+use std::net::IpAddr;
+
+use anyhow::Result;
+
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+
+use rocket::http::uri::Origin;
+use rocket::response::Redirect;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use sqlx::PgPool;
+
+use crate::database::{
+    delete_login_attempts, lock_login, query_locked_logins, query_login_lock, record_failed_login,
+    LoginAttempt, Organisation,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::menu::MenuAdministration;
+use crate::time::format_date_time;
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+use super::administration::administration_uri;
+use super::session::{check_administration_access, Session};
+
+// Number of failed attempts allowed before locking a login
+const FREE_ATTEMPTS: i32 = 5;
+// Number of failed attempts allowed before locking an IP address, higher
+// because many volunteers can share the same address, as the kitchen tablets
+const FREE_IP_ATTEMPTS: i32 = 20;
+// Lockout duration, in seconds, after the first exceeding attempt
+const BASE_LOCKOUT: i64 = 30;
+// Maximum lockout duration, in seconds
+const MAX_LOCKOUT: i64 = 3600;
+
+// Messages
+const HEADING_MESSAGE: &str = "locked-logins-heading";
+const EMPTY_LOCKS_MESSAGE: &str = "locked-logins-empty";
+const UNLOCK_MESSAGE: &str = "locked-logins-unlock";
+
+// Kind of identifiers whose login attempts are counted
+#[derive(Clone, Copy)]
+pub(crate) enum AttemptKind {
+    // IP address of a client
+    Ip,
+    // Card identifier used to access the volunteer space
+    Volunteer,
+    // Card identifier used to access the referents space
+    Administration,
+}
+
+impl AttemptKind {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ip => "ip",
+            Self::Volunteer => "volunteer",
+            Self::Administration => "administration",
+        }
+    }
+
+    // Number of failed attempts allowed before locking the login
+    const fn free_attempts(self) -> i32 {
+        match self {
+            Self::Ip => FREE_IP_ATTEMPTS,
+            Self::Volunteer | Self::Administration => FREE_ATTEMPTS,
+        }
+    }
+
+    fn label(locale: Locale, kind: &str) -> String {
+        locale.text(match kind {
+            "ip" => "locked-logins-ip",
+            "volunteer" => "locked-logins-volunteer",
+            "administration" => "locked-logins-administration",
+            _ => "locked-logins-unknown",
+        })
+    }
+}
+
+// Lockout duration, in seconds, after a number of failed attempts, none while
+// the free attempts are not exceeded. The duration doubles at each new failure.
+fn lockout_seconds(attempts: i32, free_attempts: i32) -> Option<i64> {
+    (attempts > free_attempts).then(|| {
+        let exponent = (attempts - free_attempts - 1).min(16) as u32;
+        (BASE_LOCKOUT * 2i64.pow(exponent)).min(MAX_LOCKOUT)
+    })
+}
+
+// Login attempts performed by a client with a card identifier
+pub(crate) struct LoginAttempts<'a> {
+    pool: &'a PgPool,
+    organisation_id: i32,
+    identifiers: Vec<(AttemptKind, String)>,
+}
+
+impl<'a> LoginAttempts<'a> {
+    pub(crate) fn new(
+        pool: &'a PgPool,
+        organisation_id: i32,
+        kind: AttemptKind,
+        card_id: i16,
+        ip: Option<IpAddr>,
+    ) -> Self {
+        let mut identifiers = vec![(kind, card_id.to_string())];
+        if let Some(ip) = ip {
+            identifiers.push((AttemptKind::Ip, ip.to_string()));
+        }
+        Self {
+            pool,
+            organisation_id,
+            identifiers,
+        }
+    }
+
+    // Get the minutes left before the login is unlocked, if it is locked
+    pub(crate) async fn locked_minutes(&self) -> Result<Option<i64>> {
+        let mut locked_until = None;
+        for (kind, identifier) in &self.identifiers {
+            locked_until = locked_until.max(
+                query_login_lock(self.pool, self.organisation_id, kind.as_str(), identifier)
+                    .await?,
+            );
+        }
+        Ok(locked_until.map(|date_time| ((date_time - Utc::now()).num_seconds() + 59) / 60))
+    }
+
+    // Record a failed attempt, locking the login when there are too many
+    // failures
+    pub(crate) async fn failed(&self) -> Result<()> {
+        self.record(&self.identifiers).await
+    }
+
+    // Record a login code sent to the client, which counts as a failed
+    // attempt for the card until a code is verified. It does not count for
+    // the IP address, whose attempts are never reset by a login.
+    pub(crate) async fn code_sent(&self) -> Result<()> {
+        self.record(self.cards()).await
+    }
+
+    // Reset the attempts of the card after a successful login. The attempts
+    // of the IP address are kept, otherwise a single known password would
+    // allow guessing the others without limits.
+    pub(crate) async fn succeeded(&self) -> Result<()> {
+        for (kind, identifier) in self.cards() {
+            delete_login_attempts(self.pool, self.organisation_id, kind.as_str(), identifier)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Identifiers which are card identifiers
+    fn cards(&self) -> &[(AttemptKind, String)] {
+        // The card identifier always comes first
+        &self.identifiers[..1]
+    }
+
+    // Record a failed attempt for the identifiers, locking the ones with too
+    // many failures
+    async fn record(&self, identifiers: &[(AttemptKind, String)]) -> Result<()> {
+        for (kind, identifier) in identifiers {
+            let attempts =
+                record_failed_login(self.pool, self.organisation_id, kind.as_str(), identifier)
+                    .await?;
+            if let Some(seconds) = lockout_seconds(attempts, kind.free_attempts()) {
+                let locked_until = Utc::now() + Duration::seconds(seconds);
+                lock_login(
+                    self.pool,
+                    self.organisation_id,
+                    kind.as_str(),
+                    identifier,
+                    locked_until,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Locked login and the relative route to unlock it
+#[derive(Serialize)]
+struct LockedLogin {
+    // Kind of identifier
+    kind: String,
+    // Identifier
+    identifier: String,
+    // Number of failed attempts
+    attempts: i32,
+    // Lockout end
+    locked_until: String,
+    // Route to unlock the login
+    unlock_route: Origin<'static>,
+}
+
+impl LockedLogin {
+    fn logins(locale: Locale, timezone: Tz, attempts: Vec<LoginAttempt>) -> Vec<Self> {
+        attempts
+            .into_iter()
+            .map(|attempt| Self {
+                kind: AttemptKind::label(locale, &attempt.kind),
+                locked_until: attempt
+                    .locked_until
+                    .map(|date_time| format_date_time(timezone, date_time))
+                    .unwrap_or_default(),
+                unlock_route: administration_uri!(unlock_login(&attempt.kind, &attempt.identifier)),
+                identifier: attempt.identifier,
+                attempts: attempt.attempts,
+            })
+            .collect()
+    }
+}
+
+#[get("/bloccati")]
+pub(crate) async fn show_locked_logins(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Get all locked logins
+    let locked_logins = LockedLogin::logins(
+        locale,
+        state.timezone,
+        query_error(query_locked_logins(&state.pool, organisation.id), uri).await?,
+    );
+
+    Ok(Template::render(
+        "locked_logins",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            no_locks_message: locked_logins.is_empty().then(|| locale.text(EMPTY_LOCKS_MESSAGE)),
+            locked_logins,
+            unlock_message: locale.text(UNLOCK_MESSAGE),
+        },
+    ))
+}
+
+#[delete("/bloccati/<kind>/<identifier>")]
+async fn unlock_login(
+    kind: &str,
+    identifier: &str,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Delete the attempts associated to the login
+    query_error(
+        delete_login_attempts(&state.pool, organisation.id, kind, identifier),
+        uri,
+    )
+    .await?;
+
+    // Redirect to locked logins page
+    Ok(Redirect::to(administration_uri!(show_locked_logins)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_locked_logins, unlock_login]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_attempts_are_not_locked() {
+        assert_eq!(lockout_seconds(0, FREE_ATTEMPTS), None);
+        assert_eq!(lockout_seconds(FREE_ATTEMPTS, FREE_ATTEMPTS), None);
+    }
+
+    #[test]
+    fn first_exceeding_attempt_is_locked() {
+        assert_eq!(
+            lockout_seconds(FREE_ATTEMPTS + 1, FREE_ATTEMPTS),
+            Some(BASE_LOCKOUT)
+        );
+        assert_eq!(
+            lockout_seconds(FREE_ATTEMPTS + 2, FREE_ATTEMPTS),
+            Some(2 * BASE_LOCKOUT)
+        );
+        assert_eq!(
+            lockout_seconds(FREE_ATTEMPTS + 3, FREE_ATTEMPTS),
+            Some(4 * BASE_LOCKOUT)
+        );
+    }
+
+    #[test]
+    fn lockout_is_capped() {
+        assert_eq!(
+            lockout_seconds(FREE_ATTEMPTS + 7, FREE_ATTEMPTS),
+            Some(64 * BASE_LOCKOUT)
+        );
+        assert_eq!(
+            lockout_seconds(FREE_ATTEMPTS + 8, FREE_ATTEMPTS),
+            Some(MAX_LOCKOUT)
+        );
+        assert_eq!(lockout_seconds(1000, FREE_ATTEMPTS), Some(MAX_LOCKOUT));
+        assert_eq!(lockout_seconds(i32::MAX, FREE_ATTEMPTS), Some(MAX_LOCKOUT));
+    }
+
+    #[test]
+    fn ip_addresses_have_more_free_attempts() {
+        let free_attempts = AttemptKind::Ip.free_attempts();
+        assert!(free_attempts > AttemptKind::Volunteer.free_attempts());
+        assert_eq!(lockout_seconds(FREE_ATTEMPTS + 1, free_attempts), None);
+        assert_eq!(
+            lockout_seconds(free_attempts + 1, free_attempts),
+            Some(BASE_LOCKOUT)
+        );
+    }
+}
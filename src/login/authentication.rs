@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::net::IpAddr;
 
 use rocket::form::Form;
 use rocket::http::uri::Origin;
@@ -10,15 +11,24 @@ use rocket_dyn_templates::{context, Template};
 
 use serde::Serialize;
 
+use sqlx::PgPool;
+
 use crate::cookie::CookieMessage;
 use crate::data::{Button, InputTypeNumberErrors};
 use crate::database::{
     query_card_id_to_surname, query_check_card_id, query_check_surname, query_is_disabled,
+    Organisation,
 };
 use crate::error::{query_error, InternalError};
-use crate::{AppState, APP_TITLE, SHIFTS_MANAGER_ROUTE};
-
-use super::{ADMINISTRATION_COOKIE, AUTHENTICATION_COOKIE, CARD_COOKIE, SURNAME_COOKIE};
+use crate::locale::{LanguageLink, Locale};
+use crate::{AppState, CODE_ROUTE, SHIFTS_MANAGER_ROUTE};
+
+use super::attempts::{AttemptKind, LoginAttempts};
+use super::code::{send_login_code, PENDING_CARD_COOKIE};
+use super::session::{start_session, UserAgent};
+use super::{
+    credentials_message, CARD_COOKIE, LOCKED_FLASH, SURNAME_COOKIE, WRONG_CREDENTIALS_FLASH,
+};
 
 macro_rules! shifts_manager_uri {
     ($($t:tt)*) => (rocket::uri!(SHIFTS_MANAGER_ROUTE, $crate::shifts_manager:: $($t)*))
@@ -30,37 +40,37 @@ use super::{get_cookie_value, get_cookie_value_str};
 #[derive(Serialize)]
 struct AuthenticationInfo {
     // Generic description
-    description: &'static str,
+    description: String,
     // Card identifier text
-    card_id_text: &'static str,
+    card_id_text: String,
     // Card identifier placeholder
-    card_id_placeholder: &'static str,
+    card_id_placeholder: String,
     // Card identifier value
     card_id_value: &'static str,
     // Card identifier error messages
     card_id_error: InputTypeNumberErrors,
     // Surname text
-    surname_text: &'static str,
+    surname_text: String,
     // Surname placeholder
-    surname_placeholder: &'static str,
+    surname_placeholder: String,
     // Surname value
     surname_value: &'static str,
     // Surname message when the field is empty
-    surname_empty_message: &'static str,
+    surname_empty_message: String,
 }
 
 impl AuthenticationInfo {
-    fn render(jar: &CookieJar) -> Self {
+    fn render(locale: Locale, jar: &CookieJar) -> Self {
         Self {
-            description: "Inserisci i tuoi dati",
-            card_id_text: "Numero tessera (senza sigle iniziali)",
-            card_id_placeholder: "es. 001",
+            description: locale.text("login-description"),
+            card_id_text: locale.text("login-card-id"),
+            card_id_placeholder: locale.text("login-card-id-placeholder"),
             card_id_value: get_cookie_value_str(jar, CARD_COOKIE),
-            card_id_error: InputTypeNumberErrors::text(),
-            surname_text: "Cognome",
-            surname_placeholder: "es. Rossi",
+            card_id_error: InputTypeNumberErrors::text(locale),
+            surname_text: locale.text("login-surname"),
+            surname_placeholder: locale.text("login-surname-placeholder"),
             surname_value: get_cookie_value_str(jar, SURNAME_COOKIE),
-            surname_empty_message: "Inserire un cognome.",
+            surname_empty_message: locale.text("login-surname-empty"),
         }
     }
 }
@@ -70,26 +80,36 @@ impl AuthenticationInfo {
 struct ErrorMessage {
     card_id: Option<String>,
     surname: Option<String>,
+    credentials: Option<String>,
 }
 
 impl ErrorMessage {
-    fn card_id_text(&mut self, card_id: Option<String>) {
-        self.card_id =
-            card_id.map(|card_id| format!("Il numero di tessera \"{card_id}\" non esiste"));
+    fn card_id_text(&mut self, locale: Locale, card_id: Option<String>) {
+        self.card_id = card_id
+            .map(|card_id| locale.format("login-card-id-missing", &[("card_id", card_id.into())]));
     }
 
-    fn card_id_disabled_text(&mut self, card_id: Option<String>) {
-        self.card_id =
-            card_id.map(|card_id| format!("Il numero di tessera \"{card_id}\" è disabilitato"));
+    fn card_id_disabled_text(&mut self, locale: Locale, card_id: Option<String>) {
+        self.card_id = card_id
+            .map(|card_id| locale.format("login-card-id-disabled", &[("card_id", card_id.into())]));
     }
 
-    fn surname_text(&mut self, surname: Option<String>) {
-        self.surname = surname.map(|surname| format!("Il cognome \"{surname}\" non esiste"));
+    fn surname_text(&mut self, locale: Locale, surname: Option<String>) {
+        self.surname = surname
+            .map(|surname| locale.format("login-surname-missing", &[("surname", surname.into())]));
     }
 
-    fn card_id_wrong_surname_text(&mut self, card_id: Option<String>, surname: Option<String>) {
+    fn card_id_wrong_surname_text(
+        &mut self,
+        locale: Locale,
+        card_id: Option<String>,
+        surname: Option<String>,
+    ) {
         self.surname = card_id.zip(surname).map(|(card_id, surname)| {
-            format!("Il cognome \"{surname}\" non è associato al numero di tessera \"{card_id}\"")
+            locale.format(
+                "login-wrong-surname",
+                &[("card_id", card_id.into()), ("surname", surname.into())],
+            )
         });
     }
 }
@@ -97,7 +117,9 @@ impl ErrorMessage {
 #[get("/")]
 pub(crate) async fn show_authentication(
     flash: Option<FlashMessage<'_>>,
+    organisation: &Organisation,
     jar: &CookieJar<'_>,
+    locale: Locale,
 ) -> Template {
     // Get flash message
     let flash = flash.as_ref().map(FlashMessage::message);
@@ -108,37 +130,36 @@ pub(crate) async fn show_authentication(
     // Fill error message
     if let Some(error) = flash {
         match error {
-            "card_id" => error_messages.card_id_text(get_cookie_value(jar, CARD_COOKIE)),
+            "card_id" => error_messages.card_id_text(locale, get_cookie_value(jar, CARD_COOKIE)),
             "card_id-disabled" => {
-                error_messages.card_id_disabled_text(get_cookie_value(jar, CARD_COOKIE))
-            }
-            "surname" => error_messages.surname_text(get_cookie_value(jar, SURNAME_COOKIE)),
-            "card_id-surname" => {
-                error_messages.card_id_text(get_cookie_value(jar, CARD_COOKIE));
-                error_messages.surname_text(get_cookie_value(jar, SURNAME_COOKIE))
+                error_messages.card_id_disabled_text(locale, get_cookie_value(jar, CARD_COOKIE))
             }
+            "surname" => error_messages.surname_text(locale, get_cookie_value(jar, SURNAME_COOKIE)),
             "card_id-wrong-surname" => error_messages.card_id_wrong_surname_text(
+                locale,
                 get_cookie_value(jar, CARD_COOKIE),
                 get_cookie_value(jar, SURNAME_COOKIE),
             ),
-            _ => (),
+            _ => error_messages.credentials = credentials_message(locale, error),
         }
     }
 
     // Authentication text
-    let auth_info = AuthenticationInfo::render(jar);
+    let auth_info = AuthenticationInfo::render(locale, jar);
 
     // Authentication button
-    let button = Button::authentication_button();
+    let button = Button::authentication_button(locale);
 
     Template::render(
         "authentication",
         context! {
-            title: APP_TITLE,
+            lang: locale.code(),
+            title: &organisation.name,
             auth_info,
             error_messages,
             button,
-            cookie: CookieMessage::render(jar, None),
+            languages: LanguageLink::render(locale),
+            cookie: CookieMessage::render(locale, jar, None),
         },
     )
 }
@@ -158,86 +179,94 @@ fn capitalize(s: &str) -> Cow<'_, str> {
     }
 }
 
+// Check volunteer credentials, returning the flash message associated to
+// the first error found
+async fn credentials_error(
+    pool: &PgPool,
+    organisation_id: i32,
+    card_id: i16,
+    surname: &str,
+) -> anyhow::Result<Option<&'static str>> {
+    // Check whether the card identifier is present in the volunteers table
+    if !query_check_card_id(pool, organisation_id, card_id).await? {
+        return Ok(Some(CARD_COOKIE));
+    }
+
+    // Check whether the volunteer is disabled
+    if query_is_disabled(pool, organisation_id, card_id).await? {
+        return Ok(Some("card_id-disabled"));
+    }
+
+    // Check whether the surname is present in the volunteers table
+    if !query_check_surname(pool, organisation_id, surname).await? {
+        return Ok(Some(SURNAME_COOKIE));
+    }
+
+    // Check whether the surname is associated to the card identification
+    if !query_card_id_to_surname(pool, organisation_id, card_id, surname).await? {
+        return Ok(Some("card_id-wrong-surname"));
+    }
+
+    Ok(None)
+}
+
 #[post("/", data = "<authentication_form>")]
 pub(crate) async fn check_authentication<'r>(
     authentication_form: Form<Authentication<'r>>,
     state: &State<AppState>,
+    organisation: &Organisation,
     jar: &CookieJar<'_>,
     uri: &Origin<'_>,
+    ip: Option<IpAddr>,
+    user_agent: UserAgent,
 ) -> Result<Flash<Redirect>, InternalError> {
     // Retrieve form data
     let authentication = authentication_form.into_inner();
 
-    // Check whether the card identifier is present in the volunteers table
-    let is_card_id = query_error(
-        query_check_card_id(&state.pool, authentication.card_id),
-        uri,
-    )
-    .await?;
-
-    // Save card identification cookie
+    // Save card identification and surname cookies
     jar.add_private((CARD_COOKIE, authentication.card_id.to_string()));
+    jar.add_private((SURNAME_COOKIE, authentication.surname.to_string()));
 
-    // If there is no card identifier, redirects to authentication page
-    if !is_card_id {
-        return Ok(Flash::error(
-            Redirect::to(uri!(show_authentication)),
-            CARD_COOKIE,
-        ));
-    }
-
-    // Check whether the volunteer is disabled
-    let volunteer_is_disabled =
-        query_error(query_is_disabled(&state.pool, authentication.card_id), uri).await?;
-
-    // If volunteer is disabled, redirects to authentication page
-    if volunteer_is_disabled {
+    // Login attempts of the client
+    let attempts = LoginAttempts::new(
+        &state.pool,
+        organisation.id,
+        AttemptKind::Volunteer,
+        authentication.card_id,
+        ip,
+    );
+
+    // If there are too many failed attempts, redirects to authentication page
+    if let Some(minutes) = query_error(attempts.locked_minutes(), uri).await? {
         return Ok(Flash::error(
             Redirect::to(uri!(show_authentication)),
-            "card_id_disabled",
+            format!("{LOCKED_FLASH}{minutes}"),
         ));
     }
 
     // Capitalize surname
     let surname = capitalize(authentication.surname.trim());
 
-    // Check whether the surname is present in the volunteers table
-    let is_surname = query_error(query_check_surname(&state.pool, &surname), uri).await?;
-
-    // Save surname cookie
-    jar.add_private((SURNAME_COOKIE, authentication.surname.to_string()));
-
-    // If the surname is wrong, redirect to authentication page
-    if !is_surname {
-        return Ok(Flash::error(
-            Redirect::to(uri!(show_authentication)),
-            SURNAME_COOKIE,
-        ));
-    }
-
-    // If both card identifier and surname values are wrong, redirect to
-    // authentication page
-    if !is_card_id && !is_surname {
-        // Redirect to authentication page
-        return Ok(Flash::error(
-            Redirect::to(uri!(show_authentication)),
-            "card_id-surname",
-        ));
-    }
-
-    // Check whether the surname is associated to the card identification
-    let is_query_surname = query_error(
-        query_card_id_to_surname(&state.pool, authentication.card_id, &surname),
+    // If credentials are wrong, redirects to authentication page
+    if let Some(error) = query_error(
+        credentials_error(
+            &state.pool,
+            organisation.id,
+            authentication.card_id,
+            &surname,
+        ),
         uri,
     )
-    .await?;
-
-    // If the surname is not associated to the right card identification,
-    // redirects to authentication page
-    if !is_query_surname {
+    .await?
+    {
+        query_error(attempts.failed(), uri).await?;
         return Ok(Flash::error(
             Redirect::to(uri!(show_authentication)),
-            "card_id-wrong-surname",
+            if state.uniform_login_errors {
+                WRONG_CREDENTIALS_FLASH
+            } else {
+                error
+            },
         ));
     }
 
@@ -245,11 +274,42 @@ pub(crate) async fn check_authentication<'r>(
     jar.remove_private(CARD_COOKIE);
     jar.remove_private(SURNAME_COOKIE);
 
-    // Set cookie to certificate authentication
-    jar.add_private((AUTHENTICATION_COOKIE, authentication.card_id.to_string()));
+    // When login codes are enabled, send a code and ask for it before
+    // starting the session. Failed attempts are reset only once the code is
+    // verified, and each sent code counts as an attempt, so that codes cannot
+    // be requested without limits.
+    if let Some(sender) = &state.code_sender {
+        query_error(attempts.code_sent(), uri).await?;
+        query_error(
+            send_login_code(
+                &state.pool,
+                organisation.id,
+                sender.as_ref(),
+                authentication.card_id,
+            ),
+            uri,
+        )
+        .await?;
+        jar.add_private((PENDING_CARD_COOKIE, authentication.card_id.to_string()));
+        return Ok(Flash::success(Redirect::to(CODE_ROUTE), "Login code sent."));
+    }
 
-    // Remove administration cookie
-    jar.remove_private(ADMINISTRATION_COOKIE);
+    // Reset failed attempts
+    query_error(attempts.succeeded(), uri).await?;
+
+    // Start a volunteer session
+    query_error(
+        start_session(
+            state,
+            organisation.id,
+            jar,
+            authentication.card_id,
+            false,
+            &user_agent,
+        ),
+        uri,
+    )
+    .await?;
 
     // If everything is correct, redirect to shift manager
     Ok(Flash::success(
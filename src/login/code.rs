@@ -0,0 +1,305 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use chrono::{Duration, Utc};
+
+use rand::Rng;
+
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::CookieJar;
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
+use rocket::tokio::fs::OpenOptions;
+use rocket::tokio::io::AsyncWriteExt;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use sqlx::PgPool;
+
+use crate::data::Button;
+use crate::database::{
+    delete_login_code, insert_login_code, query_login_code, query_volunteer_phone_number,
+    Organisation,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::token::{hash_token, tokens_match};
+use crate::{AppState, CODE_ROUTE, SHIFTS_MANAGER_ROUTE};
+
+use super::attempts::{AttemptKind, LoginAttempts};
+use super::session::{start_session, UserAgent};
+use super::{credentials_message, CODE_EXPIRED_FLASH, LOCKED_FLASH};
+
+// Number of digits of a login code
+const CODE_DIGITS: usize = 6;
+// Minutes of validity of a login code
+const CODE_VALIDITY: i64 = 10;
+// Maximum number of verification attempts for a login code
+const CODE_ATTEMPTS: i32 = 5;
+
+// Cookie containing the card identifier waiting for a login code
+pub(crate) const PENDING_CARD_COOKIE: &str = "pending_card_id";
+
+macro_rules! shifts_manager_uri {
+    ($($t:tt)*) => (rocket::uri!(SHIFTS_MANAGER_ROUTE, $crate::shifts_manager:: $($t)*))
+}
+
+// Sender which delivers login codes to volunteers
+#[rocket::async_trait]
+pub(crate) trait CodeSender: Send + Sync {
+    // Send a login code to a recipient
+    async fn send(&self, recipient: &str, code: &str) -> Result<()>;
+}
+
+// Sender which prints login codes on the console
+struct ConsoleSender;
+
+#[rocket::async_trait]
+impl CodeSender for ConsoleSender {
+    async fn send(&self, recipient: &str, code: &str) -> Result<()> {
+        println!("Codice di accesso per {recipient}: {code}");
+        Ok(())
+    }
+}
+
+// Sender which appends login codes to a file
+struct FileSender(PathBuf);
+
+#[rocket::async_trait]
+impl CodeSender for FileSender {
+    async fn send(&self, recipient: &str, code: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.0)
+            .await?;
+        file.write_all(format!("{recipient} {code}\n").as_bytes())
+            .await?;
+        Ok(())
+    }
+}
+
+// Create the login code sender from its configuration:
+// - `console` prints codes on the console
+// - `file:<path>` appends codes to the file at `path`
+// - an empty value or `none` disables login codes
+pub(crate) fn code_sender(config: &str) -> Result<Option<Box<dyn CodeSender>>> {
+    match config {
+        "" | "none" => Ok(None),
+        "console" => Ok(Some(Box::new(ConsoleSender))),
+        _ => config
+            .strip_prefix("file:")
+            .map(|path| Some(Box::new(FileSender(path.into())) as Box<dyn CodeSender>))
+            .ok_or_else(|| anyhow!("Unknown login code sender \"{config}\"")),
+    }
+}
+
+// Hide all recipient characters except the last three ones
+fn mask_recipient(recipient: &str) -> String {
+    let length = recipient.chars().count();
+    recipient
+        .chars()
+        .enumerate()
+        .map(|(index, c)| if index + 3 < length { '*' } else { c })
+        .collect()
+}
+
+// Generate a new login code for a volunteer and send it
+pub(crate) async fn send_login_code(
+    pool: &PgPool,
+    organisation_id: i32,
+    sender: &dyn CodeSender,
+    card_id: i16,
+) -> Result<()> {
+    let code: String = {
+        let mut rng = rand::thread_rng();
+        (0..CODE_DIGITS)
+            .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+            .collect()
+    };
+
+    // Only the hash of the code is saved, as for API tokens
+    let expires_at = Utc::now() + Duration::minutes(CODE_VALIDITY);
+    insert_login_code(
+        pool,
+        organisation_id,
+        card_id,
+        &hash_token(&code),
+        expires_at,
+    )
+    .await?;
+
+    let recipient = query_volunteer_phone_number(pool, organisation_id, card_id).await?;
+    sender.send(&recipient, &code).await
+}
+
+// Retrieve the card identifier waiting for a login code
+fn pending_card_id(jar: &CookieJar<'_>) -> Option<i16> {
+    super::get_cookie_value(jar, PENDING_CARD_COOKIE).and_then(|value| value.parse().ok())
+}
+
+// Login code information
+#[derive(Serialize)]
+struct CodeInfo {
+    // Generic description
+    description: String,
+    // Code text
+    code_text: String,
+    // Code placeholder
+    code_placeholder: String,
+    // Code message when the field is not valid
+    code_error_message: String,
+    // Code pattern
+    code_pattern: String,
+    // Number of digits
+    code_digits: usize,
+}
+
+#[get("/")]
+async fn show_login_code(
+    flash: Option<FlashMessage<'_>>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Result<Template, Redirect>, InternalError> {
+    // Without a card identifier, redirect to authentication page
+    let Some(card_id) = pending_card_id(jar) else {
+        return Ok(Err(Redirect::to(uri!(
+            crate::login::authentication::show_authentication
+        ))));
+    };
+
+    // Get error message
+    let error_message = flash.as_ref().map(FlashMessage::message).and_then(|error| {
+        if error == "wrong-code" {
+            Some(locale.text("code-wrong"))
+        } else {
+            credentials_message(locale, error)
+        }
+    });
+
+    let recipient = query_error(
+        query_volunteer_phone_number(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?;
+
+    Ok(Ok(Template::render(
+        "login_code",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            route: CODE_ROUTE,
+            code_info: CodeInfo {
+                description: locale.format(
+                    "code-description",
+                    &[("recipient", mask_recipient(&recipient).into())],
+                ),
+                code_text: locale.text("code-text"),
+                code_placeholder: locale.text("code-placeholder"),
+                code_error_message: locale.format(
+                    "code-empty",
+                    &[("digits", CODE_DIGITS.into())],
+                ),
+                code_pattern: format!("[0-9]{{{CODE_DIGITS}}}"),
+                code_digits: CODE_DIGITS,
+            },
+            error_message,
+            button: Button::authentication_button(locale),
+        },
+    )))
+}
+
+#[derive(FromForm)]
+struct LoginCode<'r> {
+    code: &'r str,
+}
+
+#[post("/", data = "<code_form>")]
+async fn check_login_code<'r>(
+    code_form: Form<LoginCode<'r>>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+    ip: Option<IpAddr>,
+    user_agent: UserAgent,
+) -> Result<Flash<Redirect>, InternalError> {
+    // Without a card identifier, redirect to authentication page
+    let Some(card_id) = pending_card_id(jar) else {
+        return Ok(Flash::error(
+            Redirect::to(uri!(crate::login::authentication::show_authentication)),
+            CODE_EXPIRED_FLASH,
+        ));
+    };
+
+    // Login attempts of the client
+    let attempts = LoginAttempts::new(
+        &state.pool,
+        organisation.id,
+        AttemptKind::Volunteer,
+        card_id,
+        ip,
+    );
+
+    // If there are too many failed attempts, redirects to login code page
+    if let Some(minutes) = query_error(attempts.locked_minutes(), uri).await? {
+        return Ok(Flash::error(
+            Redirect::to(CODE_ROUTE),
+            format!("{LOCKED_FLASH}{minutes}"),
+        ));
+    }
+
+    // If the code is expired, redirects to authentication page
+    let Some(code) = query_error(
+        query_login_code(&state.pool, organisation.id, card_id, CODE_ATTEMPTS),
+        uri,
+    )
+    .await?
+    else {
+        jar.remove_private(PENDING_CARD_COOKIE);
+        return Ok(Flash::error(
+            Redirect::to(uri!(crate::login::authentication::show_authentication)),
+            CODE_EXPIRED_FLASH,
+        ));
+    };
+
+    // If the code is wrong, redirects to login code page
+    if !tokens_match(&hash_token(code_form.code.trim()), &code) {
+        query_error(attempts.failed(), uri).await?;
+        return Ok(Flash::error(Redirect::to(CODE_ROUTE), "wrong-code"));
+    }
+
+    // Reset failed attempts and remove the used code
+    query_error(attempts.succeeded(), uri).await?;
+    query_error(
+        delete_login_code(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?;
+    jar.remove_private(PENDING_CARD_COOKIE);
+
+    // Start a volunteer session
+    query_error(
+        start_session(state, organisation.id, jar, card_id, false, &user_agent),
+        uri,
+    )
+    .await?;
+
+    // If everything is correct, redirect to shift manager
+    Ok(Flash::success(
+        Redirect::to(shifts_manager_uri!(show_shifts_manager(card_id))),
+        "Successful authentication.",
+    ))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_login_code, check_login_code]
+}
@@ -0,0 +1,271 @@
+use chrono::{Duration, Utc};
+use chrono_tz::Tz;
+
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::http::CookieJar;
+use rocket::response::{Flash, Redirect};
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::database::{
+    delete_expired_invitations, delete_invitation, insert_invitation, query_invitation_card_id,
+    query_invitations, query_is_disabled, use_invitation, Invitation, Organisation,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::menu::MenuAdministration;
+use crate::time::format_date_time;
+use crate::token::random_token;
+use crate::{AppState, INVITATION_ROUTE, SHIFTS_MANAGER_ROUTE, VOLUNTEERS_ROUTE};
+
+use super::session::{check_administration_access, start_session, Session, UserAgent};
+
+// Days of validity of an invitation
+pub(crate) const INVITATION_DAYS: i64 = 7;
+
+// Messages
+const HEADING_MESSAGE: &str = "invitations-heading";
+const EMPTY_INVITATIONS_MESSAGE: &str = "invitations-empty";
+const REVOKE_MESSAGE: &str = "invitations-revoke";
+const SINGLE_USE_MESSAGE: &str = "invitations-single-use";
+const TIME_LIMITED_MESSAGE: &str = "invitations-time-limited";
+const DISABLED_VOLUNTEER_MESSAGE: &str = "invitations-disabled-volunteer";
+const ACCEPT_HEADING_MESSAGE: &str = "invitations-accept-heading";
+const ACCEPT_MESSAGE: &str = "invitations-accept";
+
+// Route to volunteers page
+macro_rules! volunteers_uri {
+    ($($t:tt)*) => (rocket::uri!(VOLUNTEERS_ROUTE, $($t)*))
+}
+
+macro_rules! shifts_manager_uri {
+    ($($t:tt)*) => (rocket::uri!(SHIFTS_MANAGER_ROUTE, $crate::shifts_manager:: $($t)*))
+}
+
+// Outstanding invitation and the relative routes
+#[derive(Serialize)]
+struct InvitationInfo {
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname and name
+    surname_name: String,
+    // Kind of invitation
+    kind: String,
+    // Creation date and time
+    created_at: String,
+    // Expiration date and time
+    expires_at: String,
+    // Route contained in the invitation link
+    invitation_route: Origin<'static>,
+    // Route to revoke the invitation
+    revoke_route: Origin<'static>,
+}
+
+impl InvitationInfo {
+    fn invitations(locale: Locale, timezone: Tz, invitations: Vec<Invitation>) -> Vec<Self> {
+        invitations
+            .into_iter()
+            .map(|invitation| Self {
+                card_id: invitation.card_id,
+                surname_name: format!("{} {}", invitation.surname, invitation.name),
+                kind: locale.text(if invitation.single_use {
+                    SINGLE_USE_MESSAGE
+                } else {
+                    TIME_LIMITED_MESSAGE
+                }),
+                created_at: format_date_time(timezone, invitation.created_at),
+                expires_at: format_date_time(timezone, invitation.expires_at),
+                invitation_route: uri!(INVITATION_ROUTE, show_invitation(&invitation.token)),
+                revoke_route: volunteers_uri!(revoke_invitation(invitation.id)),
+            })
+            .collect()
+    }
+}
+
+#[get("/inviti")]
+pub(crate) async fn show_invitations(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Get all outstanding invitations
+    let invitations = InvitationInfo::invitations(
+        locale,
+        state.timezone,
+        query_error(query_invitations(&state.pool, organisation.id), uri).await?,
+    );
+
+    Ok(Template::render(
+        "invitations",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            no_invitations_message: invitations.is_empty().then(|| locale.text(EMPTY_INVITATIONS_MESSAGE)),
+            invitations,
+            revoke_message: locale.text(REVOKE_MESSAGE),
+        },
+    ))
+}
+
+#[derive(FromForm)]
+struct NewInvitation {
+    single_use: bool,
+}
+
+#[post("/inviti/<card_id>", data = "<invitation_form>")]
+async fn create_invitation(
+    card_id: i16,
+    invitation_form: Form<NewInvitation>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Disabled volunteers cannot be invited
+    if query_error(
+        query_is_disabled(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    {
+        return Err(InternalError::text(uri, DISABLED_VOLUNTEER_MESSAGE));
+    }
+
+    // Remove expired invitations
+    query_error(delete_expired_invitations(&state.pool), uri).await?;
+
+    // Save the new invitation
+    let expires_at = Utc::now() + Duration::days(INVITATION_DAYS);
+    query_error(
+        insert_invitation(
+            &state.pool,
+            organisation.id,
+            &random_token(),
+            card_id,
+            invitation_form.single_use,
+            expires_at,
+        ),
+        uri,
+    )
+    .await?;
+
+    // Redirect to invitations page, which contains the new link
+    Ok(Redirect::to(volunteers_uri!(show_invitations)))
+}
+
+#[delete("/inviti/<id>")]
+async fn revoke_invitation(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Delete the invitation
+    query_error(delete_invitation(&state.pool, organisation.id, id), uri).await?;
+
+    // Redirect to invitations page
+    Ok(Redirect::to(volunteers_uri!(show_invitations)))
+}
+
+// Opening an invitation link only asks for a confirmation, so that link
+// previews and scanners cannot consume single-use invitations
+#[get("/<token>")]
+pub(crate) async fn show_invitation(
+    token: &str,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check whether the invitation exists without using it
+    let Some(card_id) = query_error(
+        query_invitation_card_id(&state.pool, organisation.id, token),
+        uri,
+    )
+    .await?
+    else {
+        return Err(InternalError::invalid_invitation(uri));
+    };
+
+    // Disabled volunteers cannot access
+    if query_error(
+        query_is_disabled(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    {
+        return Err(InternalError::invalid_invitation(uri));
+    }
+
+    Ok(Template::render(
+        "invitation",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            route: uri!(INVITATION_ROUTE, accept_invitation(token)),
+            heading_message: locale.text(ACCEPT_HEADING_MESSAGE),
+            accept_message: locale.text(ACCEPT_MESSAGE),
+        },
+    ))
+}
+
+#[post("/<token>")]
+pub(crate) async fn accept_invitation(
+    token: &str,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+    user_agent: UserAgent,
+) -> Result<Flash<Redirect>, InternalError> {
+    // Retrieve the invited volunteer, consuming single-use invitations
+    let Some(card_id) =
+        query_error(use_invitation(&state.pool, organisation.id, token), uri).await?
+    else {
+        return Err(InternalError::invalid_invitation(uri));
+    };
+
+    // Disabled volunteers cannot access
+    if query_error(
+        query_is_disabled(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    {
+        return Err(InternalError::invalid_invitation(uri));
+    }
+
+    // Start a volunteer session
+    query_error(
+        start_session(state, organisation.id, jar, card_id, false, &user_agent),
+        uri,
+    )
+    .await?;
+
+    // Redirect to shift manager
+    Ok(Flash::success(
+        Redirect::to(shifts_manager_uri!(show_shifts_manager(card_id))),
+        "Successful authentication.",
+    ))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_invitations, create_invitation, revoke_invitation]
+}
@@ -1,14 +1,44 @@
 pub(crate) mod administration;
+pub(crate) mod attempts;
 pub(crate) mod authentication;
+pub(crate) mod code;
+pub(crate) mod invitation;
+pub(crate) mod session;
 
 use rocket::http::{Cookie, CookieJar};
 
+use crate::locale::Locale;
+
 // Cookies
-pub(crate) const AUTHENTICATION_COOKIE: &str = "authenticated";
-pub(crate) const ADMINISTRATION_COOKIE: &str = "administration";
+pub(crate) const SESSION_COOKIE: &str = "session";
+pub(crate) const ADMINISTRATION_CARD_COOKIE: &str = "administration_card_id";
 pub(crate) const CARD_COOKIE: &str = "card_id";
 pub(crate) const SURNAME_COOKIE: &str = "surname";
 
+// Flash messages shared by authentication pages
+pub(crate) const WRONG_CREDENTIALS_FLASH: &str = "wrong-credentials";
+pub(crate) const LOCKED_FLASH: &str = "locked-";
+pub(crate) const CODE_EXPIRED_FLASH: &str = "code-expired";
+
+// Message shown when the uniform error message is enabled
+const WRONG_CREDENTIALS_MESSAGE: &str = "login-wrong-credentials";
+// Message shown when a login code is expired
+const CODE_EXPIRED_MESSAGE: &str = "login-code-expired";
+
+// Retrieve the message associated to a credentials error, if any
+pub(crate) fn credentials_message(locale: Locale, flash: &str) -> Option<String> {
+    if flash == WRONG_CREDENTIALS_FLASH {
+        Some(locale.text(WRONG_CREDENTIALS_MESSAGE))
+    } else if flash == CODE_EXPIRED_FLASH {
+        Some(locale.text(CODE_EXPIRED_MESSAGE))
+    } else {
+        flash.strip_prefix(LOCKED_FLASH).map(|minutes| {
+            let minutes = minutes.parse::<u32>().unwrap_or_default();
+            locale.format("login-locked", &[("minutes", minutes.into())])
+        })
+    }
+}
+
 #[inline(always)]
 pub(crate) fn get_cookie_value(jar: &CookieJar<'_>, name: &str) -> Option<String> {
     jar.get_private(name)
@@ -0,0 +1,336 @@
+use anyhow::Result;
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+
+use rocket::http::uri::Origin;
+use rocket::http::{CookieJar, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::Redirect;
+use rocket::{Request, State};
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::database::{
+    delete_expired_sessions, delete_session, delete_session_from_token, insert_session,
+    query_active_sessions, query_refresh_session, query_volunteer_preferences, ActiveSession,
+    Organisation,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::{save_language, Locale};
+use crate::menu::MenuAdministration;
+use crate::time::format_date_time;
+use crate::token::random_token;
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+use super::administration::administration_uri;
+use super::{get_cookie_value, SESSION_COOKIE};
+
+// Maximum length of a saved user agent
+const USER_AGENT_LENGTH: usize = 300;
+
+// Messages
+const HEADING_MESSAGE: &str = "sessions-heading";
+const EMPTY_SESSIONS_MESSAGE: &str = "sessions-empty";
+const REVOKE_MESSAGE: &str = "sessions-revoke";
+const CURRENT_SESSION_MESSAGE: &str = "sessions-current";
+const ADMINISTRATION_ROLE: &str = "sessions-administration-role";
+const VOLUNTEER_ROLE: &str = "sessions-volunteer-role";
+
+// Session timeouts
+pub(crate) struct SessionTimeouts {
+    // Maximum time between two requests
+    pub(crate) idle: Duration,
+    // Maximum duration of a session
+    pub(crate) absolute: Duration,
+}
+
+impl SessionTimeouts {
+    // Sessions whose last request or creation happened before these limits
+    // are expired
+    fn limits(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        let now = Utc::now();
+        (now - self.idle, now - self.absolute)
+    }
+}
+
+// Authenticated session
+pub(crate) struct Session {
+    // Session identifier
+    pub(crate) id: i32,
+    // Card identifier of the authenticated volunteer
+    pub(crate) card_id: i16,
+    // Check whether the session belongs to a referent
+    pub(crate) is_administration: bool,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Session {
+    type Error = anyhow::Error;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(token) = get_cookie_value(req.cookies(), SESSION_COOKIE) else {
+            return Outcome::Forward(Status::Unauthorized);
+        };
+
+        let Some(state) = req.rocket().state::<AppState>() else {
+            return Outcome::Forward(Status::InternalServerError);
+        };
+
+        // Sessions are valid only inside their organisation
+        let Outcome::Success(organisation) = req.guard::<&Organisation>().await else {
+            return Outcome::Forward(Status::InternalServerError);
+        };
+
+        // Retrieve the session only if it is not expired
+        let (idle_limit, absolute_limit) = state.session_timeouts.limits();
+        match query_refresh_session(
+            &state.pool,
+            organisation.id,
+            &token,
+            idle_limit,
+            absolute_limit,
+        )
+        .await
+        {
+            Ok(Some(session)) => Outcome::Success(Self {
+                id: session.id,
+                card_id: session.card_id,
+                is_administration: session.is_administration,
+            }),
+            Ok(None) => {
+                // Remove the cookie of an expired or revoked session
+                req.cookies().remove_private(SESSION_COOKIE);
+                Outcome::Forward(Status::Unauthorized)
+            }
+            Err(e) => Outcome::Error((Status::InternalServerError, e)),
+        }
+    }
+}
+
+// Browser used to perform a request
+pub(crate) struct UserAgent(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self(
+            req.headers()
+                .get_one("User-Agent")
+                .unwrap_or_default()
+                .chars()
+                .take(USER_AGENT_LENGTH)
+                .collect(),
+        ))
+    }
+}
+
+// Check whether a session can access the data of a volunteer, returning
+// whether the session belongs to a referent
+pub(crate) fn check_volunteer_access(
+    session: &Option<Session>,
+    card_id: i16,
+    uri: &Origin<'_>,
+) -> Result<bool, InternalError> {
+    match session {
+        Some(session) if session.is_administration => Ok(true),
+        Some(session) if session.card_id != card_id => Err(InternalError::wrong_card_id(uri)),
+        Some(_) => Ok(false),
+        None => Err(InternalError::not_authenticated(uri)),
+    }
+}
+
+// Check whether a session belongs to a referent
+pub(crate) fn check_administration_access<'a>(
+    session: &'a Option<Session>,
+    uri: &Origin<'_>,
+) -> Result<&'a Session, InternalError> {
+    session
+        .as_ref()
+        .filter(|session| session.is_administration)
+        .ok_or_else(|| InternalError::not_authenticated_administrator(uri))
+}
+
+// Start a new session, closing the current one
+pub(crate) async fn start_session(
+    state: &AppState,
+    organisation_id: i32,
+    jar: &CookieJar<'_>,
+    card_id: i16,
+    is_administration: bool,
+    user_agent: &UserAgent,
+) -> Result<()> {
+    // Close the current session
+    if let Some(token) = get_cookie_value(jar, SESSION_COOKIE) {
+        delete_session_from_token(&state.pool, organisation_id, &token).await?;
+    }
+
+    // Remove expired sessions
+    let (idle_limit, absolute_limit) = state.session_timeouts.limits();
+    delete_expired_sessions(&state.pool, idle_limit, absolute_limit).await?;
+
+    let token = random_token();
+    insert_session(
+        &state.pool,
+        organisation_id,
+        &token,
+        card_id,
+        is_administration,
+        &user_agent.0,
+    )
+    .await?;
+
+    // Save session cookie
+    jar.add_private((SESSION_COOKIE, token));
+
+    // Show pages in the language chosen by the volunteer in the profile
+    if !is_administration {
+        let preferences =
+            query_volunteer_preferences(&state.pool, organisation_id, card_id).await?;
+        if let Some(locale) = preferences.language.as_deref().and_then(Locale::from_code) {
+            save_language(jar, locale);
+        }
+    }
+
+    Ok(())
+}
+
+#[post("/esci")]
+pub(crate) async fn logout(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Close the current session
+    if let Some(token) = get_cookie_value(jar, SESSION_COOKIE) {
+        query_error(
+            delete_session_from_token(&state.pool, organisation.id, &token),
+            uri,
+        )
+        .await?;
+    }
+    jar.remove_private(SESSION_COOKIE);
+
+    // Redirect to the right authentication page
+    if session.is_some_and(|session| session.is_administration) {
+        Ok(Redirect::to(ADMINISTRATION_ROUTE))
+    } else {
+        Ok(Redirect::to(uri!(
+            crate::login::authentication::show_authentication
+        )))
+    }
+}
+
+// Active session and the relative route to revoke it
+#[derive(Serialize)]
+struct SessionInfo {
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname and name
+    surname_name: String,
+    // Volunteer role
+    role: String,
+    // Browser
+    user_agent: String,
+    // Authentication date and time
+    created_at: String,
+    // Last request date and time
+    last_seen: String,
+    // Whether this is the session used to visualize the page
+    is_current: bool,
+    // Route to revoke the session
+    revoke_route: Origin<'static>,
+}
+
+impl SessionInfo {
+    fn sessions(
+        locale: Locale,
+        timezone: Tz,
+        sessions: Vec<ActiveSession>,
+        current_id: i32,
+    ) -> Vec<Self> {
+        sessions
+            .into_iter()
+            .map(|active| Self {
+                card_id: active.session.card_id,
+                surname_name: format!("{} {}", active.surname, active.name),
+                role: locale.text(if active.session.is_administration {
+                    ADMINISTRATION_ROLE
+                } else {
+                    VOLUNTEER_ROLE
+                }),
+                user_agent: active.user_agent,
+                created_at: format_date_time(timezone, active.created_at),
+                last_seen: format_date_time(timezone, active.last_seen),
+                is_current: active.session.id == current_id,
+                revoke_route: administration_uri!(revoke_session(active.session.id)),
+            })
+            .collect()
+    }
+}
+
+#[get("/sessioni")]
+pub(crate) async fn show_sessions(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    let current = check_administration_access(&session, uri)?;
+
+    // Get all active sessions
+    let (idle_limit, absolute_limit) = state.session_timeouts.limits();
+    let sessions = SessionInfo::sessions(
+        locale,
+        state.timezone,
+        query_error(
+            query_active_sessions(&state.pool, organisation.id, idle_limit, absolute_limit),
+            uri,
+        )
+        .await?,
+        current.id,
+    );
+
+    Ok(Template::render(
+        "sessions",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            no_sessions_message: sessions.is_empty().then(|| locale.text(EMPTY_SESSIONS_MESSAGE)),
+            sessions,
+            current_session_message: locale.text(CURRENT_SESSION_MESSAGE),
+            revoke_message: locale.text(REVOKE_MESSAGE),
+        },
+    ))
+}
+
+#[delete("/sessioni/<id>")]
+async fn revoke_session(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Delete the session
+    query_error(delete_session(&state.pool, organisation.id, id), uri).await?;
+
+    // Redirect to sessions page
+    Ok(Redirect::to(administration_uri!(show_sessions)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_sessions, revoke_session]
+}
@@ -1,40 +1,71 @@
 #[macro_use]
 extern crate rocket;
 
+mod account;
+mod api;
+mod archive;
+mod assignments;
+mod backup;
+mod certificate;
+mod change_requests;
 mod cookie;
+mod csrf;
 mod data;
 mod database;
 mod download_database;
 mod error;
+mod locale;
 mod login;
 mod menu;
+mod organisation;
+mod planning;
+mod print;
 mod shifts;
 mod shifts_manager;
+mod statistics;
 mod time;
+mod token;
 mod visualizer;
+mod volunteer_profile;
 mod volunteers;
+mod webhooks;
 
 use anyhow::anyhow;
 
+use chrono::Duration;
+use chrono_tz::Tz;
+
 use rocket::config::Config;
 use rocket::fs::{relative, FileServer};
 use rocket::http::uri::Origin;
 use rocket::http::CookieJar;
 use rocket::request::FlashMessage;
-use rocket::tokio::sync::broadcast::{channel, Sender};
 use rocket_dyn_templates::Template;
 
 use shuttle_secrets::SecretStore;
 
+use csrf::Csrf;
+use download_database::show_export;
+use locale::Locale;
 use login::authentication::{check_authentication, show_authentication};
+use login::code::{code_sender, CodeSender};
+use login::invitation::{accept_invitation, show_invitation};
+use login::session::{logout, SessionTimeouts};
+use organisation::{Organisations, Tenancy};
 use shifts::remove_shift;
 use shifts_manager::add_shift;
+use token::hash_password;
+use visualizer::ShiftsEvents;
 use volunteers::update_volunteers;
+use webhooks::{send_event, WebhookEvent};
 
-use database::{create_volunteers_shifts_tables, fill_volunteers_table};
+use database::{
+    create_volunteers_shifts_tables, query_organisations, refill_volunteers_table,
+    update_default_organisation, Organisation,
+};
 
 const APP_TITLE: &str = "Turni Volontari";
-const DISABLED_MESSAGE: &str = "Tessera Disabilitata";
+const DISABLED_MESSAGE: &str = "card-disabled";
 
 // Italian routes
 const VOLUNTEERS_ROUTE: Origin<'static> = uri!("/volontari");
@@ -44,23 +75,44 @@ const VISUALIZE_SHIFTS_ROUTE: Origin<'static> = uri!("/visualizzaturni");
 const ADMINISTRATION_ROUTE: Origin<'static> = uri!("/referenti");
 const COOKIE_ROUTE: Origin<'static> = uri!("/cookie");
 const DOWNLOAD_DATABASE_ROUTE: Origin<'static> = uri!("/download/database");
+const CSRF_ROUTE: Origin<'static> = uri!("/csrf");
+const CODE_ROUTE: Origin<'static> = uri!("/codice");
+const INVITATION_ROUTE: Origin<'static> = uri!("/invito");
+const API_ROUTE: Origin<'static> = uri!("/api/v1");
+const LANGUAGE_ROUTE: Origin<'static> = uri!("/lingua");
+const ACCOUNT_ROUTE: Origin<'static> = uri!("/profilo");
 
 // Cookies
 const POLICY_COOKIE: &str = "cookie-policy";
 
+// Default sessions timeouts
+const SESSION_IDLE_MINUTES: i64 = 60;
+const SESSION_ABSOLUTE_HOURS: i64 = 12;
+
+// Default organisation timezone
+const TIMEZONE: Tz = chrono_tz::Europe::Rome;
+
 #[get("/")]
-async fn index(flash: Option<FlashMessage<'_>>, jar: &CookieJar<'_>) -> Template {
-    show_authentication(flash, jar).await
+async fn index(
+    flash: Option<FlashMessage<'_>>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    locale: Locale,
+) -> Template {
+    show_authentication(flash, organisation, jar, locale).await
 }
 
 // Access app state among various route calls
 pub(crate) struct AppState {
     pub(crate) pool: sqlx::PgPool,
-    pub(crate) sender: Sender<u8>,
-    pub(crate) volunteers_url: String,
-    pub(crate) administration_password: String,
+    pub(crate) sender: ShiftsEvents,
     pub(crate) email: String,
     pub(crate) website: String,
+    pub(crate) uniform_login_errors: bool,
+    pub(crate) session_timeouts: SessionTimeouts,
+    pub(crate) code_sender: Option<Box<dyn CodeSender>>,
+    pub(crate) timezone: Tz,
+    pub(crate) organisations: Organisations,
 }
 
 #[shuttle_runtime::main]
@@ -97,6 +149,43 @@ async fn rocket(
         return Err(anyhow!("Website for cookie policy not found").into());
     };
 
+    // Show the same error message for any kind of wrong credentials
+    let uniform_login_errors = secret_store
+        .get("UNIFORM_LOGIN_ERRORS")
+        .is_some_and(|value| value == "true");
+
+    // Get sessions timeouts
+    let session_timeouts = SessionTimeouts {
+        idle: Duration::minutes(
+            secret_store
+                .get("SESSION_IDLE_MINUTES")
+                .and_then(|minutes| minutes.parse().ok())
+                .unwrap_or(SESSION_IDLE_MINUTES),
+        ),
+        absolute: Duration::hours(
+            secret_store
+                .get("SESSION_ABSOLUTE_HOURS")
+                .and_then(|hours| hours.parse().ok())
+                .unwrap_or(SESSION_ABSOLUTE_HOURS),
+        ),
+    };
+
+    // Get login codes sender, login codes are disabled when it is missing
+    let code_sender = code_sender(&secret_store.get("LOGIN_CODE_SENDER").unwrap_or_default())?;
+
+    // Get association name of the first organisation
+    let association_name = secret_store
+        .get("ASSOCIATION_NAME")
+        .unwrap_or_else(|| APP_TITLE.to_string());
+
+    // Get organisation timezone, used to establish the current date
+    let timezone = match secret_store.get("TIMEZONE") {
+        Some(timezone) => timezone
+            .parse()
+            .map_err(|_| anyhow!("Timezone \"{timezone}\" not found"))?,
+        None => TIMEZONE,
+    };
+
     // Get rocket secret key for private cookies
     let rocket_secret_key = if let Some(secret_key) = secret_store.get("ROCKET_SECRET_KEY") {
         secret_key
@@ -107,11 +196,34 @@ async fn rocket(
     // Configure secret key for rocket
     let figment = Config::figment().merge(("secret_key", rocket_secret_key));
 
-    // Create volunteers and shifts tables
+    // Create missing tables
     create_volunteers_shifts_tables(&pool).await?;
 
-    // Fill volunteers table
-    fill_volunteers_table(&pool, &volunteers_url).await?;
+    // Save the data of the first organisation, the password is never saved
+    // in clear
+    update_default_organisation(
+        &pool,
+        &association_name,
+        &volunteers_url,
+        &hash_password(&administration_password)?,
+    )
+    .await?;
+
+    // Insert new volunteers and update the saved ones of every organisation,
+    // notifying webhooks
+    let organisations = query_organisations(&pool).await?;
+    for organisation in &organisations {
+        let changes =
+            refill_volunteers_table(&pool, organisation.id, &organisation.volunteers_url).await?;
+        if !changes.added.is_empty() || !changes.updated.is_empty() {
+            send_event(
+                &pool,
+                organisation.id,
+                &WebhookEvent::VolunteersUpdated(changes),
+            )
+            .await;
+        }
+    }
 
     let rocket = rocket::custom(figment)
         .mount(
@@ -121,28 +233,64 @@ async fn rocket(
                 check_authentication,
                 add_shift,
                 remove_shift,
-                update_volunteers
+                update_volunteers,
+                logout
             ],
         )
         .mount(VOLUNTEERS_ROUTE, volunteers::routes())
+        .mount(VOLUNTEERS_ROUTE, volunteer_profile::routes())
+        .mount(VOLUNTEERS_ROUTE, login::invitation::routes())
         .mount(SHIFTS_ROUTE, shifts::routes())
+        .mount(SHIFTS_ROUTE, certificate::routes())
         .mount(VISUALIZE_SHIFTS_ROUTE, visualizer::routes())
         .mount(SHIFTS_MANAGER_ROUTE, shifts_manager::routes())
+        .mount(ACCOUNT_ROUTE, account::routes())
         .mount(ADMINISTRATION_ROUTE, login::administration::routes())
+        .mount(ADMINISTRATION_ROUTE, login::attempts::routes())
+        .mount(ADMINISTRATION_ROUTE, login::session::routes())
+        .mount(ADMINISTRATION_ROUTE, routes![show_export])
+        .mount(ADMINISTRATION_ROUTE, archive::routes())
+        .mount(ADMINISTRATION_ROUTE, assignments::routes())
+        .mount(ADMINISTRATION_ROUTE, planning::routes())
+        .mount(ADMINISTRATION_ROUTE, statistics::routes())
+        .mount(ADMINISTRATION_ROUTE, backup::routes())
+        .mount(ADMINISTRATION_ROUTE, api::token::routes())
+        .mount(ADMINISTRATION_ROUTE, webhooks::routes())
+        .mount(ADMINISTRATION_ROUTE, change_requests::routes())
         .mount(COOKIE_ROUTE, cookie::routes())
         .mount(DOWNLOAD_DATABASE_ROUTE, download_database::routes())
+        .mount(CSRF_ROUTE, csrf::routes())
+        .mount(CODE_ROUTE, login::code::routes())
+        .mount(
+            INVITATION_ROUTE,
+            routes![show_invitation, accept_invitation],
+        )
+        .mount(API_ROUTE, api::routes())
+        .mount(LANGUAGE_ROUTE, locale::routes())
         .mount("/", FileServer::from(relative!("assets")).rank(4))
         .mount(SHIFTS_ROUTE, FileServer::from(relative!("assets")).rank(5))
         .manage(AppState {
             pool,
-            sender: channel::<u8>(8).0,
-            volunteers_url,
-            administration_password,
+            sender: ShiftsEvents::new(),
             email,
             website,
+            uniform_login_errors,
+            session_timeouts,
+            code_sender,
+            timezone,
+            organisations: Organisations::new(organisations),
         })
-        .attach(Template::fairing())
-        .register("/", error::catchers());
+        .attach(Template::custom(|engines| {
+            engines
+                .handlebars
+                .register_helper("t", Box::new(locale::TranslateHelper));
+        }))
+        .attach(Tenancy)
+        .attach(Csrf)
+        .attach(archive::purge_job())
+        .attach(webhooks::delivery_job())
+        .register("/", error::catchers())
+        .register("/api", api::catchers());
 
     Ok(rocket.into())
 }
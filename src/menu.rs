@@ -2,80 +2,188 @@ use rocket::http::uri::Origin;
 
 use serde::Serialize;
 
+use crate::database::Organisation;
+use crate::locale::{LanguageLink, Locale};
+// Query parameters of the planning and statistics routes, needed by their uri
+use crate::planning::BoardQuery;
+use crate::statistics::StatisticsFilter;
+
 use crate::{
-    ADMINISTRATION_ROUTE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE, VISUALIZE_SHIFTS_ROUTE,
-    VOLUNTEERS_ROUTE,
+    ACCOUNT_ROUTE, ADMINISTRATION_ROUTE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE,
+    VISUALIZE_SHIFTS_ROUTE, VOLUNTEERS_ROUTE,
 };
 
 #[derive(Serialize)]
 pub(crate) struct Menu {
-    // Image path
-    image_path: &'static str,
+    // Logo of the organisation
+    image_path: String,
     // Route to index
     index_route: Origin<'static>,
     // Route to insert shifts
     insert_shifts_route: Origin<'static>,
     // Link text for route to insert shifts
-    insert_shifts_text: &'static str,
+    insert_shifts_text: String,
     // Route to view personal shifts
     personal_shifts_route: Origin<'static>,
     // Link text for view personal shifts
-    personal_shifts_text: &'static str,
+    personal_shifts_text: String,
     // Route to visualize shifts
     visualize_shifts_route: Origin<'static>,
     // Link text for visualize shifts
-    visualize_shifts_text: &'static str,
+    visualize_shifts_text: String,
+    // Route to the personal profile
+    account_route: Origin<'static>,
+    // Link text for the personal profile
+    account_text: String,
+    // Links to choose the language
+    languages: Vec<LanguageLink>,
+    // Route to logout
+    logout_route: Origin<'static>,
+    // Button text for logout
+    logout_text: String,
 }
 
 impl Menu {
-    pub(crate) fn render(card_id: i16) -> Self {
+    pub(crate) fn render(locale: Locale, organisation: &Organisation, card_id: i16) -> Self {
         Self {
-            image_path: "img/logo.png",
+            image_path: organisation.logo_path.clone(),
             index_route: uri!("/"),
             insert_shifts_route: uri!(
                 SHIFTS_MANAGER_ROUTE,
                 crate::shifts_manager::show_shifts_manager(card_id)
             ),
-            insert_shifts_text: "Inserisci turni",
+            insert_shifts_text: locale.text("menu-insert-shifts"),
             personal_shifts_route: uri!(SHIFTS_ROUTE, crate::shifts::show_shifts(card_id)),
-            personal_shifts_text: "I tuoi turni",
+            personal_shifts_text: locale.text("menu-personal-shifts"),
             visualize_shifts_route: uri!(
                 VISUALIZE_SHIFTS_ROUTE,
                 crate::visualizer::visualize_shifts
             ),
-            visualize_shifts_text: "Vedi turni",
+            visualize_shifts_text: locale.text("menu-visualize-shifts"),
+            account_route: uri!(ACCOUNT_ROUTE, crate::account::show_account(card_id)),
+            account_text: locale.text("menu-account"),
+            languages: LanguageLink::render(locale),
+            logout_route: uri!(crate::login::session::logout),
+            logout_text: locale.text("menu-logout"),
         }
     }
 }
 
 #[derive(Serialize)]
 pub(crate) struct MenuAdministration {
-    // Image path
-    image_path: &'static str,
+    // Logo of the organisation
+    image_path: String,
     // Route to index
     index_route: Origin<'static>,
     // Route to volunteers page
     volunteers_route: Origin<'static>,
     // Link text for route to volunteers
-    volunteers_text: &'static str,
+    volunteers_text: String,
+    // Route to outstanding invitations
+    invitations_route: Origin<'static>,
+    // Link text for outstanding invitations
+    invitations_text: String,
+    // Route to the data changes asked by volunteers
+    change_requests_route: Origin<'static>,
+    // Link text for the data changes asked by volunteers
+    change_requests_text: String,
+    // Route to assign shifts to more volunteers
+    assignments_route: Origin<'static>,
+    // Link text for assign shifts
+    assignments_text: String,
+    // Route to the weekly planning board
+    planning_route: Origin<'static>,
+    // Link text for the weekly planning board
+    planning_text: String,
     // Route to visualize shifts
     visualize_shifts_route: Origin<'static>,
     // Link text for visualize shifts
-    visualize_shifts_text: &'static str,
+    visualize_shifts_text: String,
+    // Route to participation statistics
+    statistics_route: Origin<'static>,
+    // Link text for participation statistics
+    statistics_text: String,
+    // Route to export shifts
+    export_route: Origin<'static>,
+    // Link text for export shifts
+    export_text: String,
+    // Route to past shifts archive
+    archive_route: Origin<'static>,
+    // Link text for past shifts archive
+    archive_text: String,
+    // Route to backup and restore
+    backup_route: Origin<'static>,
+    // Link text for backup and restore
+    backup_text: String,
+    // Route to API tokens
+    api_route: Origin<'static>,
+    // Link text for API tokens
+    api_text: String,
+    // Route to webhooks
+    webhooks_route: Origin<'static>,
+    // Link text for webhooks
+    webhooks_text: String,
+    // Route to locked logins
+    locked_logins_route: Origin<'static>,
+    // Link text for locked logins
+    locked_logins_text: String,
+    // Route to active sessions
+    sessions_route: Origin<'static>,
+    // Link text for active sessions
+    sessions_text: String,
+    // Links to choose the language
+    languages: Vec<LanguageLink>,
+    // Route to logout
+    logout_route: Origin<'static>,
+    // Button text for logout
+    logout_text: String,
 }
 
 impl MenuAdministration {
-    pub(crate) fn render() -> Self {
+    pub(crate) fn render(locale: Locale, organisation: &Organisation) -> Self {
         Self {
-            image_path: "img/logo.png",
+            image_path: organisation.logo_path.clone(),
             index_route: ADMINISTRATION_ROUTE,
             volunteers_route: VOLUNTEERS_ROUTE,
-            volunteers_text: "Volontari",
+            volunteers_text: locale.text("menu-volunteers"),
+            invitations_route: uri!(VOLUNTEERS_ROUTE, crate::login::invitation::show_invitations),
+            invitations_text: locale.text("menu-invitations"),
+            change_requests_route: uri!(
+                ADMINISTRATION_ROUTE,
+                crate::change_requests::show_change_requests
+            ),
+            change_requests_text: locale.text("menu-change-requests"),
+            assignments_route: uri!(ADMINISTRATION_ROUTE, crate::assignments::show_assignments),
+            assignments_text: locale.text("menu-assignments"),
+            planning_route: uri!(ADMINISTRATION_ROUTE, crate::planning::show_planning(_)),
+            planning_text: locale.text("menu-planning"),
             visualize_shifts_route: uri!(
                 VISUALIZE_SHIFTS_ROUTE,
                 crate::visualizer::visualize_shifts
             ),
-            visualize_shifts_text: "Vedi turni",
+            visualize_shifts_text: locale.text("menu-visualize-shifts"),
+            statistics_route: uri!(ADMINISTRATION_ROUTE, crate::statistics::show_statistics(_)),
+            statistics_text: locale.text("menu-statistics"),
+            export_route: uri!(ADMINISTRATION_ROUTE, crate::download_database::show_export),
+            export_text: locale.text("menu-export"),
+            archive_route: uri!(ADMINISTRATION_ROUTE, crate::archive::show_archive),
+            archive_text: locale.text("menu-archive"),
+            backup_route: uri!(ADMINISTRATION_ROUTE, crate::backup::show_backup),
+            backup_text: locale.text("menu-backup"),
+            api_route: uri!(ADMINISTRATION_ROUTE, crate::api::token::show_api_tokens),
+            api_text: locale.text("menu-api"),
+            webhooks_route: uri!(ADMINISTRATION_ROUTE, crate::webhooks::show_webhooks),
+            webhooks_text: locale.text("menu-webhooks"),
+            locked_logins_route: uri!(
+                ADMINISTRATION_ROUTE,
+                crate::login::attempts::show_locked_logins
+            ),
+            locked_logins_text: locale.text("menu-locked-logins"),
+            sessions_route: uri!(ADMINISTRATION_ROUTE, crate::login::session::show_sessions),
+            sessions_text: locale.text("menu-sessions"),
+            languages: LanguageLink::render(locale),
+            logout_route: uri!(crate::login::session::logout),
+            logout_text: locale.text("menu-logout"),
         }
     }
 }
@@ -0,0 +1,121 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request};
+
+use crate::database::Organisation;
+use crate::AppState;
+
+// Cookie containing the organisation chosen through a path prefix
+const ORGANISATION_COOKIE: &str = "organisation";
+
+// Organisation of a request, missing when it could not be retrieved
+struct RequestOrganisation(Option<Organisation>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r Organisation {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match &req.local_cache(|| RequestOrganisation(None)).0 {
+            Some(organisation) => Outcome::Success(organisation),
+            None => Outcome::Error((Status::InternalServerError, ())),
+        }
+    }
+}
+
+// Organisations served by the application, loaded at start so that requests
+// do not query the database
+pub(crate) struct Organisations(Vec<Organisation>);
+
+impl Organisations {
+    pub(crate) fn new(organisations: Vec<Organisation>) -> Self {
+        Self(organisations)
+    }
+
+    // Get the organisation identified by a slug, if it exists
+    fn find(&self, slug: &str) -> Option<&Organisation> {
+        self.0.iter().find(|organisation| organisation.slug == slug)
+    }
+
+    // Get the first organisation, used when a request does not choose one
+    fn first(&self) -> Option<&Organisation> {
+        self.0.first()
+    }
+}
+
+// Retrieve the organisation of a request: the one whose slug is the
+// subdomain, then the one whose slug is the first path segment, then the
+// one chosen through a previous path prefix, then the first one
+fn request_organisation(
+    req: &mut Request<'_>,
+    organisations: &Organisations,
+) -> Option<Organisation> {
+    // Subdomain, as in nord.example.org
+    let subdomain = req.host().and_then(|host| {
+        host.domain()
+            .as_str()
+            .split_once('.')
+            .map(|(subdomain, _)| subdomain.to_string())
+    });
+    if let Some(organisation) = subdomain.and_then(|subdomain| organisations.find(&subdomain)) {
+        return Some(organisation.clone());
+    }
+
+    // Path prefix, as in /nord/turni/1, which is removed before routing
+    let path = req
+        .uri()
+        .path()
+        .as_str()
+        .trim_start_matches('/')
+        .to_string();
+    let (prefix, rest) = path.split_once('/').unwrap_or((&path, ""));
+    if let Some(organisation) = organisations.find(prefix) {
+        let uri = match req.uri().query() {
+            Some(query) => format!("/{rest}?{query}"),
+            None => format!("/{rest}"),
+        };
+        if let Ok(uri) = Origin::parse_owned(uri) {
+            req.set_uri(uri);
+        }
+        // Remember the organisation, since the links of the pages do not
+        // contain the prefix. The cookie is private, so that clients cannot
+        // choose an organisation without visiting its prefix.
+        req.cookies()
+            .add_private((ORGANISATION_COOKIE, organisation.slug.clone()));
+        return Some(organisation.clone());
+    }
+
+    // Organisation chosen through a previous path prefix
+    let chosen = req
+        .cookies()
+        .get_private(ORGANISATION_COOKIE)
+        .and_then(|cookie| organisations.find(cookie.value()));
+
+    chosen.or_else(|| organisations.first()).cloned()
+}
+
+// Fairing which establishes the organisation of every request, so that
+// routes only access the data of that organisation
+pub(crate) struct Tenancy;
+
+#[rocket::async_trait]
+impl Fairing for Tenancy {
+    fn info(&self) -> Info {
+        Info {
+            name: "Organisation selection",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        let Some(state) = req.rocket().state::<AppState>() else {
+            return;
+        };
+
+        // Requests without an organisation fail inside the routes
+        let organisation = request_organisation(req, &state.organisations);
+        req.local_cache(|| RequestOrganisation(organisation));
+    }
+}
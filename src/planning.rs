@@ -0,0 +1,327 @@
+use chrono::{Datelike, NaiveDate};
+
+use rocket::http::uri::Origin;
+use rocket::http::Status;
+use rocket::response::status::Custom;
+use rocket::serde::json::Json;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::{Deserialize, Serialize};
+
+use crate::api::{api_query_error, cancel_current_shift, create_shift, ApiError, ShiftRecord};
+use crate::csrf::CsrfToken;
+use crate::data::{Locations, Tasks, WeekData};
+use crate::database::{
+    insert_db_date, query_planned_shifts, query_tasks, query_volunteers, Organisation, Shift,
+    DEFAULT_LOCATION,
+};
+use crate::download_database::DATE_FORMAT;
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::{format_weekday, Date, WORKING_DAYS};
+use crate::{AppState, ADMINISTRATION_ROUTE, VISUALIZE_SHIFTS_ROUTE};
+
+// Messages
+const HEADING_MESSAGE: &str = "planning-heading";
+const WEEK_MESSAGE: &str = "label-choose-week";
+const LOCATION_MESSAGE: &str = "label-choose-location";
+const VOLUNTEERS_MESSAGE: &str = "planning-volunteers";
+const SEARCH_MESSAGE: &str = "assignments-search";
+const EXPLAIN_MESSAGE: &str = "planning-explain";
+const REMOVE_MESSAGE: &str = "planning-remove";
+const FORBIDDEN_MESSAGE: &str = "error-not-authenticated-administrator";
+
+// Only referents can change shifts through the board
+fn check_planning_access(session: &Option<Session>, locale: Locale) -> Result<(), ApiError> {
+    session
+        .as_ref()
+        .filter(|session| session.is_administration)
+        .map(|_| ())
+        .ok_or_else(|| ApiError::forbidden(&locale.text(FORBIDDEN_MESSAGE)))
+}
+
+// Week and location requested through the query parameters
+#[derive(FromForm, Default)]
+pub(crate) struct BoardQuery {
+    // Whether the next week is shown instead of the current one
+    next: Option<bool>,
+    // Location identifier, the first location when missing
+    location: Option<i16>,
+}
+
+// Week and location shown by the board
+struct BoardView {
+    // Days of the week, from Monday to Saturday
+    dates: Vec<NaiveDate>,
+    // Location identifier
+    location: i16,
+}
+
+impl BoardView {
+    fn new(state: &AppState, locations: &Locations, next: bool, location: Option<i16>) -> Self {
+        let current_date = Date::current(state.timezone);
+        let week = if next {
+            current_date.next_week()
+        } else {
+            current_date
+        };
+        Self {
+            dates: (0..WORKING_DAYS)
+                .filter_map(|day| insert_db_date(week.day_date(day)))
+                .collect(),
+            location: location
+                .filter(|location| locations.contains(*location))
+                .or_else(|| locations.first())
+                .unwrap_or(DEFAULT_LOCATION),
+        }
+    }
+
+    // First and last day of the week
+    fn bounds(&self) -> (NaiveDate, NaiveDate) {
+        (
+            self.dates.first().copied().unwrap_or_default(),
+            self.dates.last().copied().unwrap_or_default(),
+        )
+    }
+}
+
+// Column of the board
+#[derive(Serialize)]
+struct BoardDay {
+    // Date, in the format used by requests
+    date: String,
+    // Day text
+    text: String,
+    // Whether shifts can still be changed
+    is_editable: bool,
+}
+
+// Week which can be chosen
+#[derive(Serialize)]
+struct BoardWeek {
+    // Whether it is the next week
+    next: bool,
+    // Week text
+    text: String,
+    // Whether the week is selected
+    is_selected: Option<&'static str>,
+}
+
+// Location which can be chosen
+#[derive(Serialize)]
+struct BoardLocation {
+    // Location identifier
+    id: i16,
+    // Location name
+    name: String,
+    // Whether the location is selected
+    is_selected: Option<&'static str>,
+}
+
+// Volunteer assigned to a cell of the board
+#[derive(Serialize)]
+struct BoardShift {
+    // Shift identifier, used to delete it
+    id: i32,
+    // Shift date
+    date: NaiveDate,
+    // Task identifier
+    task: i16,
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname and name
+    volunteer: String,
+}
+
+// Shift created by dragging a volunteer into a cell
+#[derive(Deserialize)]
+struct PlanningShift {
+    card_id: i16,
+    date: NaiveDate,
+    task: i16,
+    location: i16,
+}
+
+#[get("/pianificazione?<query..>")]
+pub(crate) async fn show_planning(
+    query: Option<BoardQuery>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    csrf_token: &CsrfToken,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let query = query.unwrap_or_default();
+    let next = query.next.unwrap_or_default();
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let view = BoardView::new(state, &locations, next, query.location);
+
+    // Passed days are shown but cannot be changed
+    let today = Date::current(state.timezone).naive_date();
+    let days: Vec<BoardDay> = view
+        .dates
+        .iter()
+        .map(|date| BoardDay {
+            date: date.format(DATE_FORMAT).to_string(),
+            text: format!(
+                "{} {}",
+                format_weekday(locale, date.weekday()),
+                date.format("%d/%m")
+            ),
+            is_editable: *date >= today,
+        })
+        .collect();
+
+    let current_date = Date::current(state.timezone);
+    let weeks = [
+        (false, current_date.monday()),
+        (true, current_date.next_week()),
+    ]
+    .map(|(is_next, date)| BoardWeek {
+        next: is_next,
+        text: WeekData::week_bounds(locale, &date),
+        is_selected: (is_next == next).then_some("selected"),
+    });
+
+    // The location is chosen only when there are more of them
+    let board_locations: Option<Vec<BoardLocation>> = (locations.render().len() > 1).then(|| {
+        locations
+            .render()
+            .iter()
+            .map(|location| BoardLocation {
+                id: location.id,
+                name: location.name.clone(),
+                is_selected: (location.id == view.location).then_some("selected"),
+            })
+            .collect()
+    });
+
+    // Disabled volunteers cannot book shifts, so they are not proposed
+    let volunteers: Vec<_> = query_error(query_volunteers(&state.pool, organisation.id), uri)
+        .await?
+        .into_iter()
+        .filter(|volunteer| !volunteer.volunteer.disabled)
+        .map(|volunteer| volunteer.volunteer)
+        .collect();
+
+    Ok(Template::render(
+        "planning",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            planning_route: administration_uri!(show_planning(_)),
+            week_message: locale.text(WEEK_MESSAGE),
+            weeks,
+            location_message: locale.text(LOCATION_MESSAGE),
+            locations: board_locations,
+            location: view.location,
+            days,
+            tasks: locations.tasks(view.location, &tasks),
+            volunteers_message: locale.text(VOLUNTEERS_MESSAGE),
+            search_message: locale.text(SEARCH_MESSAGE),
+            explain_message: locale.text(EXPLAIN_MESSAGE),
+            remove_message: locale.text(REMOVE_MESSAGE),
+            volunteers,
+            shifts_route: administration_uri!(planned_shifts(Some(next), Some(view.location))),
+            new_shift_route: administration_uri!(plan_shift),
+            stream_route: rocket::uri!(
+                VISUALIZE_SHIFTS_ROUTE,
+                crate::visualizer::visualize_shifts_stream(_, Some(view.location))
+            ),
+            csrf_token: csrf_token.value(),
+        },
+    ))
+}
+
+#[get("/pianificazione/turni?<next>&<location>")]
+async fn planned_shifts(
+    next: Option<bool>,
+    location: Option<i16>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Json<Vec<BoardShift>>, ApiError> {
+    check_planning_access(&session, locale)?;
+
+    let locations = api_query_error(Locations::load(&state.pool, organisation.id)).await?;
+    let view = BoardView::new(state, &locations, next.unwrap_or_default(), location);
+    let (from, to) = view.bounds();
+
+    let shifts = api_query_error(query_planned_shifts(
+        &state.pool,
+        organisation.id,
+        from,
+        to,
+        view.location,
+    ))
+    .await?;
+
+    Ok(Json(
+        shifts
+            .into_iter()
+            .map(|planned| BoardShift {
+                id: planned.shift.id,
+                date: planned.shift.shift.date,
+                task: planned.shift.shift.task,
+                card_id: planned.shift.shift.card_id,
+                volunteer: format!("{} {}", planned.surname, planned.name),
+            })
+            .collect(),
+    ))
+}
+
+#[post("/pianificazione/turni", format = "json", data = "<new_shift>")]
+async fn plan_shift(
+    new_shift: Json<PlanningShift>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Custom<Json<ShiftRecord>>, ApiError> {
+    check_planning_access(&session, locale)?;
+
+    let locations = api_query_error(Locations::load(&state.pool, organisation.id)).await?;
+    let shift = Shift {
+        date: new_shift.date,
+        task: new_shift.task,
+        card_id: new_shift.card_id,
+        location_id: new_shift.location,
+    };
+
+    // The same rules of the API are applied, and the other boards are
+    // updated through the visualizer events
+    let record = create_shift(state, organisation, &locations, &shift, locale).await?;
+
+    Ok(Custom(Status::Created, Json(record)))
+}
+
+#[delete("/pianificazione/turni/<id>")]
+async fn unplan_shift(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    locale: Locale,
+) -> Result<Status, ApiError> {
+    check_planning_access(&session, locale)?;
+
+    cancel_current_shift(state, organisation, id, locale).await?;
+
+    Ok(Status::NoContent)
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_planning, planned_shifts, plan_shift, unplan_shift]
+}
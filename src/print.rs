@@ -0,0 +1,497 @@
+use anyhow::Result;
+
+use printpdf::path::PaintMode;
+use printpdf::{
+    BuiltinFont, Color, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Rect, Rgb,
+};
+
+use crate::locale::Locale;
+
+// A4 landscape page size, in millimeters
+const PAGE_WIDTH: f32 = 297.0;
+const PAGE_HEIGHT: f32 = 210.0;
+// Page margin, in millimeters
+const MARGIN: f32 = 10.0;
+// Height of the title area, in millimeters
+const TITLE_HEIGHT: f32 = 14.0;
+// Height of the footer area, in millimeters
+const FOOTER_HEIGHT: f32 = 8.0;
+// Height of the row containing days, in millimeters
+const HEADER_HEIGHT: f32 = 10.0;
+// Width of the column containing tasks, in millimeters
+const TASK_WIDTH: f32 = 42.0;
+// Padding inside cells, in millimeters
+const PADDING: f32 = 2.0;
+
+// Font sizes, in points
+const TITLE_SIZE: f32 = 18.0;
+const HEADER_SIZE: f32 = 11.0;
+const TEXT_SIZE: f32 = 9.0;
+const FOOTER_SIZE: f32 = 8.0;
+
+// A4 portrait page size used by certificates, in millimeters
+const CERTIFICATE_WIDTH: f32 = 210.0;
+const CERTIFICATE_HEIGHT: f32 = 297.0;
+// Certificate page margin, in millimeters
+const CERTIFICATE_MARGIN: f32 = 20.0;
+// Height of a certificate table row, in millimeters
+const CERTIFICATE_ROW_HEIGHT: f32 = 7.0;
+// Width of the certificate table columns, in millimeters
+const CERTIFICATE_COLUMNS: [f32; 4] = [30.0, 70.0, 40.0, 30.0];
+// Height of the signature area, in millimeters
+const SIGNATURE_HEIGHT: f32 = 40.0;
+
+// Millimeters contained in a point
+const POINT: f32 = 0.3528;
+// Average width of an Helvetica character, relative to the font size
+const CHARACTER_WIDTH: f32 = 0.52;
+
+// Certificate table headers
+const CERTIFICATE_HEADERS: [&str; 4] = [
+    "certificate-date",
+    "certificate-task",
+    "certificate-hours",
+    "certificate-duration",
+];
+
+// Empty slot message
+const EMPTY_SLOT_MESSAGE: &str = "print-empty-slot";
+
+// Cell of the roster grid
+pub(crate) struct RosterCell {
+    // Names of the volunteers assigned to the shift
+    pub(crate) volunteers_names: Vec<String>,
+}
+
+// Task row of the roster grid
+pub(crate) struct RosterRow {
+    // Task name
+    pub(crate) task_name: String,
+    // Task hours
+    pub(crate) task_hours: String,
+    // One cell for each day
+    pub(crate) cells: Vec<RosterCell>,
+}
+
+// Weekly roster, organized as a grid of days and tasks
+pub(crate) struct Roster {
+    // Title of the page
+    pub(crate) title: String,
+    // Days, used as column headers
+    pub(crate) days: Vec<String>,
+    // Tasks, used as rows
+    pub(crate) rows: Vec<RosterRow>,
+    // Generation date and time
+    pub(crate) generated_at: String,
+    // Language of the roster
+    pub(crate) locale: Locale,
+}
+
+// Shift listed in a certificate
+pub(crate) struct CertificateShift {
+    // Shift date
+    pub(crate) date: String,
+    // Task name
+    pub(crate) task: String,
+    // Task hours
+    pub(crate) hours: String,
+    // Shift duration
+    pub(crate) duration: String,
+}
+
+// Statement of the hours served by a volunteer
+pub(crate) struct Certificate {
+    // Association name, shown as page header
+    pub(crate) association_name: String,
+    // Title of the document
+    pub(crate) title: String,
+    // Statement paragraph
+    pub(crate) statement: String,
+    // Served shifts
+    pub(crate) shifts: Vec<CertificateShift>,
+    // Total duration of the served shifts
+    pub(crate) total: String,
+    // Generation date and time
+    pub(crate) generated_at: String,
+    // Language of the certificate
+    pub(crate) locale: Locale,
+}
+
+// Colors used in the page
+fn rgb(red: f32, green: f32, blue: f32) -> Color {
+    Color::Rgb(Rgb::new(red, green, blue, None))
+}
+
+// Shorten a text which does not fit the given width
+fn fit_text(text: &str, size: f32, width: f32) -> String {
+    let max_characters = (width / (size * POINT * CHARACTER_WIDTH)) as usize;
+    if text.chars().count() <= max_characters {
+        text.to_string()
+    } else {
+        let mut text: String = text
+            .chars()
+            .take(max_characters.saturating_sub(1))
+            .collect();
+        text.push('…');
+        text
+    }
+}
+
+// Split a text into lines which fit the given width
+fn wrap_text(text: &str, size: f32, width: f32) -> Vec<String> {
+    let max_characters = (width / (size * POINT * CHARACTER_WIDTH)) as usize;
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + word.chars().count() >= max_characters {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+// Draw a cell with the given background
+fn draw_cell(layer: &PdfLayerReference, x: f32, y: f32, width: f32, height: f32, fill: Color) {
+    layer.set_fill_color(fill);
+    layer.add_rect(
+        Rect::new(Mm(x), Mm(y - height), Mm(x + width), Mm(y)).with_mode(PaintMode::FillStroke),
+    );
+    layer.set_fill_color(rgb(0.0, 0.0, 0.0));
+}
+
+// Write a line of text inside a cell
+fn write_line(
+    layer: &PdfLayerReference,
+    text: &str,
+    size: f32,
+    x: f32,
+    y: f32,
+    width: f32,
+    font: &IndirectFontRef,
+) {
+    layer.use_text(
+        fit_text(text, size, width - 2.0 * PADDING),
+        size,
+        Mm(x + PADDING),
+        Mm(y),
+        font,
+    );
+}
+
+impl Roster {
+    // Render the roster as an A4 landscape PDF
+    pub(crate) fn pdf(&self) -> Result<Vec<u8>> {
+        let (document, page, layer) =
+            PdfDocument::new(&self.title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Turni");
+        let layer = document.get_page(page).get_layer(layer);
+        let font = document.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = document.add_builtin_font(BuiltinFont::HelveticaBold)?;
+
+        layer.set_outline_color(rgb(0.4, 0.4, 0.4));
+        layer.set_outline_thickness(0.5);
+
+        // Title
+        layer.use_text(
+            &self.title,
+            TITLE_SIZE,
+            Mm(MARGIN),
+            Mm(PAGE_HEIGHT - MARGIN - TITLE_SIZE * POINT),
+            &bold,
+        );
+
+        // Grid sizes
+        let top = PAGE_HEIGHT - MARGIN - TITLE_HEIGHT;
+        let day_width = (PAGE_WIDTH - 2.0 * MARGIN - TASK_WIDTH) / self.days.len().max(1) as f32;
+        let row_height =
+            (top - HEADER_HEIGHT - MARGIN - FOOTER_HEIGHT) / self.rows.len().max(1) as f32;
+        let line_height = TEXT_SIZE * POINT * 1.4;
+        let header_baseline = top - HEADER_HEIGHT / 2.0 - HEADER_SIZE * POINT / 3.0;
+
+        // Days header
+        draw_cell(
+            &layer,
+            MARGIN,
+            top,
+            TASK_WIDTH,
+            HEADER_HEIGHT,
+            rgb(0.28, 0.78, 0.45),
+        );
+        for (index, day) in self.days.iter().enumerate() {
+            let x = MARGIN + TASK_WIDTH + index as f32 * day_width;
+            draw_cell(
+                &layer,
+                x,
+                top,
+                day_width,
+                HEADER_HEIGHT,
+                rgb(0.28, 0.78, 0.45),
+            );
+            write_line(
+                &layer,
+                day,
+                HEADER_SIZE,
+                x,
+                header_baseline,
+                day_width,
+                &bold,
+            );
+        }
+
+        // Tasks rows
+        for (row_index, row) in self.rows.iter().enumerate() {
+            let y = top - HEADER_HEIGHT - row_index as f32 * row_height;
+            let first_line = y - PADDING - TEXT_SIZE * POINT;
+
+            draw_cell(
+                &layer,
+                MARGIN,
+                y,
+                TASK_WIDTH,
+                row_height,
+                rgb(0.93, 0.93, 0.93),
+            );
+            write_line(
+                &layer,
+                &row.task_name,
+                TEXT_SIZE,
+                MARGIN,
+                first_line,
+                TASK_WIDTH,
+                &bold,
+            );
+            write_line(
+                &layer,
+                &row.task_hours,
+                TEXT_SIZE,
+                MARGIN,
+                first_line - line_height,
+                TASK_WIDTH,
+                &font,
+            );
+
+            // Number of names which fit a cell
+            let max_lines = ((row_height - 2.0 * PADDING) / line_height).max(1.0) as usize;
+
+            for (day_index, cell) in row.cells.iter().enumerate() {
+                let x = MARGIN + TASK_WIDTH + day_index as f32 * day_width;
+
+                // Highlight the slots which need a volunteer
+                if cell.volunteers_names.is_empty() {
+                    draw_cell(&layer, x, y, day_width, row_height, rgb(1.0, 0.85, 0.85));
+                    layer.set_fill_color(rgb(0.8, 0.1, 0.1));
+                    write_line(
+                        &layer,
+                        &self.locale.text(EMPTY_SLOT_MESSAGE),
+                        TEXT_SIZE,
+                        x,
+                        first_line,
+                        day_width,
+                        &font,
+                    );
+                    layer.set_fill_color(rgb(0.0, 0.0, 0.0));
+                    continue;
+                }
+
+                draw_cell(&layer, x, y, day_width, row_height, rgb(1.0, 1.0, 1.0));
+
+                // When names do not fit, the last line reports how many are missing
+                let hidden = cell.volunteers_names.len().saturating_sub(max_lines);
+                let visible = if hidden > 0 { max_lines - 1 } else { max_lines };
+                for (line, name) in cell.volunteers_names.iter().take(visible).enumerate() {
+                    let baseline = first_line - line as f32 * line_height;
+                    write_line(&layer, name, TEXT_SIZE, x, baseline, day_width, &font);
+                }
+                if hidden > 0 {
+                    let baseline = first_line - visible as f32 * line_height;
+                    let others = self
+                        .locale
+                        .format("print-others", &[("count", (hidden + 1).into())]);
+                    write_line(&layer, &others, TEXT_SIZE, x, baseline, day_width, &bold);
+                }
+            }
+        }
+
+        // Generation date and time
+        layer.use_text(
+            self.locale.format(
+                "print-generated-at",
+                &[("date", self.generated_at.as_str().into())],
+            ),
+            FOOTER_SIZE,
+            Mm(MARGIN),
+            Mm(MARGIN),
+            &font,
+        );
+
+        Ok(document.save_to_bytes()?)
+    }
+}
+
+// Layout of a certificate page
+struct CertificatePage<'a> {
+    document: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    font: &'a IndirectFontRef,
+    bold: &'a IndirectFontRef,
+    // Table headers, repeated on each page
+    headers: [String; 4],
+    // Footer containing the generation date and time
+    footer: String,
+    // Vertical position of the next element
+    y: f32,
+}
+
+impl CertificatePage<'_> {
+    // Prepare the current layer for drawing
+    fn setup(&mut self) {
+        self.layer.set_outline_color(rgb(0.4, 0.4, 0.4));
+        self.layer.set_outline_thickness(0.5);
+        self.layer.use_text(
+            &self.footer,
+            FOOTER_SIZE,
+            Mm(CERTIFICATE_MARGIN),
+            Mm(CERTIFICATE_MARGIN / 2.0),
+            self.font,
+        );
+        self.y = CERTIFICATE_HEIGHT - CERTIFICATE_MARGIN;
+    }
+
+    // Continue on a new page
+    fn new_page(&mut self) {
+        let (page, layer) =
+            self.document
+                .add_page(Mm(CERTIFICATE_WIDTH), Mm(CERTIFICATE_HEIGHT), "Attestato");
+        self.layer = self.document.get_page(page).get_layer(layer);
+        self.setup();
+    }
+
+    // Write a line of text and move to the next one
+    fn line(&mut self, text: &str, size: f32, font: &IndirectFontRef) {
+        self.y -= size * POINT * 1.5;
+        self.layer
+            .use_text(text, size, Mm(CERTIFICATE_MARGIN), Mm(self.y), font);
+    }
+
+    // Write a table row, moving to a new page when it does not fit
+    fn row(&mut self, cells: [&str; 4], is_header: bool) {
+        if self.y - CERTIFICATE_ROW_HEIGHT < CERTIFICATE_MARGIN {
+            self.new_page();
+            if !is_header {
+                self.headers_row();
+            }
+        }
+        let (fill, font) = if is_header {
+            (rgb(0.28, 0.78, 0.45), self.bold)
+        } else {
+            (rgb(1.0, 1.0, 1.0), self.font)
+        };
+        let baseline = self.y - CERTIFICATE_ROW_HEIGHT / 2.0 - TEXT_SIZE * POINT / 3.0;
+        let mut x = CERTIFICATE_MARGIN;
+        for (cell, width) in cells.iter().zip(CERTIFICATE_COLUMNS) {
+            draw_cell(
+                &self.layer,
+                x,
+                self.y,
+                width,
+                CERTIFICATE_ROW_HEIGHT,
+                fill.clone(),
+            );
+            write_line(&self.layer, cell, TEXT_SIZE, x, baseline, width, font);
+            x += width;
+        }
+        self.y -= CERTIFICATE_ROW_HEIGHT;
+    }
+
+    // Write the table headers
+    fn headers_row(&mut self) {
+        let headers = self.headers.clone();
+        self.row(headers.each_ref().map(String::as_str), true);
+    }
+}
+
+impl Certificate {
+    // Render the certificate as an A4 portrait PDF
+    pub(crate) fn pdf(&self) -> Result<Vec<u8>> {
+        let (document, page, layer) = PdfDocument::new(
+            &self.title,
+            Mm(CERTIFICATE_WIDTH),
+            Mm(CERTIFICATE_HEIGHT),
+            "Attestato",
+        );
+        let font = document.add_builtin_font(BuiltinFont::Helvetica)?;
+        let bold = document.add_builtin_font(BuiltinFont::HelveticaBold)?;
+        let text_width = CERTIFICATE_WIDTH - 2.0 * CERTIFICATE_MARGIN;
+
+        let mut page = CertificatePage {
+            document: &document,
+            layer: document.get_page(page).get_layer(layer),
+            font: &font,
+            bold: &bold,
+            headers: CERTIFICATE_HEADERS.map(|header| self.locale.text(header)),
+            footer: self.locale.format(
+                "print-generated-at",
+                &[("date", self.generated_at.as_str().into())],
+            ),
+            y: 0.0,
+        };
+        page.setup();
+
+        // Association and title
+        page.line(&self.association_name, HEADER_SIZE, &bold);
+        page.y -= TITLE_HEIGHT / 2.0;
+        page.line(&self.title, TITLE_SIZE, &bold);
+        page.y -= TITLE_HEIGHT / 2.0;
+
+        // Statement
+        for line in wrap_text(&self.statement, HEADER_SIZE, text_width) {
+            page.line(&line, HEADER_SIZE, &font);
+        }
+        page.y -= HEADER_HEIGHT;
+
+        // Served shifts
+        page.headers_row();
+        for shift in &self.shifts {
+            page.row(
+                [&shift.date, &shift.task, &shift.hours, &shift.duration],
+                false,
+            );
+        }
+        page.row(
+            [&self.locale.text("certificate-total"), "", "", &self.total],
+            true,
+        );
+
+        // Signature
+        if page.y - SIGNATURE_HEIGHT < CERTIFICATE_MARGIN {
+            page.new_page();
+        }
+        page.y -= SIGNATURE_HEIGHT / 2.0;
+        page.line(
+            &format!(
+                "{} ____________________",
+                self.locale.text("certificate-place")
+            ),
+            HEADER_SIZE,
+            &font,
+        );
+        page.y -= HEADER_HEIGHT;
+        page.line(
+            &format!(
+                "{} ____________________",
+                self.locale.text("certificate-signature")
+            ),
+            HEADER_SIZE,
+            &font,
+        );
+
+        Ok(document.save_to_bytes()?)
+    }
+}
@@ -1,29 +1,33 @@
-use std::borrow::Cow;
-
 use rocket::http::uri::Origin;
-use rocket::http::{Cookie, CookieJar};
+use rocket::request::FlashMessage;
 use rocket::response::Redirect;
 use rocket::State;
 use rocket_dyn_templates::{context, Template};
 
 use serde::Serialize;
 
-use crate::data::Tasks;
+use crate::api::ShiftRecord;
+use crate::data::{Locations, Tasks};
 use crate::database::{
-    delete_shift, format_db_date, query_is_disabled, query_shifts, query_volunteer_surname_name,
-    ShiftQuery,
+    delete_shift, query_is_disabled, query_shifts, query_tasks, query_volunteer_surname_name,
+    Organisation, ShiftQuery,
 };
 use crate::error::{query_error, InternalError};
-use crate::login::{ADMINISTRATION_COOKIE, AUTHENTICATION_COOKIE};
+use crate::locale::Locale;
+use crate::login::session::{check_volunteer_access, Session};
 use crate::menu::{Menu, MenuAdministration};
 use crate::shifts_manager::shifts_manager_uri;
-use crate::{AppState, APP_TITLE, DISABLED_MESSAGE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE};
+use crate::time::{format_date, Date};
+use crate::webhooks::{send_event, WebhookEvent};
+use crate::{AppState, DISABLED_MESSAGE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE};
 
 // Messages
-const HEADING_MESSAGE: &str = "Ecco i tuoi turni";
-const EMPTY_SHIFTS_MESSAGE: &str = "Nessun turno inserito!";
-const INSERT_SHIFTS_TEXT: &str = "Inserisci nuovi turni";
-const DELETE_MESSAGE: &str = "Cancella";
+const HEADING_MESSAGE: &str = "shifts-heading";
+const EMPTY_SHIFTS_MESSAGE: &str = "shifts-empty";
+const INSERT_SHIFTS_TEXT: &str = "shifts-insert";
+const DELETE_MESSAGE: &str = "shifts-delete";
+const CERTIFICATE_MESSAGE: &str = "shifts-certificate";
+const REJECTED_MESSAGE: &str = "shifts-rejected";
 
 // Route to shifts page
 macro_rules! shifts_uri {
@@ -38,21 +42,33 @@ struct VolunteerShift {
     // Date
     date: String,
     // Task
-    task: &'static str,
+    task: String,
     // Hours
-    hours: &'static str,
+    hours: String,
+    // Location name
+    location: String,
+    // Location address
+    address: String,
     // Route to delete the shift
     delete_route: Origin<'static>,
 }
 
 impl VolunteerShift {
-    fn shifts(shifts: Vec<ShiftQuery>, card_id: i16) -> Vec<Self> {
+    fn shifts(
+        locale: Locale,
+        tasks: &Tasks,
+        locations: &Locations,
+        shifts: Vec<ShiftQuery>,
+        card_id: i16,
+    ) -> Vec<Self> {
         shifts
             .into_iter()
             .map(|shift| VolunteerShift {
-                date: format_db_date(&shift.shift.date),
-                task: Tasks::task_from_id(shift.shift.task as usize),
-                hours: Tasks::hours_from_id(shift.shift.task as usize),
+                date: format_date(locale, &shift.shift.date),
+                task: tasks.task_from_id(shift.shift.task),
+                hours: tasks.hours_from_id(shift.shift.task),
+                location: locations.name_from_id(shift.shift.location_id),
+                address: locations.address_from_id(shift.shift.location_id),
                 delete_route: uri!(remove_shift(card_id, shift.id)),
             })
             .collect()
@@ -63,14 +79,44 @@ impl VolunteerShift {
 pub(crate) async fn remove_shift(
     card_id: i16,
     shift_id: i32,
+    session: Option<Session>,
     state: &State<AppState>,
+    organisation: &Organisation,
     uri: &Origin<'_>,
 ) -> Result<Redirect, InternalError> {
-    // Delete a shift using its identifier
-    query_error(delete_shift(&state.pool, shift_id), uri).await?;
+    // Check if the user is authenticated
+    check_volunteer_access(&session, card_id, uri)?;
+
+    // Tasks and locations are retrieved before the shift is deleted, so that
+    // the request does not fail afterwards
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
 
-    // Send an event to refresh inserted shifts
-    let _res = state.sender.send(1);
+    // Delete a shift using its identifier
+    let deleted = query_error(
+        delete_shift(&state.pool, organisation.id, shift_id, card_id),
+        uri,
+    )
+    .await?;
+
+    // Notify webhooks and visualizer pages about the deleted shift
+    if let Some(shift) = deleted {
+        send_event(
+            &state.pool,
+            organisation.id,
+            &WebhookEvent::ShiftDeleted(ShiftRecord::new(&tasks, &locations, shift_id, &shift)),
+        )
+        .await;
+
+        // The shift is already deleted, so failures are only logged
+        if let Err(e) = state
+            .sender
+            .shift_removed(&state.pool, organisation.id, &shift)
+            .await
+        {
+            error!("Error notifying visualizer pages: {e}");
+        }
+    }
 
     // Redirect to personal shifts page
     Ok(Redirect::to(shifts_uri!(show_shifts(card_id))))
@@ -78,22 +124,30 @@ pub(crate) async fn remove_shift(
 
 #[inline(always)]
 fn render_shifts_template(
+    locale: Locale,
+    organisation: &Organisation,
     id: i16,
     is_administration: bool,
     heading_message: &str,
     no_shifts_message: &str,
+    rejected_shifts: &[&str],
 ) -> Template {
     Template::render(
         "shifts",
         context! {
-           title: APP_TITLE,
+           lang: locale.code(),
+           title: &organisation.name,
            is_administration,
-           menu: Menu::render(id),
-           menu_administration: MenuAdministration::render(),
+           menu: Menu::render(locale, organisation, id),
+           menu_administration: MenuAdministration::render(locale, organisation),
            heading_message,
-           no_shifts_message: Some(no_shifts_message),
+           no_shifts_message: Some(locale.text(no_shifts_message)),
+           rejected_message: locale.text(REJECTED_MESSAGE),
+           rejected_shifts,
            insert_shifts_link: shifts_manager_uri!(crate::shifts_manager::show_shifts_manager(id)),
-           insert_shifts_text: INSERT_SHIFTS_TEXT,
+           insert_shifts_text: locale.text(INSERT_SHIFTS_TEXT),
+           certificate_link: shifts_uri!(crate::certificate::show_certificate(id)),
+           certificate_text: locale.text(CERTIFICATE_MESSAGE),
         },
     )
 }
@@ -101,69 +155,88 @@ fn render_shifts_template(
 #[get("/?<id>")]
 pub(crate) async fn show_shifts(
     id: i16,
+    session: Option<Session>,
     state: &State<AppState>,
-    jar: &CookieJar<'_>,
+    organisation: &Organisation,
     uri: &Origin<'_>,
+    locale: Locale,
+    flash: Option<FlashMessage<'_>>,
 ) -> Result<Template, InternalError> {
-    // Check if the administrator is authenticated
-    let (is_administration, heading_message) = if jar.get_private(ADMINISTRATION_COOKIE).is_none() {
-        // Check if the user is authenticated
-        match jar
-            .get_private(AUTHENTICATION_COOKIE)
-            .as_ref()
-            .map(Cookie::value)
-        {
-            Some(cookie_card_id) if cookie_card_id != id.to_string() => {
-                return Err(InternalError::wrong_card_id(uri))
-            }
-            Some(_) => (false, Cow::Borrowed(HEADING_MESSAGE)),
-            None => return Err(InternalError::not_authenticated(uri)),
-        }
+    // Check if the user or the administrator is authenticated
+    let is_administration = check_volunteer_access(&session, id, uri)?;
+
+    // Shifts rejected while booking, one for each line
+    let rejected_shifts: Vec<&str> = flash
+        .as_ref()
+        .filter(|flash| flash.kind() == "warning")
+        .map(|flash| flash.message().lines().collect())
+        .unwrap_or_default();
+    let heading_message = if is_administration {
+        let surname_name = query_error(
+            query_volunteer_surname_name(&state.pool, organisation.id, id),
+            uri,
+        )
+        .await?;
+        format!("({id}) {surname_name}")
     } else {
-        let surname_name = query_error(query_volunteer_surname_name(&state.pool, id), uri).await?;
-        (true, Cow::Owned(format!("({id}) {surname_name}")))
+        locale.text(HEADING_MESSAGE)
     };
 
     // Check whether the volunteer is disabled
-    let volunteer_is_disabled = query_error(query_is_disabled(&state.pool, id), uri).await?;
+    let volunteer_is_disabled =
+        query_error(query_is_disabled(&state.pool, organisation.id, id), uri).await?;
 
     // If volunteer is disabled, show the message
     if volunteer_is_disabled {
         return Ok(render_shifts_template(
+            locale,
+            organisation,
             id,
             is_administration,
             &heading_message,
             DISABLED_MESSAGE,
+            &rejected_shifts,
         ));
     }
 
     // Get shifts for a volunteer
-    let shifts = query_error(query_shifts(&state.pool, id), uri).await?;
+    let today = Date::current(state.timezone).naive_date();
+    let shifts = query_error(query_shifts(&state.pool, organisation.id, id, today), uri).await?;
 
     // No shifts for the current volunteer
     if shifts.is_empty() {
         return Ok(render_shifts_template(
+            locale,
+            organisation,
             id,
             is_administration,
             &heading_message,
             EMPTY_SHIFTS_MESSAGE,
+            &rejected_shifts,
         ));
     }
 
-    let shifts = VolunteerShift::shifts(shifts, id);
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let shifts = VolunteerShift::shifts(locale, &tasks, &locations, shifts, id);
 
     Ok(Template::render(
         "shifts",
         context! {
-           title: APP_TITLE,
+           lang: locale.code(),
+           title: &organisation.name,
            is_administration,
-           menu: Menu::render(id),
-           menu_administration: MenuAdministration::render(),
+           menu: Menu::render(locale, organisation, id),
+           menu_administration: MenuAdministration::render(locale, organisation),
            heading_message,
+           rejected_message: locale.text(REJECTED_MESSAGE),
+           rejected_shifts,
            shifts,
-           delete_message: DELETE_MESSAGE,
+           delete_message: locale.text(DELETE_MESSAGE),
            insert_shifts_link: shifts_manager_uri!(crate::shifts_manager::show_shifts_manager(id)),
-           insert_shifts_text: INSERT_SHIFTS_TEXT,
+           insert_shifts_text: locale.text(INSERT_SHIFTS_TEXT),
+           certificate_link: shifts_uri!(crate::certificate::show_certificate(id)),
+           certificate_text: locale.text(CERTIFICATE_MESSAGE),
         },
     ))
 }
@@ -1,24 +1,32 @@
+use chrono_tz::Tz;
+
 use rocket::form::Form;
 use rocket::http::uri::Origin;
 use rocket::http::{Cookie, CookieJar};
-use rocket::response::Redirect;
+use rocket::response::{Flash, Redirect};
 use rocket::State;
 use rocket_dyn_templates::{context, Template};
 
 use serde::Serialize;
 
-use crate::data::{Button, ShiftLabels, ShiftsData, Tasks, WeekData, FAKE_DAY_VALUE};
+use crate::api::ShiftRecord;
+use crate::data::{
+    book_shifts, Button, Locations, ShiftLabels, ShiftsData, Tasks, WeekData, FAKE_DAY_VALUE,
+};
 use crate::database::{
-    fill_shifts_table, query_current_shifts, query_volunteer_name, query_volunteer_surname_name,
+    query_is_disabled, query_tasks, query_volunteer_name, query_volunteer_surname_name, Location,
+    Organisation, ShiftQuery, Task,
 };
 use crate::error::{query_error, InternalError};
-use crate::login::{ADMINISTRATION_COOKIE, AUTHENTICATION_COOKIE};
+use crate::locale::Locale;
+use crate::login::session::{check_volunteer_access, Session};
 use crate::menu::{Menu, MenuAdministration};
 use crate::shifts::shifts_uri;
-use crate::time::Date;
-use crate::{AppState, APP_TITLE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE};
+use crate::time::{format_date, Date};
+use crate::webhooks::{send_event, WebhookEvent};
+use crate::{AppState, DISABLED_MESSAGE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE};
 
-const HEADING_MESSAGE: &str = "Inserisci uno o più turni";
+const HEADING_MESSAGE: &str = "shifts-manager-heading";
 
 // Cookie keys
 const SHIFT_NUMBERS: &str = "shift";
@@ -60,7 +68,7 @@ impl Routes {
 
 // Shifts data
 #[derive(Serialize)]
-struct Shifts {
+struct Shifts<'a> {
     // Routes for shift
     routes: Routes,
     // Shift title
@@ -70,22 +78,31 @@ struct Shifts {
     // Fake value
     fake_value: u32,
     // Dates explanation
-    explain_dates: &'static str,
+    explain_dates: String,
     // First week date
     first_week_date: WeekData,
     // Second week date
     second_week_date: WeekData,
     // Tasks explanation
-    explain_tasks: &'static str,
+    explain_tasks: String,
     // Tasks
-    tasks: &'static [Tasks],
+    tasks: &'a [Task],
+    // Locations
+    locations: &'a [Location],
     // Button text
     button: Button,
 }
 
-impl Shifts {
-    fn render(card_id: i16, jar: &CookieJar<'_>) -> Vec<Self> {
-        let current_date = Date::current();
+impl<'a> Shifts<'a> {
+    fn render(
+        locale: Locale,
+        timezone: Tz,
+        tasks: &'a Tasks,
+        locations: &'a Locations,
+        card_id: i16,
+        jar: &CookieJar<'_>,
+    ) -> Vec<Self> {
+        let current_date = Date::current(timezone);
         if let Some(shifts_number) = jar
             .get_private(SHIFT_NUMBERS)
             .as_ref()
@@ -94,33 +111,58 @@ impl Shifts {
         {
             (1..shifts_number + 1)
                 .map(|shift| {
-                    Self::fill_shift(card_id, shift, shift == shifts_number, &current_date)
+                    Self::fill_shift(
+                        locale,
+                        tasks,
+                        locations,
+                        card_id,
+                        shift,
+                        shift == shifts_number,
+                        &current_date,
+                    )
                 })
                 .collect()
         } else {
-            vec![Self::fill_shift(card_id, 1, true, &current_date)]
+            vec![Self::fill_shift(
+                locale,
+                tasks,
+                locations,
+                card_id,
+                1,
+                true,
+                &current_date,
+            )]
         }
     }
 
     #[inline]
-    fn fill_shift(card_id: i16, shift: u16, is_last_shift: bool, date: &Date) -> Self {
+    fn fill_shift(
+        locale: Locale,
+        tasks: &'a Tasks,
+        locations: &'a Locations,
+        card_id: i16,
+        shift: u16,
+        is_last_shift: bool,
+        date: &Date,
+    ) -> Self {
         Self {
             routes: Routes::render(card_id, shift, is_last_shift),
-            shift_title: Self::shift_title(shift),
+            shift_title: Self::shift_title(locale, shift),
             shift_id: Self::shift_id(shift),
             fake_value: FAKE_DAY_VALUE,
-            explain_dates: "Inserisci data...",
-            first_week_date: WeekData::week_info(date),
-            second_week_date: WeekData::week_info(&date.next_week().monday()),
-            explain_tasks: "Inserisci mansione...",
-            tasks: Tasks::render(),
-            button: Button::add_shift(),
+            explain_dates: locale.text("shifts-manager-explain-dates"),
+            first_week_date: WeekData::week_info(locale, date),
+            second_week_date: WeekData::week_info(locale, &date.next_week().monday()),
+            explain_tasks: locale.text("shifts-manager-explain-tasks"),
+            tasks: tasks.render(),
+            locations: locations.render(),
+            button: Button::add_shift(locale),
         }
     }
 
     #[inline(always)]
-    fn shift_title(shift_number: u16) -> String {
-        format!("Turno {shift_number}")
+    fn shift_title(locale: Locale, shift_number: u16) -> String {
+        locale.format("shifts-manager-shift", &[("number", shift_number.into())])
     }
 
     #[inline(always)]
@@ -136,73 +178,73 @@ impl Shifts {
 }
 
 #[put("/add/<card_id>/<shift_id>")]
-pub(crate) async fn add_shift(card_id: i16, shift_id: u16, jar: &CookieJar<'_>) -> Redirect {
+pub(crate) async fn add_shift(
+    card_id: i16,
+    shift_id: u16,
+    session: Option<Session>,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the user is authenticated
+    check_volunteer_access(&session, card_id, uri)?;
     // Increment current shift number and save it as cookie
     jar.add_private((SHIFT_NUMBERS, (shift_id + 1).to_string()));
     // Redirect to shifts manager page
-    Redirect::to(shifts_manager_uri!(show_shifts_manager(card_id)))
+    Ok(Redirect::to(shifts_manager_uri!(show_shifts_manager(
+        card_id
+    ))))
 }
 
 #[get("/?<id>")]
 pub(crate) async fn show_shifts_manager(
     id: i16,
+    session: Option<Session>,
     state: &State<AppState>,
+    organisation: &Organisation,
     jar: &CookieJar<'_>,
     uri: &Origin<'_>,
+    locale: Locale,
 ) -> Result<Template, InternalError> {
-    // Check if the administrator is authenticated
-    let is_administration = if jar.get_private(ADMINISTRATION_COOKIE).is_none() {
-        // Check if the user is authenticated
-        match jar
-            .get_private(AUTHENTICATION_COOKIE)
-            .as_ref()
-            .map(Cookie::value)
-        {
-            Some(cookie_card_id) if cookie_card_id != id.to_string() => {
-                return Err(InternalError::wrong_card_id(uri))
-            }
-            Some(_) => false,
-            None => return Err(InternalError::not_authenticated(uri)),
-        }
-    } else {
-        true
-    };
+    // Check if the user or the administrator is authenticated
+    let is_administration = check_volunteer_access(&session, id, uri)?;
 
     // Clear sessionStorage
-    let clear_session_storage = jar
-        .get(DATA_KEY)
-        .map(Cookie::value)
-        .map_or(false, |value| value == "1");
+    let clear_session_storage = jar.get_private(DATA_KEY).as_ref().map(Cookie::value) == Some("1");
 
     // Set cookie to allow new data insertion
-    jar.add((DATA_KEY, "0"));
+    jar.add_private((DATA_KEY, "0"));
 
     // Shift Labels
-    let shift_labels = ShiftLabels::render();
+    let shift_labels = ShiftLabels::render(locale);
 
     // Create shifts
-    let shifts = Shifts::render(id, jar);
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let shifts = Shifts::render(locale, state.timezone, &tasks, &locations, id, jar);
 
     // Button text
-    let button = Button::shifts_buttons();
+    let button = Button::shifts_buttons(locale);
 
     Ok(Template::render(
         "shifts_manager",
         context! {
-            title: APP_TITLE,
+            lang: locale.code(),
+            title: &organisation.name,
             is_administration,
-            menu: Menu::render(id),
-            menu_administration: MenuAdministration::render(),
+            menu: Menu::render(locale, organisation, id),
+            menu_administration: MenuAdministration::render(locale, organisation),
             route: SHIFTS_MANAGER_ROUTE,
             heading_message: if is_administration {
-                let surname_name = query_error(query_volunteer_surname_name(&state.pool, id), uri).await?;
+                let surname_name = query_error(query_volunteer_surname_name(&state.pool, organisation.id, id), uri).await?;
                 format!("({id}) {surname_name}")
             } else {
-                let volunteer_name = query_error(query_volunteer_name(&state.pool, id), uri).await?;
-                format!("Ciao {}!", volunteer_name)
+                let volunteer_name = query_error(query_volunteer_name(&state.pool, organisation.id, id), uri).await?;
+                locale.format("shifts-manager-greeting", &[("name", volunteer_name.into())])
             },
-            guide_message: HEADING_MESSAGE,
+            guide_message: locale.text(HEADING_MESSAGE),
             shift_labels,
+            // The location is chosen only when there are more of them
+            choose_location: locations.render().len() > 1,
             shifts,
             id,
             button,
@@ -214,47 +256,111 @@ pub(crate) async fn show_shifts_manager(
 #[post("/", data = "<shifts_form>")]
 async fn check_shifts_data(
     shifts_form: Form<ShiftsData>,
+    session: Option<Session>,
     state: &State<AppState>,
+    organisation: &Organisation,
     jar: &CookieJar<'_>,
     uri: &Origin<'_>,
-) -> Result<Redirect, InternalError> {
+    locale: Locale,
+) -> Result<Flash<Redirect>, InternalError> {
     // Retrieve form data
     let data = shifts_form.into_inner();
 
+    // Check if the user is authenticated
+    check_volunteer_access(&session, data.card_id, uri)?;
+
     // If the same data is inserted again without refreshing the page,
     // redirect to success page
-    if jar
-        .get(DATA_KEY)
-        .map(Cookie::value)
-        .map_or(false, |data| data == "1")
-    {
-        return Ok(Redirect::to(shifts_uri!(crate::shifts::show_shifts(
-            data.card_id
-        ))));
+    if jar.get_private(DATA_KEY).as_ref().map(Cookie::value) == Some("1") {
+        return Ok(Flash::success(
+            Redirect::to(shifts_uri!(crate::shifts::show_shifts(data.card_id))),
+            "",
+        ));
     }
 
-    // Retrieve every volunteer shifts to avoid producing duplicates
-    let all_shifts = query_error(query_current_shifts(&state.pool, data.card_id), uri).await?;
+    // Disabled volunteers cannot book shifts
+    if query_error(
+        query_is_disabled(&state.pool, organisation.id, data.card_id),
+        uri,
+    )
+    .await?
+    {
+        return Err(InternalError::text(uri, DISABLED_MESSAGE));
+    }
 
     // Create new shifts
-    let shifts = data.create_shifts(all_shifts);
+    let current_date = Date::current(state.timezone);
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let shifts = data.create_shifts(&current_date);
+
+    // Insert all shifts inside a transaction, rejected shifts are skipped
+    let mut tx = query_error(state.pool.begin(), uri).await?;
+    let results = query_error(
+        book_shifts(
+            &mut tx,
+            organisation.id,
+            &shifts,
+            &tasks,
+            &locations,
+            &current_date,
+        ),
+        uri,
+    )
+    .await?;
+    query_error(tx.commit(), uri).await?;
+    let mut inserted = Vec::new();
+    let mut rejected = Vec::new();
+    for (shift, result) in shifts.into_iter().zip(results) {
+        match result {
+            Ok(id) => inserted.push(ShiftQuery { id, shift }),
+            // Each rejected shift is reported in the shifts page
+            Err(rejection) => rejected.push(format!(
+                "{} {}: {}",
+                format_date(locale, &shift.date),
+                tasks.task_from_id(shift.task),
+                rejection.message(locale)
+            )),
+        }
+    }
 
-    // Insert all shifts
-    query_error(fill_shifts_table(&state.pool, shifts), uri).await?;
+    // Notify webhooks and visualizer pages about the new shifts
+    for inserted in inserted {
+        send_event(
+            &state.pool,
+            organisation.id,
+            &WebhookEvent::ShiftCreated(ShiftRecord::new(
+                &tasks,
+                &locations,
+                inserted.id,
+                &inserted.shift,
+            )),
+        )
+        .await;
+
+        // The shift is already saved, so failures are only logged
+        if let Err(e) = state
+            .sender
+            .shift_added(&state.pool, organisation.id, &inserted.shift)
+            .await
+        {
+            error!("Error notifying visualizer pages: {e}");
+        }
+    }
 
     // Set cookie to notify that data has been inserted correctly
-    jar.add((DATA_KEY, "1"));
-
-    // Send an event to refresh inserted shifts
-    let _res = state.sender.send(1);
+    jar.add_private((DATA_KEY, "1"));
 
     // Reset cookies
     Shifts::reset_cookie(jar);
 
-    // If everything is correct, redirect to personal shifts page
-    Ok(Redirect::to(shifts_uri!(crate::shifts::show_shifts(
-        data.card_id
-    ))))
+    // Redirect to personal shifts page, together with the rejected shifts
+    let redirect = Redirect::to(shifts_uri!(crate::shifts::show_shifts(data.card_id)));
+    if rejected.is_empty() {
+        Ok(Flash::success(redirect, ""))
+    } else {
+        Ok(Flash::warning(redirect, rejected.join("\n")))
+    }
 }
 
 pub(crate) fn routes() -> Vec<rocket::Route> {
@@ -0,0 +1,495 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use anyhow::Result;
+
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use chrono_tz::Tz;
+
+use rocket::http::uri::Origin;
+use rocket::http::ContentType;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::data::Tasks;
+use crate::database::{
+    query_exported_shifts, query_last_shifts, query_tasks, ExportedShift, LastShift, Organisation,
+};
+use crate::download_database::{
+    file_name, month_bounds, parse_date, ExportFile, DATE_FORMAT, INVALID_DATE_MESSAGE,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::{format_month, Date};
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+// Default number of weeks without shifts after which a volunteer is inactive
+const INACTIVE_WEEKS: u32 = 4;
+// Months shown when no date range is chosen
+const DEFAULT_MONTHS: u32 = 12;
+
+// Format of dates shown in the page
+const SHOWN_DATE_FORMAT: &str = "%d/%m/%Y";
+
+// Messages
+const HEADING_MESSAGE: &str = "statistics-heading";
+const FROM_MESSAGE: &str = "period-from";
+const TO_MESSAGE: &str = "period-to";
+const WEEKS_MESSAGE: &str = "statistics-weeks";
+const FILTER_MESSAGE: &str = "statistics-filter";
+const VOLUNTEERS_MESSAGE: &str = "statistics-volunteers";
+const TASKS_MESSAGE: &str = "statistics-tasks";
+const INACTIVE_MESSAGE: &str = "statistics-inactive";
+const DOWNLOAD_MESSAGE: &str = "statistics-download";
+const EMPTY_SHIFTS_MESSAGE: &str = "statistics-empty-shifts";
+const EMPTY_INACTIVE_MESSAGE: &str = "statistics-empty-inactive";
+const NEVER_MESSAGE: &str = "statistics-never";
+const DATES_ERROR: &str = "error-dates";
+
+// Columns of the tables, also used in CSV files
+const CARD_ID_COLUMN: &str = "statistics-card-id";
+const SURNAME_COLUMN: &str = "statistics-surname";
+const NAME_COLUMN: &str = "statistics-name";
+const SHIFTS_COLUMN: &str = "statistics-shifts";
+const HOURS_COLUMN: &str = "statistics-hours";
+const LAST_SHIFT_COLUMN: &str = "statistics-last-shift";
+const TASK_COLUMN: &str = "statistics-task";
+const TASK_HOURS_COLUMN: &str = "statistics-task-hours";
+const COVERED_DAYS_COLUMN: &str = "statistics-covered-days";
+const FILL_RATE_COLUMN: &str = "statistics-fill-rate";
+const AVERAGE_COLUMN: &str = "statistics-average";
+
+// Table which can be downloaded
+#[derive(FromFormField, Default)]
+enum StatisticsTable {
+    #[default]
+    Volunteers,
+    Tasks,
+    Inactive,
+}
+
+// Participation of a volunteer in the chosen date range
+#[derive(Serialize)]
+struct VolunteerStatistics {
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname
+    surname: String,
+    // Volunteer name
+    name: String,
+    // Shifts served in each month
+    months: Vec<u32>,
+    // Total number of shifts
+    shifts: u32,
+    // Hours contributed
+    hours: String,
+    // Date of the last shift
+    last_shift: String,
+    // Minutes contributed, used to compute hours
+    #[serde(skip)]
+    minutes: u32,
+}
+
+// Coverage of a task in the chosen date range
+#[derive(Serialize)]
+struct TaskStatistics {
+    // Task name
+    task_name: String,
+    // Task hours
+    task_hours: String,
+    // Total number of shifts
+    shifts: u32,
+    // Number of days with at least one volunteer
+    covered_days: u32,
+    // Percentage of days with at least one volunteer
+    fill_rate: u32,
+    // Average number of volunteers per day
+    average: String,
+}
+
+// Volunteer without shifts for the chosen number of weeks
+#[derive(Serialize)]
+struct InactiveVolunteer {
+    // Card identifier
+    card_id: i16,
+    // Volunteer surname
+    surname: String,
+    // Volunteer name
+    name: String,
+    // Date of the last shift
+    last_shift: String,
+}
+
+// Statistics computed from the shifts history
+struct Statistics {
+    // Months contained in the date range
+    months: Vec<String>,
+    // Volunteers participation, most active first
+    volunteers: Vec<VolunteerStatistics>,
+    // Tasks coverage
+    tasks: Vec<TaskStatistics>,
+    // Inactive volunteers, least recently active first
+    inactive: Vec<InactiveVolunteer>,
+}
+
+// Format an optional date, a missing date means never
+fn format_last_shift(locale: Locale, date: Option<NaiveDate>) -> String {
+    date.map_or(locale.text(NEVER_MESSAGE), |date| {
+        date.format(SHOWN_DATE_FORMAT).to_string()
+    })
+}
+
+// First days of the months contained in a date range
+fn months(from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    let mut months = Vec::new();
+    let mut month = from.with_day(1);
+    while let Some(first) = month.filter(|first| *first <= to) {
+        months.push(first);
+        month = first.checked_add_months(Months::new(1));
+    }
+    months
+}
+
+// Number of days in which shifts take place, Sundays are excluded
+fn service_days(from: NaiveDate, to: NaiveDate) -> u32 {
+    from.iter_days()
+        .take_while(|day| *day <= to)
+        .filter(|day| day.weekday() != Weekday::Sun)
+        .count() as u32
+}
+
+impl Statistics {
+    fn compute(
+        locale: Locale,
+        tasks: &Tasks,
+        shifts: Vec<ExportedShift>,
+        last_shifts: Vec<LastShift>,
+        (from, to): (Option<NaiveDate>, Option<NaiveDate>),
+        inactive_since: NaiveDate,
+    ) -> Self {
+        // Unbounded ranges are limited by the first and the last shift
+        let from = from.or_else(|| shifts.iter().map(|exported| exported.shift.date).min());
+        let to = to.or_else(|| shifts.iter().map(|exported| exported.shift.date).max());
+        let (months, service_days) = match (from, to) {
+            (Some(from), Some(to)) => (months(from, to), service_days(from, to)),
+            _ => (Vec::new(), 0),
+        };
+        let month_index = |date: NaiveDate| {
+            months
+                .iter()
+                .rposition(|first| *first <= date)
+                .unwrap_or_default()
+        };
+
+        let mut volunteers: BTreeMap<i16, VolunteerStatistics> = BTreeMap::new();
+        let mut tasks_shifts: HashMap<i16, u32> = HashMap::new();
+        let mut tasks_days: HashMap<i16, HashSet<NaiveDate>> = HashMap::new();
+        for exported in shifts {
+            let shift = exported.shift;
+            let volunteer =
+                volunteers
+                    .entry(shift.card_id)
+                    .or_insert_with(|| VolunteerStatistics {
+                        card_id: shift.card_id,
+                        surname: exported.surname,
+                        name: exported.name,
+                        months: vec![0; months.len()],
+                        shifts: 0,
+                        hours: String::new(),
+                        last_shift: String::new(),
+                        minutes: 0,
+                    });
+            if let Some(month) = volunteer.months.get_mut(month_index(shift.date)) {
+                *month += 1;
+            }
+            volunteer.shifts += 1;
+            volunteer.minutes += tasks.minutes_from_id(shift.task);
+            // Shifts are ordered by date
+            volunteer.last_shift = shift.date.format(SHOWN_DATE_FORMAT).to_string();
+
+            *tasks_shifts.entry(shift.task).or_default() += 1;
+            tasks_days.entry(shift.task).or_default().insert(shift.date);
+        }
+
+        let mut volunteers: Vec<VolunteerStatistics> = volunteers
+            .into_values()
+            .map(|mut volunteer| {
+                volunteer.hours = locale.format_decimal(volunteer.minutes as f64 / 60.0);
+                volunteer
+            })
+            .collect();
+        volunteers.sort_by_key(|volunteer| Reverse(volunteer.shifts));
+
+        let tasks = tasks
+            .render()
+            .iter()
+            .map(|task| {
+                let shifts = tasks_shifts.get(&task.task_value).copied().unwrap_or(0);
+                let covered_days = tasks_days
+                    .get(&task.task_value)
+                    .map_or(0, |days| days.len() as u32);
+                TaskStatistics {
+                    task_name: task.task_name.clone(),
+                    task_hours: task.task_hours.clone(),
+                    shifts,
+                    covered_days,
+                    fill_rate: (covered_days * 100).checked_div(service_days).unwrap_or(0),
+                    average: locale.format_decimal(if service_days > 0 {
+                        shifts as f64 / service_days as f64
+                    } else {
+                        0.0
+                    }),
+                }
+            })
+            .collect();
+
+        let inactive = last_shifts
+            .into_iter()
+            .filter(|volunteer| {
+                volunteer
+                    .last_shift
+                    .is_none_or(|last_shift| last_shift < inactive_since)
+            })
+            .map(|volunteer| InactiveVolunteer {
+                card_id: volunteer.card_id,
+                surname: volunteer.surname,
+                name: volunteer.name,
+                last_shift: format_last_shift(locale, volunteer.last_shift),
+            })
+            .collect();
+
+        Self {
+            months: months
+                .into_iter()
+                .map(|month| format_month(locale, month))
+                .collect(),
+            volunteers,
+            tasks,
+            inactive,
+        }
+    }
+
+    // Write a table as a CSV file
+    fn csv_file(&self, locale: Locale, table: &StatisticsTable) -> Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let columns = |columns: &[&str]| -> Vec<String> {
+            columns.iter().map(|column| locale.text(column)).collect()
+        };
+        match table {
+            StatisticsTable::Volunteers => {
+                let mut headers = columns(&[CARD_ID_COLUMN, SURNAME_COLUMN, NAME_COLUMN]);
+                headers.extend(self.months.iter().cloned());
+                headers.extend(columns(&[SHIFTS_COLUMN, HOURS_COLUMN, LAST_SHIFT_COLUMN]));
+                writer.write_record(headers)?;
+                for volunteer in &self.volunteers {
+                    let mut record = vec![
+                        volunteer.card_id.to_string(),
+                        volunteer.surname.clone(),
+                        volunteer.name.clone(),
+                    ];
+                    record.extend(volunteer.months.iter().map(u32::to_string));
+                    record.extend([
+                        volunteer.shifts.to_string(),
+                        format!("{:.1}", volunteer.minutes as f64 / 60.0),
+                        volunteer.last_shift.clone(),
+                    ]);
+                    writer.write_record(record)?;
+                }
+            }
+            StatisticsTable::Tasks => {
+                writer.write_record(columns(&[
+                    TASK_COLUMN,
+                    TASK_HOURS_COLUMN,
+                    SHIFTS_COLUMN,
+                    COVERED_DAYS_COLUMN,
+                    FILL_RATE_COLUMN,
+                    AVERAGE_COLUMN,
+                ]))?;
+                for task in &self.tasks {
+                    writer.write_record([
+                        &task.task_name,
+                        &task.task_hours,
+                        &task.shifts.to_string(),
+                        &task.covered_days.to_string(),
+                        &task.fill_rate.to_string(),
+                        &task.average.replace(',', "."),
+                    ])?;
+                }
+            }
+            StatisticsTable::Inactive => {
+                writer.write_record(columns(&[
+                    CARD_ID_COLUMN,
+                    SURNAME_COLUMN,
+                    NAME_COLUMN,
+                    LAST_SHIFT_COLUMN,
+                ]))?;
+                for volunteer in &self.inactive {
+                    writer.write_record([
+                        &volunteer.card_id.to_string(),
+                        &volunteer.surname,
+                        &volunteer.name,
+                        &volunteer.last_shift,
+                    ])?;
+                }
+            }
+        }
+        Ok(writer.into_inner()?)
+    }
+}
+
+// Date range of the statistics, without parameters the last months are chosen
+fn date_range(
+    timezone: Tz,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), &'static str> {
+    if from.is_none() && to.is_none() {
+        let today = Date::current(timezone).naive_date();
+        let (first, _) = month_bounds(today.year(), today.month()).ok_or(DATES_ERROR)?;
+        return Ok((
+            first.checked_sub_months(Months::new(DEFAULT_MONTHS - 1)),
+            Some(today),
+        ));
+    }
+    let from = parse_date(from).map_err(|_| INVALID_DATE_MESSAGE)?;
+    let to = parse_date(to).map_err(|_| INVALID_DATE_MESSAGE)?;
+    Ok((from, to))
+}
+
+// Retrieve shifts and compute statistics
+async fn statistics(
+    locale: Locale,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    range: (Option<NaiveDate>, Option<NaiveDate>),
+    weeks: u32,
+) -> Result<Statistics, InternalError> {
+    let shifts = query_error(
+        query_exported_shifts(&state.pool, organisation.id, range.0, range.1, None),
+        uri,
+    )
+    .await?;
+    let today = Date::current(state.timezone).naive_date();
+    let last_shifts =
+        query_error(query_last_shifts(&state.pool, organisation.id, today), uri).await?;
+    let inactive_since = today
+        .checked_sub_days(Days::new(weeks as u64 * 7))
+        .ok_or_else(|| InternalError::text(uri, DATES_ERROR))?;
+
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+
+    Ok(Statistics::compute(
+        locale,
+        &tasks,
+        shifts,
+        last_shifts,
+        range,
+        inactive_since,
+    ))
+}
+
+// Period and inactivity threshold chosen in the statistics page
+#[derive(FromForm, Default)]
+pub(crate) struct StatisticsFilter<'r> {
+    from: Option<&'r str>,
+    to: Option<&'r str>,
+    weeks: Option<u32>,
+}
+
+#[get("/statistiche?<filter..>")]
+pub(crate) async fn show_statistics(
+    filter: Option<StatisticsFilter<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let filter = filter.unwrap_or_default();
+    let range = date_range(state.timezone, filter.from, filter.to)
+        .map_err(|message| InternalError::text(uri, message))?;
+    let weeks = filter.weeks.unwrap_or(INACTIVE_WEEKS);
+    let statistics = statistics(locale, state, organisation, uri, range, weeks).await?;
+    let format_value = |date: Option<NaiveDate>| {
+        date.map(|date| date.format(DATE_FORMAT).to_string())
+            .unwrap_or_default()
+    };
+
+    Ok(Template::render(
+        "statistics",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            statistics_route: administration_uri!(show_statistics(_)),
+            download_route: administration_uri!(download_statistics(_, _)),
+            from_message: locale.text(FROM_MESSAGE),
+            from_value: format_value(range.0),
+            to_message: locale.text(TO_MESSAGE),
+            to_value: format_value(range.1),
+            weeks_message: locale.text(WEEKS_MESSAGE),
+            weeks,
+            filter_message: locale.text(FILTER_MESSAGE),
+            download_message: locale.text(DOWNLOAD_MESSAGE),
+            volunteers_message: locale.text(VOLUNTEERS_MESSAGE),
+            tasks_message: locale.text(TASKS_MESSAGE),
+            inactive_message: locale.text(INACTIVE_MESSAGE),
+            no_shifts_message: statistics.volunteers.is_empty().then(|| locale.text(EMPTY_SHIFTS_MESSAGE)),
+            no_inactive_message: statistics.inactive.is_empty().then(|| locale.text(EMPTY_INACTIVE_MESSAGE)),
+            months: statistics.months,
+            volunteers: statistics.volunteers,
+            tasks: statistics.tasks,
+            inactive: statistics.inactive,
+        },
+    ))
+}
+
+#[get("/statistiche/csv?<table>&<filter..>")]
+async fn download_statistics(
+    table: Option<StatisticsTable>,
+    filter: Option<StatisticsFilter<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<ExportFile, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let table = table.unwrap_or_default();
+    let filter = filter.unwrap_or_default();
+    let range = date_range(state.timezone, filter.from, filter.to)
+        .map_err(|message| InternalError::text(uri, message))?;
+    let weeks = filter.weeks.unwrap_or(INACTIVE_WEEKS);
+    let statistics = statistics(locale, state, organisation, uri, range, weeks).await?;
+    let content = statistics
+        .csv_file(locale, &table)
+        .map_err(|e| InternalError::unexpected(uri, e))?;
+
+    let name = match table {
+        StatisticsTable::Volunteers => "statistiche_volontari",
+        StatisticsTable::Tasks => "statistiche_mansioni",
+        StatisticsTable::Inactive => "volontari_inattivi",
+    };
+
+    Ok(ExportFile::new(
+        content,
+        ContentType::CSV,
+        file_name(name, range.0, range.1, "csv"),
+    ))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![show_statistics, download_statistics]
+}
@@ -1,57 +1,98 @@
-use chrono::{DateTime, Datelike, Duration, NaiveDateTime, TimeZone, Utc, Weekday};
-use chrono_tz::Europe::Rome;
-
-// Italian days
-pub(crate) const ITALIAN_DAYS: [&str; 6] = [
-    "Lunedì",
-    "Martedì",
-    "Mercoledì",
-    "Giovedì",
-    "Venerdì",
-    "Sabato",
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::locale::Locale;
+
+// Number of days with shifts in a week, from Monday to Saturday
+pub(crate) const WORKING_DAYS: u32 = 6;
+
+// Days names, from Monday to Sunday
+const DAYS: [&str; 7] = [
+    "day-monday",
+    "day-tuesday",
+    "day-wednesday",
+    "day-thursday",
+    "day-friday",
+    "day-saturday",
+    "day-sunday",
 ];
 
-// Italian months acronyms
-const ITALIAN_MONTHS_ACRONYMS: [&str; 12] = [
-    "Gen", "Feb", "Mar", "Apr", "Mag", "Giu", "Lug", "Ago", "Set", "Ott", "Nov", "Dic",
+// Months acronyms
+const MONTHS_ACRONYMS: [&str; 12] = [
+    "month-short-january",
+    "month-short-february",
+    "month-short-march",
+    "month-short-april",
+    "month-short-may",
+    "month-short-june",
+    "month-short-july",
+    "month-short-august",
+    "month-short-september",
+    "month-short-october",
+    "month-short-november",
+    "month-short-december",
 ];
 
-// Italian months acronyms
-const ITALIAN_MONTHS: [&str; 12] = [
-    "Gennaio",
-    "Febbraio",
-    "Marzo",
-    "Aprile",
-    "Maggio",
-    "Giugno",
-    "Luglio",
-    "Agosto",
-    "Settembre",
-    "Ottobre",
-    "Novembre",
-    "Dicembre",
+// Months names
+const MONTHS: [&str; 12] = [
+    "month-january",
+    "month-february",
+    "month-march",
+    "month-april",
+    "month-may",
+    "month-june",
+    "month-july",
+    "month-august",
+    "month-september",
+    "month-october",
+    "month-november",
+    "month-december",
 ];
 
-// Returns Italian timezone
-fn italian_timezone() -> DateTime<chrono_tz::Tz> {
-    // Current UTC date and time
-    let utc = Utc::now();
-    // Current UTC date as NaiveDate
-    let current_date = utc.date_naive();
-    // Current UTC time as NaiveTime
-    let current_time = utc.time();
-    // Italian date and time
-    Rome.from_utc_datetime(&NaiveDateTime::new(current_date, current_time))
+// Returns the current date and time in the organisation timezone
+fn local_now(timezone: Tz) -> DateTime<Tz> {
+    Utc::now().with_timezone(&timezone)
+}
+
+// Formats the month of a date as acronym and year
+pub(crate) fn format_month(locale: Locale, date: NaiveDate) -> String {
+    format!(
+        "{} {}",
+        locale.text(MONTHS_ACRONYMS[date.month0() as usize]),
+        date.year()
+    )
+}
+
+// Formats a weekday
+pub(crate) fn format_weekday(locale: Locale, weekday: Weekday) -> String {
+    locale.text(DAYS[weekday.num_days_from_monday() as usize])
+}
+
+// Formats a date as weekday followed by day, month and year
+pub(crate) fn format_date(locale: Locale, date: &NaiveDate) -> String {
+    format!(
+        "{} {}",
+        format_weekday(locale, date.weekday()),
+        date.format("%d/%m/%Y")
+    )
+}
+
+// Formats a UTC date and time using the organisation timezone
+pub(crate) fn format_date_time(timezone: Tz, date_time: DateTime<Utc>) -> String {
+    date_time
+        .with_timezone(&timezone)
+        .format("%d/%m/%Y %H:%M")
+        .to_string()
 }
 
 // Date structure
-pub(crate) struct Date(DateTime<chrono_tz::Tz>);
+pub(crate) struct Date(DateTime<Tz>);
 
 impl Date {
     // Get current date
     #[inline(always)]
-    pub(crate) fn current() -> Self {
-        let date = Self(italian_timezone());
+    pub(crate) fn current(timezone: Tz) -> Self {
+        let date = Self(local_now(timezone));
         // If it is Sunday, skip to next Monday
         if date.day_as_number() == 6 {
             Self(date.0 + Duration::days(1))
@@ -74,25 +115,25 @@ impl Date {
 
     // Formats a date
     #[inline(always)]
-    pub(crate) fn month_date(&self) -> String {
+    pub(crate) fn month_date(&self, locale: Locale) -> String {
         format!(
             "{} {}",
             self.0.format("%d"),
-            ITALIAN_MONTHS[self.0.month0() as usize]
+            locale.text(MONTHS[self.0.month0() as usize])
         )
     }
 
     // Retrieves week bounds
     #[inline(always)]
-    pub(crate) fn week_bounds(&self) -> (String, String) {
+    pub(crate) fn week_bounds(&self, locale: Locale) -> (String, String) {
         // Monday
         let monday = self.monday().0;
         // Saturday
         let saturday = monday + Duration::days(5);
 
         (
-            Self::format_week_date(monday),
-            Self::format_week_date(saturday),
+            Self::format_week_date(locale, monday),
+            Self::format_week_date(locale, saturday),
         )
     }
 
@@ -112,8 +153,8 @@ impl Date {
 
     // Gets the day associated to a date
     #[inline(always)]
-    pub(crate) fn weekday(&self) -> &'static str {
-        ITALIAN_DAYS[self.day_as_number() as usize]
+    pub(crate) fn weekday(&self, locale: Locale) -> String {
+        format_weekday(locale, self.0.weekday())
     }
 
     // Gets day associated to a date
@@ -134,6 +175,12 @@ impl Date {
         self.0.year()
     }
 
+    // Gets the date without time, as saved in the database
+    #[inline(always)]
+    pub(crate) fn naive_date(&self) -> NaiveDate {
+        self.0.date_naive()
+    }
+
     // Gets day as number days from Monday (excluding Sunday)
     #[inline(always)]
     pub(crate) fn day_as_number(&self) -> u32 {
@@ -149,14 +196,14 @@ impl Date {
 
     // Formats a date
     #[inline(always)]
-    fn format_week_date<T: TimeZone>(date: DateTime<T>) -> String
+    fn format_week_date<T: TimeZone>(locale: Locale, date: DateTime<T>) -> String
     where
         T::Offset: core::fmt::Display,
     {
         format!(
             "{} {}.",
             date.format("%d"),
-            ITALIAN_MONTHS_ACRONYMS[date.month0() as usize]
+            locale.text(MONTHS_ACRONYMS[date.month0() as usize])
         )
     }
 }
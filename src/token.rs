@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+use hmac::{Hmac, Mac};
+
+use rand::RngCore;
+
+use sha2::{Digest, Sha256};
+
+// Number of random bytes contained in a token
+const TOKEN_BYTES: usize = 32;
+// Number of random bytes contained in a password salt
+const SALT_BYTES: usize = 16;
+
+// Generate a random token encoded as hexadecimal string
+pub(crate) fn random_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Compare two tokens in constant time
+pub(crate) fn tokens_match(first: &str, second: &str) -> bool {
+    first.len() == second.len()
+        && first
+            .bytes()
+            .zip(second.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+// Hash a token, so that it is never saved in clear
+pub(crate) fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// Hash a password through Argon2 with a random salt, encoded as a PHC string
+// which contains the salt and the parameters
+pub(crate) fn hash_password(password: &str) -> Result<String> {
+    let mut bytes = [0u8; SALT_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let salt = SaltString::encode_b64(&bytes).map_err(|_| anyhow!("Invalid password salt"))?;
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|_| anyhow!("Password hashing failed"))?
+        .to_string())
+}
+
+// Check a password against its Argon2 hash
+pub(crate) fn password_matches(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+// Sign a message with a secret through HMAC-SHA256, encoded as hexadecimal
+// string
+pub(crate) fn sign_message(secret: &str, message: &str) -> Result<String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|_| anyhow!("Invalid signature secret"))?;
+    mac.update(message.as_bytes());
+    Ok(mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
@@ -1,26 +1,44 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use chrono::{Datelike, NaiveDate, Utc};
+
 use rocket::form::Form;
 use rocket::http::uri::Origin;
-use rocket::http::{Cookie, CookieJar};
+use rocket::http::{ContentType, Cookie, CookieJar, Status};
+use rocket::request::{FromRequest, Outcome};
 use rocket::response::stream::{Event, EventStream};
 use rocket::response::Redirect;
 use rocket::tokio::select;
 use rocket::tokio::sync::broadcast::error::RecvError;
-use rocket::{Shutdown, State};
+use rocket::tokio::sync::broadcast::{channel, Receiver, Sender};
+use rocket::{Request, Shutdown, State};
 use rocket_dyn_templates::{context, Template};
 
 use serde::Serialize;
 
-use crate::data::{Day, SelectDay, Tasks, WeekData};
-use crate::database::query_volunteers_shifts;
+use sqlx::PgPool;
+
+use crate::data::{Day, Locations, SelectDay, Tasks, WeekData};
+use crate::database::{
+    insert_db_date, query_tasks, query_volunteer, query_volunteers_shifts, Organisation, Shift,
+    DEFAULT_LOCATION,
+};
+use crate::download_database::{parse_date, ExportFile, DATE_FORMAT};
 use crate::error::{query_error, InternalError};
-use crate::login::{ADMINISTRATION_COOKIE, AUTHENTICATION_COOKIE};
+use crate::locale::Locale;
+use crate::login::session::Session;
 use crate::menu::{Menu, MenuAdministration};
-use crate::time::Date;
-use crate::{AppState, APP_TITLE, VISUALIZE_SHIFTS_ROUTE};
+use crate::print::{Roster, RosterCell, RosterRow};
+use crate::time::{format_date_time, format_weekday, Date, WORKING_DAYS};
+use crate::{AppState, VISUALIZE_SHIFTS_ROUTE};
 
 // Cookies key
 const WEEK: &str = "week";
 const DAY: &str = "day";
+const LOCATION: &str = "location";
 
 // Macro that redirects to visualize shifts
 macro_rules! visualize_shifts_uri {
@@ -29,12 +47,226 @@ macro_rules! visualize_shifts_uri {
 
 use visualize_shifts_uri as uri;
 
+// Number of events kept to resume interrupted streams
+const EVENTS_HISTORY: usize = 100;
+
+// Shift shown in the visualizer
+#[derive(Clone, Serialize)]
+pub(crate) struct VisualizedShift {
+    // Shift date
+    date: NaiveDate,
+    // Task identifier
+    task: i16,
+    // Location identifier
+    location: i16,
+    // Volunteer name and surname, as shown in the visualizer
+    volunteer: String,
+}
+
+impl VisualizedShift {
+    async fn new(pool: &PgPool, organisation_id: i32, shift: &Shift) -> Result<Self> {
+        let volunteer = query_volunteer(pool, organisation_id, shift.card_id).await?;
+        Ok(Self {
+            date: shift.date,
+            task: shift.task,
+            location: shift.location_id,
+            volunteer: format!("{} {}", volunteer.name, volunteer.surname),
+        })
+    }
+}
+
+// Change together with its event identifier and its organisation
+type NumberedChange = (u64, i32, ShiftChange);
+
+// Change shown in the visualizer
+#[derive(Clone)]
+enum ShiftChange {
+    // A shift has been booked
+    Added(VisualizedShift),
+    // A shift has been cancelled
+    Removed(VisualizedShift),
+    // Many shifts or volunteers changed, the whole page must be reloaded
+    Reload,
+}
+
+impl ShiftChange {
+    // Check whether the change concerns the day and the location shown by a
+    // client
+    fn concerns(&self, date: Option<NaiveDate>, location: Option<i16>) -> bool {
+        match self {
+            Self::Added(shift) | Self::Removed(shift) => {
+                date.is_none_or(|date| date == shift.date)
+                    && location.is_none_or(|location| location == shift.location)
+            }
+            Self::Reload => true,
+        }
+    }
+
+    // Convert the change into a server-sent event
+    fn event(&self, id: Option<u64>) -> Event {
+        let event = match self {
+            Self::Added(shift) => Event::json(shift).event("shift_added"),
+            Self::Removed(shift) => Event::json(shift).event("shift_removed"),
+            Self::Reload => Event::data("").event("reload"),
+        };
+        match id {
+            Some(id) => event.id(id.to_string()),
+            None => event,
+        }
+    }
+}
+
+// Events history, used to resume interrupted streams
+struct EventsHistory {
+    // Identifier of the next event
+    next_id: u64,
+    // Most recent events together with their identifiers
+    events: VecDeque<NumberedChange>,
+}
+
+// Changes sent to the clients showing the visualizer
+pub(crate) struct ShiftsEvents {
+    // Changes sent to the connected clients
+    sender: Sender<NumberedChange>,
+    // Most recent changes
+    history: Mutex<EventsHistory>,
+}
+
+impl ShiftsEvents {
+    pub(crate) fn new() -> Self {
+        Self {
+            sender: channel(EVENTS_HISTORY).0,
+            // Identifiers start from the server start time, so that
+            // identifiers sent before a restart are always smaller
+            history: Mutex::new(EventsHistory {
+                next_id: Utc::now().timestamp_millis().unsigned_abs(),
+                events: VecDeque::with_capacity(EVENTS_HISTORY),
+            }),
+        }
+    }
+
+    // Send a change to the connected clients, saving it into the history
+    fn send(&self, organisation_id: i32, change: ShiftChange) {
+        let Ok(mut history) = self.history.lock() else {
+            return;
+        };
+        let id = history.next_id;
+        history.next_id += 1;
+        if history.events.len() == EVENTS_HISTORY {
+            history.events.pop_front();
+        }
+        history
+            .events
+            .push_back((id, organisation_id, change.clone()));
+        let _res = self.sender.send((id, organisation_id, change));
+    }
+
+    // Notify a booked shift
+    pub(crate) async fn shift_added(
+        &self,
+        pool: &PgPool,
+        organisation_id: i32,
+        shift: &Shift,
+    ) -> Result<()> {
+        let shift = VisualizedShift::new(pool, organisation_id, shift).await?;
+        self.send(organisation_id, ShiftChange::Added(shift));
+        Ok(())
+    }
+
+    // Notify a cancelled shift
+    pub(crate) async fn shift_removed(
+        &self,
+        pool: &PgPool,
+        organisation_id: i32,
+        shift: &Shift,
+    ) -> Result<()> {
+        let shift = VisualizedShift::new(pool, organisation_id, shift).await?;
+        self.send(organisation_id, ShiftChange::Removed(shift));
+        Ok(())
+    }
+
+    // Notify that every client of an organisation must reload its page
+    pub(crate) fn reload(&self, organisation_id: i32) {
+        self.send(organisation_id, ShiftChange::Reload);
+    }
+
+    // Identifier of the last sent event
+    fn last_id(&self) -> Option<u64> {
+        self.history
+            .lock()
+            .ok()
+            .map(|history| history.next_id.saturating_sub(1))
+    }
+
+    // Subscribe to new changes, retrieving the ones sent after the last
+    // event received by a client. When they are not available anymore,
+    // the client is asked to reload its page.
+    fn subscribe(
+        &self,
+        organisation_id: i32,
+        last_id: Option<u64>,
+    ) -> (Vec<NumberedChange>, Receiver<NumberedChange>) {
+        let Ok(history) = self.history.lock() else {
+            return (
+                vec![(0, organisation_id, ShiftChange::Reload)],
+                self.sender.subscribe(),
+            );
+        };
+        let receiver = self.sender.subscribe();
+        let Some(last_id) = last_id else {
+            return (Vec::new(), receiver);
+        };
+
+        let first_id = history
+            .events
+            .front()
+            .map_or(history.next_id, |(id, _, _)| *id);
+        let missed = if last_id >= history.next_id || last_id + 1 < first_id {
+            vec![(
+                history.next_id.saturating_sub(1),
+                organisation_id,
+                ShiftChange::Reload,
+            )]
+        } else {
+            history
+                .events
+                .iter()
+                .filter(|(id, _, _)| *id > last_id)
+                .cloned()
+                .collect()
+        };
+        (missed, receiver)
+    }
+}
+
+// Identifier of the last event received by a client. The header is sent by
+// browsers reconnecting on their own, while the `last_event_id` parameter is
+// sent by the page when it opens a new stream.
+pub(crate) struct LastEventId(Option<u64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self(
+            req.headers()
+                .get_one("Last-Event-ID")
+                .and_then(|id| id.trim().parse().ok())
+                .or_else(|| req.query_value("last_event_id").and_then(Result::ok)),
+        ))
+    }
+}
+
 #[derive(FromForm)]
 struct ParamsForm {
     #[field(name = "week")]
     week: String,
     #[field(name = "day")]
     day: String,
+    // Missing when the organisation has a single location
+    #[field(name = "location")]
+    location: Option<String>,
 }
 
 #[put("/", data = "<params_form>", rank = 2)]
@@ -45,6 +277,9 @@ async fn process_visualizer_params(params_form: Form<ParamsForm>, jar: &CookieJa
     // Add data to cookies
     jar.add((WEEK, data.week));
     jar.add((DAY, data.day));
+    if let Some(location) = data.location {
+        jar.add((LOCATION, location));
+    }
 
     // Redirect to visualize shifts
     Redirect::to(uri!(visualize_shifts))
@@ -60,49 +295,96 @@ struct Week {
 }
 
 impl Week {
-    fn new(week_value: bool, date: &Date) -> Self {
+    fn new(locale: Locale, week_value: bool, date: &Date) -> Self {
         Self {
             week_value,
-            week_text: WeekData::week_bounds(date),
+            week_text: WeekData::week_bounds(locale, date),
         }
     }
 }
 
+// Location which can be chosen
+#[derive(Serialize)]
+struct SelectLocation {
+    // Location identifier
+    id: i16,
+    // Location name
+    name: String,
+    // Whether the location is selected
+    is_selected: Option<&'static str>,
+}
+
+impl SelectLocation {
+    // Locations shown in the form, only when there are more of them
+    fn locations(locations: &Locations, selected: i16) -> Option<Vec<Self>> {
+        (locations.render().len() > 1).then(|| {
+            locations
+                .render()
+                .iter()
+                .map(|location| Self {
+                    id: location.id,
+                    name: location.name.clone(),
+                    is_selected: (location.id == selected).then_some("selected"),
+                })
+                .collect()
+        })
+    }
+}
+
 // Form information
 #[derive(Serialize)]
 struct FormInfo {
     // Route
     route: Origin<'static>,
     // Week text
-    week_text: &'static str,
+    week_text: String,
     // Weeks
     weeks: [Week; 2],
     // Days text
-    days_text: &'static str,
+    days_text: String,
     // First week days
     first_week_days: Option<Vec<SelectDay>>,
     // Second week days
     second_week_days: Option<Vec<Day>>,
+    // Location text
+    location_text: String,
+    // Locations
+    locations: Option<Vec<SelectLocation>>,
+    // Address of the chosen location
+    address: String,
 }
 
 impl FormInfo {
-    fn render(date: Date, is_first_week: bool, is_first_time: bool) -> Self {
+    fn render(
+        locale: Locale,
+        date: Date,
+        is_first_week: bool,
+        is_first_time: bool,
+        locations: &Locations,
+        location: i16,
+    ) -> Self {
         let first_week = date.monday();
         let second_week = first_week.next_week();
         Self {
             route: uri!(process_visualizer_params),
-            week_text: "Scegli settimana",
-            weeks: [Week::new(true, &first_week), Week::new(false, &second_week)],
-            days_text: "Scegli giorno",
+            week_text: locale.text("label-choose-week"),
+            weeks: [
+                Week::new(locale, true, &first_week),
+                Week::new(locale, false, &second_week),
+            ],
+            days_text: locale.text("label-choose-day"),
             first_week_days: if is_first_time && is_first_week {
                 let day_as_number = date.day_as_number();
-                Some(SelectDay::selected_days(&first_week, day_as_number))
+                Some(SelectDay::selected_days(locale, &first_week, day_as_number))
             } else if is_first_week {
-                Some(SelectDay::days(&first_week))
+                Some(SelectDay::days(locale, &first_week))
             } else {
                 None
             },
-            second_week_days: (!is_first_week).then(|| WeekData::days(&second_week)),
+            second_week_days: (!is_first_week).then(|| WeekData::days(locale, &second_week)),
+            location_text: locale.text("label-choose-location"),
+            locations: SelectLocation::locations(locations, location),
+            address: locations.address_from_id(location),
         }
     }
 }
@@ -110,10 +392,14 @@ impl FormInfo {
 // Visualizer information
 #[derive(Serialize)]
 struct VisualizerInfo {
+    // Task identifier
+    task_value: i16,
     // Task
-    task_name: &'static str,
+    task_name: String,
     // Hours
-    task_hours: &'static str,
+    task_hours: String,
+    // Maximum number of volunteers, none when unlimited
+    capacity: Option<i16>,
     // Volunteers names
     volunteers_names: Vec<String>,
 }
@@ -126,6 +412,15 @@ fn get_week(week: Option<&str>) -> bool {
     }
 }
 
+// Location chosen through the form, the first one when missing
+fn get_location(location: Option<&str>, locations: &Locations) -> i16 {
+    location
+        .and_then(|location| location.parse().ok())
+        .filter(|location| locations.contains(*location))
+        .or_else(|| locations.first())
+        .unwrap_or(DEFAULT_LOCATION)
+}
+
 #[inline(always)]
 fn get_day(day: Option<&str>, date: &Date) -> u32 {
     match day {
@@ -139,32 +434,24 @@ fn get_day(day: Option<&str>, date: &Date) -> u32 {
     }
 }
 
-#[inline(always)]
-fn get_card_id(jar: &CookieJar, name: &str) -> Option<i16> {
-    jar.get_private(name)
-        .as_ref()
-        .map(Cookie::value)
-        .and_then(|val| val.parse::<i16>().ok())
-}
-
 #[get("/", rank = 2)]
 pub(crate) async fn visualize_shifts(
+    session: Option<Session>,
     state: &State<AppState>,
+    organisation: &Organisation,
     jar: &CookieJar<'_>,
     uri: &Origin<'_>,
+    locale: Locale,
 ) -> Result<Template, InternalError> {
-    // Check if the administrator is authenticated
-    let (is_administration, card_id) =
-        if let Some(card_id) = get_card_id(jar, ADMINISTRATION_COOKIE) {
-            (true, card_id)
-        } else {
-            // Check if the user is authenticated
-            if let Some(card_id) = get_card_id(jar, AUTHENTICATION_COOKIE) {
-                (false, card_id)
-            } else {
-                return Err(InternalError::not_authenticated(uri));
-            }
-        };
+    // Check if the user or the administrator is authenticated
+    let Some(Session {
+        card_id,
+        is_administration,
+        ..
+    }) = session
+    else {
+        return Err(InternalError::not_authenticated(uri));
+    };
 
     // Week cookie
     let week = jar.get(WEEK).map(Cookie::value);
@@ -176,7 +463,7 @@ pub(crate) async fn visualize_shifts(
     let week = get_week(week);
 
     // Get current date
-    let current_date = Date::current();
+    let current_date = Date::current(state.timezone);
 
     // Get day value
     let day = get_day(jar.get(DAY).map(Cookie::value), &current_date);
@@ -188,22 +475,42 @@ pub(crate) async fn visualize_shifts(
         current_date.next_week().day_date(day)
     };
 
+    // Get location value
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let location = get_location(jar.get(LOCATION).map(Cookie::value), &locations);
+
     // Retrieve form information
-    let form_info = FormInfo::render(current_date, week, is_first_time);
+    let form_info = FormInfo::render(
+        locale,
+        current_date,
+        week,
+        is_first_time,
+        &locations,
+        location,
+    );
 
     // Visualizer information
     let mut visualize_info = Vec::new();
 
-    for task in Tasks::render() {
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    for task in locations.tasks(location, &tasks) {
         let volunteers_names = query_error(
-            query_volunteers_shifts(&state.pool, date, task.task_value as i16),
+            query_volunteers_shifts(
+                &state.pool,
+                organisation.id,
+                date,
+                location,
+                task.task_value,
+            ),
             uri,
         )
         .await?;
 
         visualize_info.push(VisualizerInfo {
-            task_name: task.task_name,
-            task_hours: task.task_hours,
+            task_value: task.task_value,
+            task_name: task.task_name.clone(),
+            task_hours: task.task_hours.clone(),
+            capacity: locations.capacity(location, task.task_value),
             volunteers_names,
         });
     }
@@ -211,39 +518,177 @@ pub(crate) async fn visualize_shifts(
     Ok(Template::render(
         "visualizer",
         context! {
-            title: APP_TITLE,
+            lang: locale.code(),
+            title: &organisation.name,
             is_administration,
-            menu: Menu::render(card_id),
-            menu_administration: MenuAdministration::render(),
+            menu: Menu::render(locale, organisation, card_id),
+            menu_administration: MenuAdministration::render(locale, organisation),
             form_info,
             visualize_info,
+            print_route: uri!(print_shifts),
+            print_message: locale.text("visualizer-print"),
+            stream_route: uri!(visualize_shifts_stream(
+                insert_db_date(date).map(|date| date.format(DATE_FORMAT).to_string()),
+                Some(location)
+            )),
+            last_event_id: state.sender.last_id(),
         },
     ))
 }
 
-#[get("/", format = "text/event-stream", rank = 1)]
-async fn visualize_shifts_stream(state: &State<AppState>, mut end: Shutdown) -> EventStream![] {
-    let mut rx = state.sender.subscribe();
-    EventStream! {
+#[get("/stampa")]
+pub(crate) async fn print_shifts(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    jar: &CookieJar<'_>,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<ExportFile, InternalError> {
+    // Check if the user or the administrator is authenticated
+    if session.is_none() {
+        return Err(InternalError::not_authenticated(uri));
+    }
+
+    // Retrieve the week selected in the visualizer page
+    let current_date = Date::current(state.timezone);
+    let week = if get_week(jar.get(WEEK).map(Cookie::value)) {
+        current_date.monday()
+    } else {
+        current_date.next_week().monday()
+    };
+
+    // Retrieve the location selected in the visualizer page
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let location = get_location(jar.get(LOCATION).map(Cookie::value), &locations);
+
+    // Week days, from Monday to Saturday
+    let dates: Vec<(i32, u32, u32)> = (0..WORKING_DAYS).map(|day| week.day_date(day)).collect();
+
+    // Build the grid using the same data of the visualizer page
+    let mut rows = Vec::new();
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    for task in locations.tasks(location, &tasks) {
+        let mut cells = Vec::new();
+        for date in &dates {
+            let volunteers_names = query_error(
+                query_volunteers_shifts(
+                    &state.pool,
+                    organisation.id,
+                    *date,
+                    location,
+                    task.task_value,
+                ),
+                uri,
+            )
+            .await?;
+            cells.push(RosterCell { volunteers_names });
+        }
+        rows.push(RosterRow {
+            task_name: task.task_name.clone(),
+            task_hours: task.task_hours.clone(),
+            cells,
+        });
+    }
+
+    // The location is written only when there are more of them
+    let organisation_name = if locations.render().len() > 1 {
+        format!(
+            "{} - {}",
+            organisation.name,
+            locations.name_from_id(location)
+        )
+    } else {
+        organisation.name.clone()
+    };
+    let roster = Roster {
+        title: format!(
+            "{}: {}",
+            organisation_name,
+            WeekData::week_bounds(locale, &week)
+        ),
+        days: dates
+            .iter()
+            .filter_map(|&date| insert_db_date(date))
+            .map(|date| {
+                format!(
+                    "{} {}",
+                    format_weekday(locale, date.weekday()),
+                    date.format("%d/%m")
+                )
+            })
+            .collect(),
+        rows,
+        generated_at: format_date_time(state.timezone, Utc::now()),
+        locale,
+    };
+    let content = roster
+        .pdf()
+        .map_err(|e| InternalError::unexpected(uri, e))?;
+
+    let (year, month, day) = dates[0];
+    Ok(ExportFile::inline(
+        content,
+        ContentType::PDF,
+        format!("turni_{year}-{month:02}-{day:02}.pdf"),
+    ))
+}
+
+#[get("/?<date>&<location>", format = "text/event-stream", rank = 1)]
+pub(crate) async fn visualize_shifts_stream(
+    date: Option<String>,
+    location: Option<i16>,
+    last_event_id: LastEventId,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    mut end: Shutdown,
+) -> Result<EventStream![], Status> {
+    // Volunteers names are sent, so the stream needs a session as the page
+    if session.is_none() {
+        return Err(Status::Forbidden);
+    }
+
+    // Only changes of the organisation and of the day and the location shown
+    // by the client are sent
+    let organisation_id = organisation.id;
+    let date = parse_date(date.as_deref()).ok().flatten();
+
+    let (missed, mut rx) = state.sender.subscribe(organisation_id, last_event_id.0);
+
+    Ok(EventStream! {
+        for (id, organisation, change) in missed {
+            if organisation == organisation_id && change.concerns(date, location) {
+                yield change.event(Some(id));
+            }
+        }
+
         loop {
-            let _ = select! {
+            let (id, organisation, change) = select! {
                 msg = rx.recv() => match msg {
                     Ok(msg) => msg,
                     Err(RecvError::Closed) => break,
-                    Err(RecvError::Lagged(_)) => continue,
+                    // Some changes have been lost
+                    Err(RecvError::Lagged(_)) => {
+                        yield ShiftChange::Reload.event(None);
+                        continue;
+                    }
                 },
                 _ = &mut end => break,
             };
 
-            yield Event::data("");
+            if organisation == organisation_id && change.concerns(date, location) {
+                yield change.event(Some(id));
+            }
         }
-    }
+    })
 }
 
 pub(crate) fn routes() -> Vec<rocket::Route> {
     routes![
         visualize_shifts_stream,
         visualize_shifts,
-        process_visualizer_params
+        process_visualizer_params,
+        print_shifts
     ]
 }
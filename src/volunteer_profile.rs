@@ -0,0 +1,516 @@
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::response::Redirect;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use crate::data::{Locations, Tasks};
+use crate::database::{
+    delete_volunteer_note, delete_volunteer_override, insert_volunteer_note, query_change_request,
+    query_past_shifts, query_served_tasks, query_shifts, query_tasks, query_volunteer_notes,
+    query_volunteer_override, query_volunteer_preferences, query_volunteer_profile,
+    query_volunteer_surname_name, set_volunteer_override, Organisation, ShiftQuery, Volunteer,
+    VolunteerManager, VolunteerOverride, VolunteerPreferences, VolunteersChanges,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::invitation::INVITATION_DAYS;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::shifts::shifts_uri;
+use crate::shifts_manager::shifts_manager_uri;
+use crate::time::{format_date, format_date_time, Date};
+use crate::webhooks::{send_event, WebhookEvent};
+use crate::{
+    AppState, ADMINISTRATION_ROUTE, DISABLED_MESSAGE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE,
+    VOLUNTEERS_ROUTE,
+};
+
+// Messages
+const CONTACTS_MESSAGE: &str = "profile-contacts";
+const CALL_MESSAGE: &str = "profile-call";
+const SEND_MESSAGE: &str = "profile-send-message";
+const ACTIONS_MESSAGE: &str = "profile-actions";
+const LOCAL_DATA_MESSAGE: &str = "profile-local-data";
+const EDIT_MESSAGE: &str = "profile-edit";
+const SURNAME_MESSAGE: &str = "profile-surname";
+const NAME_MESSAGE: &str = "profile-name";
+const PHONE_MESSAGE: &str = "profile-phone";
+const DISABLED_FIELD_MESSAGE: &str = "profile-disabled";
+const SAVE_MESSAGE: &str = "profile-save";
+const RESTORE_MESSAGE: &str = "profile-restore";
+const SOURCE_MESSAGE: &str = "profile-source";
+const ENABLED_MESSAGE: &str = "profile-enabled";
+const EMAIL_MESSAGE: &str = "profile-email";
+const CHANNELS_MESSAGE: &str = "profile-channels";
+const NO_CHANNELS_MESSAGE: &str = "profile-no-channels";
+const LANGUAGE_MESSAGE: &str = "profile-language";
+const CHANGE_REQUEST_MESSAGE: &str = "profile-change-request";
+const NOTIFY_EMAIL_MESSAGE: &str = "account-notify-email";
+const NOTIFY_SMS_MESSAGE: &str = "account-notify-sms";
+const UPCOMING_MESSAGE: &str = "profile-upcoming";
+const PAST_MESSAGE: &str = "profile-past";
+const NO_SHIFTS_MESSAGE: &str = "profile-no-shifts";
+const SKILLS_MESSAGE: &str = "profile-skills";
+const NO_SKILLS_MESSAGE: &str = "profile-no-skills";
+const NOTES_MESSAGE: &str = "profile-notes";
+const NOTES_PRIVATE_MESSAGE: &str = "profile-notes-private";
+const ADD_NOTE_MESSAGE: &str = "profile-add-note";
+const DELETE_NOTE_MESSAGE: &str = "profile-delete-note";
+const NO_NOTES_MESSAGE: &str = "profile-no-notes";
+const BOOK_MESSAGE: &str = "volunteers-shifts-manager";
+const CANCEL_MESSAGE: &str = "volunteers-shifts";
+const INVITATION_MESSAGE: &str = "volunteers-invitation";
+const CERTIFICATE_MESSAGE: &str = "volunteers-certificate";
+const SINGLE_USE_MESSAGE: &str = "volunteers-single-use";
+const TIME_LIMITED_MESSAGE: &str = "volunteers-time-limited";
+
+// Number of past shifts shown in the profile
+const PAST_SHIFTS: i64 = 20;
+
+// Route to volunteers pages
+macro_rules! volunteers_uri {
+    ($($t:tt)*) => (rocket::uri!(VOLUNTEERS_ROUTE, $($t)*))
+}
+
+// Shift shown in the profile
+#[derive(Serialize)]
+struct ProfileShift {
+    // Date
+    date: String,
+    // Task
+    task: String,
+    // Hours
+    hours: String,
+    // Location name
+    location: String,
+}
+
+impl ProfileShift {
+    fn shifts(
+        locale: Locale,
+        tasks: &Tasks,
+        locations: &Locations,
+        shifts: Vec<ShiftQuery>,
+    ) -> Vec<Self> {
+        shifts
+            .into_iter()
+            .map(|shift| Self {
+                date: format_date(locale, &shift.shift.date),
+                task: tasks.task_from_id(shift.shift.task),
+                hours: tasks.hours_from_id(shift.shift.task),
+                location: locations.name_from_id(shift.shift.location_id),
+            })
+            .collect()
+    }
+}
+
+// Task served by the volunteer, together with the number of shifts
+#[derive(Serialize)]
+struct ProfileSkill {
+    // Task
+    task: String,
+    // Number of served shifts
+    shifts: i64,
+}
+
+// Note shown in the profile, with the route to delete it
+#[derive(Serialize)]
+struct ProfileNote {
+    // Author surname and name
+    author: String,
+    // Note text
+    text: String,
+    // Creation date and time
+    created_at: String,
+    // Route to delete the note
+    delete_route: Origin<'static>,
+}
+
+// Values of the volunteers file replaced by referents, each one already
+// formatted with its explanation
+#[derive(Serialize, Default)]
+struct ProfileSource {
+    surname: Option<String>,
+    name: Option<String>,
+    phone_number: Option<String>,
+    disabled: Option<String>,
+}
+
+impl ProfileSource {
+    fn new(locale: Locale, local: Option<&VolunteerOverride>) -> Self {
+        let Some(local) = local else {
+            return Self::default();
+        };
+        let source = |value: &str| locale.format(SOURCE_MESSAGE, &[("value", value.into())]);
+        Self {
+            surname: local
+                .surname
+                .as_ref()
+                .map(|_| source(&local.source_surname)),
+            name: local.name.as_ref().map(|_| source(&local.source_name)),
+            phone_number: local
+                .phone_number
+                .as_ref()
+                .map(|_| source(&local.source_phone_number)),
+            disabled: local.disabled.map(|_| {
+                source(&locale.text(if local.source_disabled {
+                    DISABLED_MESSAGE
+                } else {
+                    ENABLED_MESSAGE
+                }))
+            }),
+        }
+    }
+}
+
+// Contact preferences chosen by the volunteer
+#[derive(Serialize)]
+struct ProfilePreferences {
+    // Email address
+    email: Option<String>,
+    // Names of the channels the volunteer wants to be contacted by
+    channels: Vec<String>,
+    // Name of the chosen language
+    language: Option<&'static str>,
+}
+
+impl ProfilePreferences {
+    fn new(locale: Locale, preferences: VolunteerPreferences) -> Self {
+        let channels = [
+            (preferences.notify_email, NOTIFY_EMAIL_MESSAGE),
+            (preferences.notify_sms, NOTIFY_SMS_MESSAGE),
+        ]
+        .into_iter()
+        .filter(|(chosen, _)| *chosen)
+        .map(|(_, channel)| locale.text(channel))
+        .collect();
+        Self {
+            email: preferences.email,
+            channels,
+            language: preferences
+                .language
+                .as_deref()
+                .and_then(Locale::from_code)
+                .map(Locale::name),
+        }
+    }
+}
+
+#[get("/<card_id>")]
+pub(crate) async fn show_profile(
+    card_id: i16,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let Some(volunteer) = query_error(
+        query_volunteer_profile(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    else {
+        return Err(InternalError::unknown_volunteer(uri));
+    };
+
+    let tasks = Tasks::new(query_error(query_tasks(&state.pool, organisation.id), uri).await?);
+    let locations = query_error(Locations::load(&state.pool, organisation.id), uri).await?;
+    let today = Date::current(state.timezone).naive_date();
+
+    let upcoming = query_error(
+        query_shifts(&state.pool, organisation.id, card_id, today),
+        uri,
+    )
+    .await?;
+    let past = query_error(
+        query_past_shifts(&state.pool, organisation.id, card_id, today, PAST_SHIFTS),
+        uri,
+    )
+    .await?;
+
+    // Skills are the tasks the volunteer has already served
+    let skills: Vec<ProfileSkill> = query_error(
+        query_served_tasks(&state.pool, organisation.id, card_id, today),
+        uri,
+    )
+    .await?
+    .into_iter()
+    .map(|served| ProfileSkill {
+        task: tasks.task_from_id(served.task),
+        shifts: served.shifts,
+    })
+    .collect();
+
+    let notes: Vec<ProfileNote> = query_error(
+        query_volunteer_notes(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    .into_iter()
+    .map(|note| ProfileNote {
+        author: note.author,
+        text: note.text,
+        created_at: format_date_time(state.timezone, note.created_at),
+        delete_route: volunteers_uri!(delete_note(card_id, note.id)),
+    })
+    .collect();
+
+    // Tell which values differ from the volunteers file
+    let local = query_error(
+        query_volunteer_override(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?;
+
+    let preferences = query_error(
+        query_volunteer_preferences(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?;
+
+    // Data changes asked by the volunteer are handled in their own page
+    let change_request_route = query_error(
+        query_change_request(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?
+    .map(|_| {
+        rocket::uri!(
+            ADMINISTRATION_ROUTE,
+            crate::change_requests::show_change_requests
+        )
+    });
+
+    // Phone numbers used by links cannot contain spaces
+    let phone_link: String = volunteer
+        .phone_number
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+
+    Ok(Template::render(
+        "volunteer_profile",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            volunteer: &volunteer.volunteer,
+            disabled_message: locale.text(DISABLED_MESSAGE),
+            local_data_message: local.is_some().then(|| locale.text(LOCAL_DATA_MESSAGE)),
+            contacts_message: locale.text(CONTACTS_MESSAGE),
+            phone_number: &volunteer.phone_number,
+            phone_link,
+            call_message: locale.text(CALL_MESSAGE),
+            send_message: locale.text(SEND_MESSAGE),
+            email_message: locale.text(EMAIL_MESSAGE),
+            channels_message: locale.text(CHANNELS_MESSAGE),
+            no_channels_message: locale.text(NO_CHANNELS_MESSAGE),
+            language_message: locale.text(LANGUAGE_MESSAGE),
+            preferences: ProfilePreferences::new(locale, preferences),
+            change_request_message: locale.text(CHANGE_REQUEST_MESSAGE),
+            change_request_route,
+            actions_message: locale.text(ACTIONS_MESSAGE),
+            book_route: shifts_manager_uri!(crate::shifts_manager::show_shifts_manager(card_id)),
+            book_message: locale.text(BOOK_MESSAGE),
+            cancel_route: shifts_uri!(crate::shifts::show_shifts(card_id)),
+            cancel_message: locale.text(CANCEL_MESSAGE),
+            certificate_route: shifts_uri!(crate::certificate::show_certificate(card_id)),
+            certificate_message: locale.text(CERTIFICATE_MESSAGE),
+            invitation_route: volunteers_uri!(crate::login::invitation::show_invitations),
+            invitation_message: locale.text(INVITATION_MESSAGE),
+            single_use_message: locale.text(SINGLE_USE_MESSAGE),
+            time_limited_message: locale.format(
+                TIME_LIMITED_MESSAGE,
+                &[("days", INVITATION_DAYS.into())],
+            ),
+            edit_message: locale.text(EDIT_MESSAGE),
+            edit_route: volunteers_uri!(edit_volunteer(card_id)),
+            surname_message: locale.text(SURNAME_MESSAGE),
+            name_message: locale.text(NAME_MESSAGE),
+            phone_message: locale.text(PHONE_MESSAGE),
+            disabled_field_message: locale.text(DISABLED_FIELD_MESSAGE),
+            save_message: locale.text(SAVE_MESSAGE),
+            source: ProfileSource::new(locale, local.as_ref()),
+            restore_message: local.is_some().then(|| locale.text(RESTORE_MESSAGE)),
+            upcoming_message: locale.text(UPCOMING_MESSAGE),
+            upcoming: ProfileShift::shifts(locale, &tasks, &locations, upcoming),
+            past_message: locale.text(PAST_MESSAGE),
+            past: ProfileShift::shifts(locale, &tasks, &locations, past),
+            no_shifts_message: locale.text(NO_SHIFTS_MESSAGE),
+            // The location is shown only when there are more of them
+            show_location: locations.render().len() > 1,
+            skills_message: locale.text(SKILLS_MESSAGE),
+            skills,
+            no_skills_message: locale.text(NO_SKILLS_MESSAGE),
+            notes_message: locale.text(NOTES_MESSAGE),
+            notes_private_message: locale.text(NOTES_PRIVATE_MESSAGE),
+            note_route: volunteers_uri!(add_note(card_id)),
+            add_note_message: locale.text(ADD_NOTE_MESSAGE),
+            delete_note_message: locale.text(DELETE_NOTE_MESSAGE),
+            no_notes_message: locale.text(NO_NOTES_MESSAGE),
+            notes,
+        },
+    ))
+}
+
+// Note written by a referent
+#[derive(FromForm)]
+struct NewNote<'r> {
+    #[field(validate = len(..=2000))]
+    text: &'r str,
+}
+
+// Ranked after the invitation route, which has the same shape
+#[post("/<card_id>/note", data = "<note_form>", rank = 2)]
+async fn add_note(
+    card_id: i16,
+    note_form: Form<NewNote<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    let session = check_administration_access(&session, uri)?;
+
+    // Empty notes are ignored
+    let text = note_form.text.trim();
+    if text.is_empty() {
+        return Ok(Redirect::to(volunteers_uri!(show_profile(card_id))));
+    }
+
+    // The author is the referent who is authenticated
+    let author = query_error(
+        query_volunteer_surname_name(&state.pool, organisation.id, session.card_id),
+        uri,
+    )
+    .await?;
+    query_error(
+        insert_volunteer_note(&state.pool, organisation.id, card_id, &author, text),
+        uri,
+    )
+    .await?;
+
+    Ok(Redirect::to(volunteers_uri!(show_profile(card_id))))
+}
+
+#[delete("/<card_id>/note/<id>")]
+async fn delete_note(
+    card_id: i16,
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(
+        delete_volunteer_note(&state.pool, organisation.id, id, card_id),
+        uri,
+    )
+    .await?;
+
+    Ok(Redirect::to(volunteers_uri!(show_profile(card_id))))
+}
+
+// Notify webhooks and visualizer pages about a changed volunteer
+pub(crate) async fn notify_volunteer(state: &AppState, organisation_id: i32, card_id: i16) {
+    state.sender.reload(organisation_id);
+    send_event(
+        &state.pool,
+        organisation_id,
+        &WebhookEvent::VolunteersUpdated(VolunteersChanges {
+            updated: vec![card_id],
+            ..Default::default()
+        }),
+    )
+    .await;
+}
+
+// Volunteer data changed by a referent
+#[derive(FromForm)]
+struct VolunteerData<'r> {
+    #[field(validate = len(1..=50))]
+    surname: &'r str,
+    #[field(validate = len(1..=60))]
+    name: &'r str,
+    #[field(validate = len(..=20))]
+    phone_number: &'r str,
+    disabled: bool,
+}
+
+#[put("/<card_id>/dati", data = "<data_form>")]
+async fn edit_volunteer(
+    card_id: i16,
+    data_form: Form<VolunteerData<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Values equal to the ones of the volunteers file are not kept as
+    // local changes
+    let volunteer = VolunteerManager {
+        volunteer: Volunteer {
+            card_id,
+            surname: data_form.surname.trim().to_string(),
+            name: data_form.name.trim().to_string(),
+            disabled: data_form.disabled,
+        },
+        phone_number: data_form.phone_number.trim().to_string(),
+    };
+    query_error(
+        set_volunteer_override(&state.pool, organisation.id, &volunteer),
+        uri,
+    )
+    .await?;
+
+    notify_volunteer(state, organisation.id, card_id).await;
+
+    Ok(Redirect::to(volunteers_uri!(show_profile(card_id))))
+}
+
+// Ranked after the invitation revoking route, which has the same shape
+#[delete("/<card_id>/dati", rank = 2)]
+async fn restore_volunteer(
+    card_id: i16,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(
+        delete_volunteer_override(&state.pool, organisation.id, card_id),
+        uri,
+    )
+    .await?;
+
+    notify_volunteer(state, organisation.id, card_id).await;
+
+    Ok(Redirect::to(volunteers_uri!(show_profile(card_id))))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![
+        show_profile,
+        add_note,
+        delete_note,
+        edit_volunteer,
+        restore_volunteer
+    ]
+}
@@ -1,20 +1,33 @@
 use rocket::http::uri::Origin;
-use rocket::http::CookieJar;
 use rocket::response::Redirect;
 use rocket::State;
 use rocket_dyn_templates::{context, Template};
 
-use crate::database::{delete_old_shifts, query_volunteers, refill_volunteers_table};
+use serde::Serialize;
+
+use crate::database::{
+    insert_db_date, query_searched_volunteers_count, refill_volunteers_table, search_volunteers,
+    Organisation, VolunteersSearch,
+};
 use crate::error::{query_error, InternalError};
-use crate::login::ADMINISTRATION_COOKIE;
+use crate::locale::Locale;
+use crate::login::session::{check_administration_access, Session};
 use crate::menu::MenuAdministration;
-use crate::{
-    AppState, APP_TITLE, DISABLED_MESSAGE, SHIFTS_MANAGER_ROUTE, SHIFTS_ROUTE, VOLUNTEERS_ROUTE,
-};
+use crate::time::{Date, WORKING_DAYS};
+use crate::webhooks::{send_event, WebhookEvent};
+use crate::{AppState, DISABLED_MESSAGE, VOLUNTEERS_ROUTE};
 
-const SHIFTS_MANAGER_MESSAGE: &str = "Inserisci turni";
-const SHIFTS_MESSAGE: &str = "Cancella turni";
-const UPDATE_VOLUNTEER_MESSAGE: &str = "Aggiorna volontari";
+const UPDATE_VOLUNTEER_MESSAGE: &str = "volunteers-update";
+const PROFILE_MESSAGE: &str = "volunteers-profile";
+const SEARCH_MESSAGE: &str = "volunteers-search";
+const FILTER_MESSAGE: &str = "volunteers-filter";
+const FOUND_MESSAGE: &str = "volunteers-found";
+const EMPTY_MESSAGE: &str = "volunteers-empty";
+const PREVIOUS_MESSAGE: &str = "volunteers-previous";
+const NEXT_MESSAGE: &str = "volunteers-next";
+
+// Number of volunteers shown in a page
+const PAGE_SIZE: i64 = 24;
 
 // Route to volunteers page
 macro_rules! volunteers_uri {
@@ -23,50 +36,254 @@ macro_rules! volunteers_uri {
 
 #[put("/updatevolunteers")]
 pub(crate) async fn update_volunteers(
+    session: Option<Session>,
     state: &State<AppState>,
+    organisation: &Organisation,
     uri: &Origin<'_>,
 ) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
     // Update volunteers retrieving their data from the csv file
-    query_error(
-        refill_volunteers_table(&state.pool, &state.volunteers_url),
+    let changes = query_error(
+        refill_volunteers_table(&state.pool, organisation.id, &organisation.volunteers_url),
         uri,
     )
     .await?;
 
+    // Notify webhooks and visualizer pages about the changed volunteers
+    if !changes.added.is_empty() || !changes.updated.is_empty() {
+        state.sender.reload(organisation.id);
+        send_event(
+            &state.pool,
+            organisation.id,
+            &WebhookEvent::VolunteersUpdated(changes),
+        )
+        .await;
+    }
+
     // Redirect to volunteers page
-    Ok(Redirect::to(volunteers_uri!(show_volunteers)))
+    Ok(Redirect::to(volunteers_uri!(show_volunteers(_))))
+}
+
+// State of the volunteers shown in the page
+#[derive(FromFormField, Default, Clone, Copy, PartialEq)]
+enum VolunteersStatus {
+    #[default]
+    All,
+    Enabled,
+    Disabled,
 }
 
-#[get("/")]
+impl VolunteersStatus {
+    const CHOICES: [(Self, &'static str, &'static str); 3] = [
+        (Self::All, "all", "volunteers-status-all"),
+        (Self::Enabled, "enabled", "volunteers-status-enabled"),
+        (Self::Disabled, "disabled", "volunteers-status-disabled"),
+    ];
+
+    fn disabled(self) -> Option<bool> {
+        match self {
+            Self::All => None,
+            Self::Enabled => Some(false),
+            Self::Disabled => Some(true),
+        }
+    }
+}
+
+// Shifts booked by the volunteers shown in the page during the current week
+#[derive(FromFormField, Default, Clone, Copy, PartialEq)]
+enum VolunteersShifts {
+    #[default]
+    All,
+    With,
+    Without,
+}
+
+impl VolunteersShifts {
+    const CHOICES: [(Self, &'static str, &'static str); 3] = [
+        (Self::All, "all", "volunteers-shifts-all"),
+        (Self::With, "with", "volunteers-shifts-with"),
+        (Self::Without, "without", "volunteers-shifts-without"),
+    ];
+
+    fn has_shifts(self) -> Option<bool> {
+        match self {
+            Self::All => None,
+            Self::With => Some(true),
+            Self::Without => Some(false),
+        }
+    }
+}
+
+// Order of the volunteers shown in the page
+#[derive(FromFormField, Default, Clone, Copy, PartialEq)]
+enum VolunteersOrder {
+    #[default]
+    Card,
+    Surname,
+    Name,
+}
+
+impl VolunteersOrder {
+    const CHOICES: [(Self, &'static str, &'static str); 3] = [
+        (Self::Card, "card", "volunteers-sort-card"),
+        (Self::Surname, "surname", "volunteers-sort-surname"),
+        (Self::Name, "name", "volunteers-sort-name"),
+    ];
+
+    fn column(self) -> &'static str {
+        match self {
+            Self::Card => "card_id",
+            Self::Surname => "surname",
+            Self::Name => "name",
+        }
+    }
+}
+
+// Option of a filter select
+#[derive(Serialize)]
+struct FilterOption {
+    // Value sent by the form
+    value: &'static str,
+    // Option text
+    text: String,
+    // Whether the option is selected
+    is_selected: Option<&'static str>,
+}
+
+// Options of a filter select, where the current choice is selected
+fn filter_options<T: PartialEq + Copy>(
+    locale: Locale,
+    choices: &[(T, &'static str, &'static str)],
+    current: T,
+) -> Vec<FilterOption> {
+    choices
+        .iter()
+        .map(|(choice, value, id)| FilterOption {
+            value,
+            text: locale.text(id),
+            is_selected: (*choice == current).then_some("selected"),
+        })
+        .collect()
+}
+
+// Link to a page of the volunteers, or a gap between pages when missing
+#[derive(Serialize)]
+struct PageLink {
+    // Page number
+    number: Option<i64>,
+    // Whether it is the current page
+    is_current: bool,
+}
+
+// Links to the first and last pages and to the ones near the current page
+fn page_links(page: i64, pages: i64) -> Vec<PageLink> {
+    let mut links = Vec::new();
+    for number in 1..=pages {
+        if number == 1 || number == pages || (number - page).abs() <= 2 {
+            links.push(PageLink {
+                number: Some(number),
+                is_current: number == page,
+            });
+        } else if links.last().is_some_and(|link| link.number.is_some()) {
+            links.push(PageLink {
+                number: None,
+                is_current: false,
+            });
+        }
+    }
+    links
+}
+
+// Search, filters, order and page chosen in the volunteers page
+#[derive(FromForm, Default)]
+pub(crate) struct VolunteersFilter<'r> {
+    search: Option<&'r str>,
+    status: Option<VolunteersStatus>,
+    shifts: Option<VolunteersShifts>,
+    sort: Option<VolunteersOrder>,
+    page: Option<i64>,
+}
+
+#[get("/?<filter..>")]
 pub(crate) async fn show_volunteers(
+    filter: Option<VolunteersFilter<'_>>,
+    session: Option<Session>,
     state: &State<AppState>,
-    jar: &CookieJar<'_>,
+    organisation: &Organisation,
     uri: &Origin<'_>,
+    locale: Locale,
 ) -> Result<Template, InternalError> {
     // Check if the administrator is authenticated
-    if jar.get_private(ADMINISTRATION_COOKIE).is_none() {
-        return Err(InternalError::not_authenticated_administrator(uri));
-    }
+    check_administration_access(&session, uri)?;
+
+    let filter = filter.unwrap_or_default();
+    let status = filter.status.unwrap_or_default();
+    let shifts = filter.shifts.unwrap_or_default();
+    let order = filter.sort.unwrap_or_default();
+    let text = filter.search.unwrap_or_default().trim();
 
-    // Get all volunteers saved in database
-    let volunteers = query_error(query_volunteers(&state.pool), uri).await?;
+    // Shifts are checked inside the current week, from Monday to Saturday
+    let current_date = Date::current(state.timezone);
+    let week = (
+        insert_db_date(current_date.day_date(0)).unwrap_or_default(),
+        insert_db_date(current_date.day_date(WORKING_DAYS - 1)).unwrap_or_default(),
+    );
+    let search = VolunteersSearch {
+        text,
+        disabled: status.disabled(),
+        has_shifts: shifts.has_shifts(),
+        period: week,
+        order: order.column(),
+    };
 
-    // Remove all volunteers shifts in the database which are older than current date
-    query_error(delete_old_shifts(&state.pool), uri).await?;
+    // Pages going beyond the last one show the last volunteers
+    let count = query_error(
+        query_searched_volunteers_count(&state.pool, organisation.id, &search),
+        uri,
+    )
+    .await?;
+    let pages = ((count + PAGE_SIZE - 1) / PAGE_SIZE).max(1);
+    let page = filter.page.unwrap_or(1).clamp(1, pages);
+    let volunteers = query_error(
+        search_volunteers(
+            &state.pool,
+            organisation.id,
+            &search,
+            PAGE_SIZE,
+            (page - 1) * PAGE_SIZE,
+        ),
+        uri,
+    )
+    .await?;
 
     Ok(Template::render(
         "volunteers",
         context! {
-            title: APP_TITLE,
-            menu_administration: MenuAdministration::render(),
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            filter_route: volunteers_uri!(show_volunteers(_)),
+            search_message: locale.text(SEARCH_MESSAGE),
+            search: text,
+            status_options: filter_options(locale, &VolunteersStatus::CHOICES, status),
+            shifts_options: filter_options(locale, &VolunteersShifts::CHOICES, shifts),
+            sort_options: filter_options(locale, &VolunteersOrder::CHOICES, order),
+            filter_message: locale.text(FILTER_MESSAGE),
+            found_message: locale.format(FOUND_MESSAGE, &[("count", count.into())]),
+            empty_message: locale.text(EMPTY_MESSAGE),
             volunteers,
-            disabled_message: DISABLED_MESSAGE,
-            shifts_manager_route: SHIFTS_MANAGER_ROUTE,
-            shifts_manager_message: SHIFTS_MANAGER_MESSAGE,
-            shifts_route: SHIFTS_ROUTE,
-            shifts_message: SHIFTS_MESSAGE,
+            pages: (pages > 1).then(|| page_links(page, pages)),
+            previous_page: (page > 1).then_some(page - 1),
+            next_page: (page < pages).then_some(page + 1),
+            previous_message: locale.text(PREVIOUS_MESSAGE),
+            next_message: locale.text(NEXT_MESSAGE),
+            disabled_message: locale.text(DISABLED_MESSAGE),
+            profile_route: VOLUNTEERS_ROUTE,
+            profile_message: locale.text(PROFILE_MESSAGE),
             volunteer_route: uri!(update_volunteers),
-            update_volunteer_message: UPDATE_VOLUNTEER_MESSAGE,
+            update_volunteer_message: locale.text(UPDATE_VOLUNTEER_MESSAGE),
         },
     ))
 }
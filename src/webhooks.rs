@@ -0,0 +1,444 @@
+use anyhow::Result;
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, Url};
+
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::uri::Origin;
+use rocket::response::Redirect;
+use rocket::serde::json;
+use rocket::tokio::time::interval;
+use rocket::State;
+use rocket_dyn_templates::{context, Template};
+
+use serde::Serialize;
+
+use sqlx::PgPool;
+
+use crate::api::ShiftRecord;
+use crate::database::{
+    delete_old_deliveries, delete_webhook, insert_webhook, insert_webhook_deliveries,
+    query_deliveries, query_pending_deliveries, query_webhooks, retry_delivery, update_delivery,
+    Delivery, DeliveryUpdate, Organisation, PendingDelivery, VolunteersChanges, Webhook,
+};
+use crate::error::{query_error, InternalError};
+use crate::locale::Locale;
+use crate::login::administration::administration_uri;
+use crate::login::session::{check_administration_access, Session};
+use crate::menu::MenuAdministration;
+use crate::time::format_date_time;
+use crate::token::{random_token, sign_message};
+use crate::{AppState, ADMINISTRATION_ROUTE};
+
+// Deliveries status
+const PENDING_STATUS: &str = "pending";
+const DELIVERED_STATUS: &str = "delivered";
+const FAILED_STATUS: &str = "failed";
+
+// Headers sent together with an event
+const EVENT_HEADER: &str = "X-Webhook-Event";
+const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+// Seconds between two checks of the deliveries to be sent
+const DELIVERY_INTERVAL_SECONDS: u64 = 10;
+// Maximum number of deliveries sent at each check
+const DELIVERIES_PER_CHECK: i64 = 20;
+// Seconds to wait for a webhook response
+const REQUEST_TIMEOUT_SECONDS: u64 = 10;
+// Attempts made before a delivery fails, the waiting time between two
+// attempts starts from one minute and doubles at each failure
+const MAX_ATTEMPTS: i32 = 6;
+// Maximum length of a saved response
+const RESPONSE_LENGTH: usize = 300;
+
+// Days completed deliveries are kept in the log for
+const LOG_DAYS: i64 = 30;
+// Hours between two cleanups of the log
+const LOG_CLEANUP_HOURS: u64 = 24;
+// Number of deliveries shown in the log
+const LOG_SIZE: i64 = 100;
+
+// Messages
+const HEADING_MESSAGE: &str = "webhooks-heading";
+const EMPTY_WEBHOOKS_MESSAGE: &str = "webhooks-empty";
+const URL_MESSAGE: &str = "webhooks-url";
+const CREATE_MESSAGE: &str = "webhooks-create";
+const SECRET_MESSAGE: &str = "webhooks-secret";
+const DELETE_MESSAGE: &str = "webhooks-delete";
+const LOG_MESSAGE: &str = "webhooks-log";
+const EMPTY_LOG_MESSAGE: &str = "webhooks-log-empty";
+const RETRY_MESSAGE: &str = "webhooks-retry";
+const PENDING_MESSAGE: &str = "webhooks-pending";
+const DELIVERED_MESSAGE: &str = "webhooks-delivered";
+const FAILED_MESSAGE: &str = "webhooks-failed";
+
+// Events sent to webhooks
+#[derive(Serialize)]
+#[serde(tag = "event", content = "data")]
+pub(crate) enum WebhookEvent {
+    // A shift has been booked
+    #[serde(rename = "shift.created")]
+    ShiftCreated(ShiftRecord),
+    // A shift has been cancelled
+    #[serde(rename = "shift.deleted")]
+    ShiftDeleted(ShiftRecord),
+    // Volunteers have been added or changed by an update of the volunteers
+    // table
+    #[serde(rename = "volunteers.updated")]
+    VolunteersUpdated(VolunteersChanges),
+}
+
+impl WebhookEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ShiftCreated(_) => "shift.created",
+            Self::ShiftDeleted(_) => "shift.deleted",
+            Self::VolunteersUpdated(_) => "volunteers.updated",
+        }
+    }
+}
+
+// JSON body sent to webhooks
+#[derive(Serialize)]
+struct Payload<'a> {
+    // Event name and data
+    #[serde(flatten)]
+    event: &'a WebhookEvent,
+    // Event date and time
+    created_at: DateTime<Utc>,
+}
+
+// Queue an event for every webhook of an organisation, it is delivered in
+// background. The event data are already saved when it is queued, so a
+// failure is logged without failing the request.
+pub(crate) async fn send_event(pool: &PgPool, organisation_id: i32, event: &WebhookEvent) {
+    if let Err(e) = queue_event(pool, organisation_id, event).await {
+        error!("Error queueing webhooks event {}: {e}", event.name());
+    }
+}
+
+// Save the deliveries of an event for every webhook of an organisation
+async fn queue_event(pool: &PgPool, organisation_id: i32, event: &WebhookEvent) -> Result<()> {
+    let payload = json::to_string(&Payload {
+        event,
+        created_at: Utc::now(),
+    })?;
+    insert_webhook_deliveries(
+        pool,
+        organisation_id,
+        event.name(),
+        &payload,
+        PENDING_STATUS,
+    )
+    .await
+}
+
+// Send an event to a webhook, returning whether it has been accepted and
+// the response status or the error
+async fn deliver(client: &Client, delivery: &PendingDelivery) -> (bool, String) {
+    let signature = match sign_message(&delivery.secret, &delivery.payload) {
+        Ok(signature) => signature,
+        Err(e) => return (false, e.to_string()),
+    };
+
+    let response = client
+        .post(&delivery.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, delivery.id)
+        .header(SIGNATURE_HEADER, format!("sha256={signature}"))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match response {
+        Ok(response) => (
+            response.status().is_success(),
+            response.status().to_string(),
+        ),
+        Err(e) => (false, e.to_string()),
+    }
+}
+
+// Send the deliveries whose next attempt is due
+async fn deliver_pending(pool: &PgPool, client: &Client) -> Result<()> {
+    for delivery in query_pending_deliveries(pool, PENDING_STATUS, DELIVERIES_PER_CHECK).await? {
+        let (delivered, response) = deliver(client, &delivery).await;
+        let attempts = delivery.attempts + 1;
+        let status = if delivered {
+            DELIVERED_STATUS
+        } else if attempts >= MAX_ATTEMPTS {
+            FAILED_STATUS
+        } else {
+            PENDING_STATUS
+        };
+
+        update_delivery(
+            pool,
+            &DeliveryUpdate {
+                id: delivery.id,
+                status: status.to_string(),
+                attempts,
+                response: Some(response.chars().take(RESPONSE_LENGTH).collect()),
+                next_attempt: Utc::now() + Duration::minutes(1_i64 << (attempts - 1)),
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+// Fairing which delivers events to webhooks once the server is started
+pub(crate) fn delivery_job() -> AdHoc {
+    AdHoc::on_liftoff("Webhooks delivery", |rocket| {
+        Box::pin(async move {
+            let Some(state) = rocket.state::<AppState>() else {
+                return;
+            };
+            let pool = state.pool.clone();
+            let client = match Client::builder()
+                .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECONDS))
+                .build()
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    error!("Error creating webhooks client: {e}");
+                    return;
+                }
+            };
+            rocket::tokio::spawn(async move {
+                let mut deliveries =
+                    interval(std::time::Duration::from_secs(DELIVERY_INTERVAL_SECONDS));
+                let mut cleanup =
+                    interval(std::time::Duration::from_secs(LOG_CLEANUP_HOURS * 3600));
+                loop {
+                    rocket::tokio::select! {
+                        _ = deliveries.tick() => {
+                            if let Err(e) = deliver_pending(&pool, &client).await {
+                                error!("Error delivering webhooks events: {e}");
+                            }
+                        }
+                        _ = cleanup.tick() => {
+                            let before = Utc::now() - Duration::days(LOG_DAYS);
+                            if let Err(e) = delete_old_deliveries(&pool, before, PENDING_STATUS).await {
+                                error!("Error deleting old webhooks deliveries: {e}");
+                            }
+                        }
+                    }
+                }
+            });
+        })
+    })
+}
+
+// Webhook and the relative route to delete it
+#[derive(Serialize)]
+struct WebhookView {
+    // Address receiving the events
+    url: String,
+    // Secret used to sign the events
+    secret: String,
+    // Creation date and time
+    created_at: String,
+    // Route to delete the webhook
+    delete_route: Origin<'static>,
+}
+
+impl WebhookView {
+    fn webhooks(timezone: Tz, webhooks: Vec<Webhook>) -> Vec<Self> {
+        webhooks
+            .into_iter()
+            .map(|webhook| Self {
+                url: webhook.url,
+                secret: webhook.secret,
+                created_at: format_date_time(timezone, webhook.created_at),
+                delete_route: administration_uri!(remove_webhook(webhook.id)),
+            })
+            .collect()
+    }
+}
+
+// Delivery shown in the log
+#[derive(Serialize)]
+struct DeliveryView {
+    // Address receiving the event
+    url: String,
+    // Event name
+    event: String,
+    // Delivery status
+    status: String,
+    // Bulma color of the delivery status
+    color: &'static str,
+    // Number of attempts made
+    attempts: i32,
+    // Response status or error of the last attempt
+    response: String,
+    // Creation date and time
+    created_at: String,
+    // Date and time of the last attempt
+    last_attempt: String,
+    // Route to retry a failed delivery
+    retry_route: Option<Origin<'static>>,
+}
+
+impl DeliveryView {
+    fn deliveries(locale: Locale, timezone: Tz, deliveries: Vec<Delivery>) -> Vec<Self> {
+        deliveries
+            .into_iter()
+            .map(|delivery| {
+                let (status, color) = match delivery.status.as_str() {
+                    DELIVERED_STATUS => (DELIVERED_MESSAGE, "is-success"),
+                    FAILED_STATUS => (FAILED_MESSAGE, "is-danger"),
+                    _ => (PENDING_MESSAGE, "is-warning"),
+                };
+                Self {
+                    url: delivery.url,
+                    event: delivery.event,
+                    status: locale.text(status),
+                    color,
+                    attempts: delivery.attempts,
+                    response: delivery.response.unwrap_or_default(),
+                    created_at: format_date_time(timezone, delivery.created_at),
+                    last_attempt: delivery
+                        .last_attempt
+                        .map(|date_time| format_date_time(timezone, date_time))
+                        .unwrap_or_default(),
+                    retry_route: (delivery.status == FAILED_STATUS)
+                        .then(|| administration_uri!(retry_webhook_delivery(delivery.id))),
+                }
+            })
+            .collect()
+    }
+}
+
+#[get("/webhook")]
+pub(crate) async fn show_webhooks(
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+    locale: Locale,
+) -> Result<Template, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    let webhooks = WebhookView::webhooks(
+        state.timezone,
+        query_error(query_webhooks(&state.pool, organisation.id), uri).await?,
+    );
+    let deliveries = DeliveryView::deliveries(
+        locale,
+        state.timezone,
+        query_error(
+            query_deliveries(&state.pool, organisation.id, LOG_SIZE),
+            uri,
+        )
+        .await?,
+    );
+
+    Ok(Template::render(
+        "webhooks",
+        context! {
+            lang: locale.code(),
+            title: &organisation.name,
+            menu_administration: MenuAdministration::render(locale, organisation),
+            heading_message: locale.text(HEADING_MESSAGE),
+            no_webhooks_message: webhooks.is_empty().then(|| locale.text(EMPTY_WEBHOOKS_MESSAGE)),
+            webhooks,
+            create_route: administration_uri!(create_webhook),
+            url_message: locale.text(URL_MESSAGE),
+            create_message: locale.text(CREATE_MESSAGE),
+            secret_message: locale.text(SECRET_MESSAGE),
+            delete_message: locale.text(DELETE_MESSAGE),
+            log_message: locale.text(LOG_MESSAGE),
+            no_deliveries_message: deliveries.is_empty().then(|| locale.text(EMPTY_LOG_MESSAGE)),
+            deliveries,
+            retry_message: locale.text(RETRY_MESSAGE),
+        },
+    ))
+}
+
+#[derive(FromForm)]
+struct NewWebhook<'r> {
+    #[field(validate = len(1..=300))]
+    url: &'r str,
+}
+
+#[post("/webhook", data = "<webhook_form>")]
+async fn create_webhook(
+    webhook_form: Form<NewWebhook<'_>>,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    // Only web addresses can receive events
+    let url = webhook_form.url.trim();
+    if !Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return Err(InternalError::text(uri, "webhooks-invalid-url"));
+    }
+
+    query_error(
+        insert_webhook(&state.pool, organisation.id, url, &random_token()),
+        uri,
+    )
+    .await?;
+
+    // Redirect to webhooks page
+    Ok(Redirect::to(administration_uri!(show_webhooks)))
+}
+
+#[delete("/webhook/<id>")]
+async fn remove_webhook(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(delete_webhook(&state.pool, organisation.id, id), uri).await?;
+
+    // Redirect to webhooks page
+    Ok(Redirect::to(administration_uri!(show_webhooks)))
+}
+
+#[put("/webhook/consegne/<id>")]
+async fn retry_webhook_delivery(
+    id: i32,
+    session: Option<Session>,
+    state: &State<AppState>,
+    organisation: &Organisation,
+    uri: &Origin<'_>,
+) -> Result<Redirect, InternalError> {
+    // Check if the administrator is authenticated
+    check_administration_access(&session, uri)?;
+
+    query_error(
+        retry_delivery(&state.pool, organisation.id, id, PENDING_STATUS),
+        uri,
+    )
+    .await?;
+
+    // Redirect to webhooks page
+    Ok(Redirect::to(administration_uri!(show_webhooks)))
+}
+
+pub(crate) fn routes() -> Vec<rocket::Route> {
+    routes![
+        show_webhooks,
+        create_webhook,
+        remove_webhook,
+        retry_webhook_delivery
+    ]
+}